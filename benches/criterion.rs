@@ -1,6 +1,10 @@
+use bls::basic_bls::{BLSSignature, PreparedVerifier};
+use bls::threshold_bls::state_machine::keygen::{Keygen, LocalKey};
+use bls::threshold_bls::state_machine::sign::{Sign, SignedMessage};
 use bls::threshold_bls::test::{keygen_t_n_parties, sign};
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion, SamplingMode};
+use round_based::dev::Simulation;
 
 pub fn threshold_bls(c: &mut Criterion) {
     // Configure benchmarks
@@ -30,5 +34,153 @@ pub fn threshold_bls(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, threshold_bls);
+/// Parameters `(t, n)` the state machines are benchmarked at
+const STATE_MACHINE_PARAMS: &[(u16, u16)] = &[(1, 2), (2, 3), (3, 5)];
+
+/// Measures protocols via state machines, i.e. the code path users actually run, including
+/// message stores overhead
+pub fn state_machine(c: &mut Criterion) {
+    let mut g = c.benchmark_group("bls-mpc-state-machine");
+    g.sampling_mode(SamplingMode::Flat);
+    g.sample_size(10);
+
+    for &(t, n) in STATE_MACHINE_PARAMS {
+        g.bench_function(format!("keygen t={} n={}", t, n), |b| {
+            b.iter(|| black_box(simulate_keygen(t, n)))
+        });
+    }
+
+    let data_to_sign = b"Hello threshold World";
+    for &(t, n) in STATE_MACHINE_PARAMS {
+        let keys = simulate_keygen(t, n);
+        let signers = &keys[..usize::from(t) + 1];
+        g.bench_function(format!("sign t={} n={}", t, n), |b| {
+            b.iter(|| black_box(simulate_sign(data_to_sign, signers)))
+        });
+    }
+}
+
+fn simulate_keygen(t: u16, n: u16) -> Vec<LocalKey> {
+    let mut simulation = Simulation::new();
+    for i in 1..=n {
+        simulation.add_party(Keygen::new(i, t, n).unwrap());
+    }
+    simulation.run().unwrap()
+}
+
+fn simulate_sign(message: &[u8], keys: &[LocalKey]) -> SignedMessage {
+    let mut simulation = Simulation::new();
+    let n = keys.len() as u16;
+    for (i, key) in (1..).zip(keys) {
+        simulation.add_party(Sign::new(message.to_vec(), i, n, key.clone()).unwrap());
+    }
+    simulation.run().unwrap()[0]
+}
+
+/// Numbers of parties VSS shares validation is benchmarked at, threshold is `n/2`
+const VSS_VALIDATION_PARAMS: &[usize] = &[16, 32];
+
+/// Measures keygen of large groups, which is dominated by validation of received VSS shares
+pub fn vss_validation(c: &mut Criterion) {
+    use bls::threshold_bls::party_i::{find_invalid_shares, Keys};
+    use curv::cryptographic_primitives::secret_sharing::feldman_vss::{
+        ShamirSecretSharing, VerifiableSS,
+    };
+
+    let mut g = c.benchmark_group("bls-mpc-vss");
+    g.sampling_mode(SamplingMode::Flat);
+    g.sample_size(10);
+
+    for &n in VSS_VALIDATION_PARAMS {
+        g.bench_function(format!("keygen t={} n={}", n / 2, n), |b| {
+            b.iter(|| black_box(keygen_t_n_parties(n / 2, n)))
+        });
+    }
+
+    // Shares received by a single party: validated one by one, and at once as keygen does
+    for &n in VSS_VALIDATION_PARAMS {
+        let params = ShamirSecretSharing {
+            threshold: n / 2,
+            share_count: n,
+        };
+        let party_keys: Vec<_> = (0..n).map(Keys::phase1_create).collect();
+        let y_vec: Vec<_> = party_keys.iter().map(|k| k.y_i).collect();
+        let (vss_vec, shares): (Vec<_>, Vec<_>) = party_keys
+            .iter()
+            .map(|k| {
+                let (vss, shares) = VerifiableSS::share(params.threshold, n, &k.u_i);
+                (vss, shares[0])
+            })
+            .unzip();
+
+        g.bench_function(format!("validate shares one by one n={}", n), |b| {
+            b.iter(|| black_box(find_invalid_shares(&shares, &vss_vec, 1)))
+        });
+        g.bench_function(format!("validate shares batched n={}", n), |b| {
+            b.iter(|| {
+                party_keys[0]
+                    .phase2_verify_vss_construct_keypair_prove_dlog(
+                        &params,
+                        &y_vec,
+                        black_box(&shares),
+                        &vss_vec,
+                        &1,
+                    )
+                    .unwrap()
+            })
+        });
+    }
+}
+
+/// Measures verification of DLog proofs sent at the last keygen round
+pub fn dlog_proofs(c: &mut Criterion) {
+    use bls::threshold_bls::party_i::Keys;
+    use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
+    use curv::cryptographic_primitives::secret_sharing::feldman_vss::ShamirSecretSharing;
+    use curv::elliptic::curves::bls12_381::g2::FE as FE2;
+    use curv::elliptic::curves::traits::ECScalar;
+
+    let mut g = c.benchmark_group("bls-mpc-dlog");
+    g.sample_size(10);
+
+    let params = ShamirSecretSharing {
+        threshold: 8,
+        share_count: 16,
+    };
+    let proofs: Vec<_> = (0..16)
+        .map(|_| DLogProof::prove(&FE2::new_random()))
+        .collect();
+    g.bench_function("verify_dlog_proofs n=16", |b| {
+        b.iter(|| Keys::verify_dlog_proofs(&params, black_box(&proofs)).unwrap())
+    });
+    g.bench_function("verify_dlog_proofs_batched n=16", |b| {
+        b.iter(|| Keys::verify_dlog_proofs_batched(&params, black_box(&proofs)).unwrap())
+    });
+}
+
+pub fn verification(c: &mut Criterion) {
+    let mut g = c.benchmark_group("bls-verify");
+
+    let keygen = keygen_t_n_parties(1, 2);
+    let public_key = keygen.0[0].vk;
+    let message = b"Hello threshold World";
+    let signature: BLSSignature = sign(message, 1, 2, &[0, 1], Some(keygen));
+    let verifier = PreparedVerifier::new(&public_key);
+
+    g.bench_function("BLSSignature::verify", |b| {
+        b.iter(|| black_box(signature.verify(message, &public_key)))
+    });
+    g.bench_function("PreparedVerifier::verify", |b| {
+        b.iter(|| black_box(verifier.verify(message, &signature)))
+    });
+}
+
+criterion_group!(
+    benches,
+    threshold_bls,
+    state_machine,
+    vss_validation,
+    dlog_proofs,
+    verification
+);
 criterion_main!(benches);
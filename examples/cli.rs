@@ -1,7 +1,7 @@
 use std::net::SocketAddr;
 use std::process::exit;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
 use structopt::StructOpt;
 use tokio::runtime;
 use tracing::{error, info};
@@ -9,7 +9,7 @@ use tracing::{error, info};
 mod cli_args;
 mod mediator;
 
-use cli_args::{App, Cmd, KeygenArgs, MediatorCmd, SignArgs, VerifyArgs};
+use cli_args::{App, Cmd, InspectArgs, KeygenArgs, MediatorCmd, SignArgs, VerifyArgs};
 
 fn main() {
     tracing_subscriber::fmt::init();
@@ -35,10 +35,17 @@ fn main() {
     // Execute requested command
     let result = runtime.block_on(async move {
         match app.command {
-            Cmd::MediatorServer(MediatorCmd::Run) => mediator_server_run(app.mediator_addr).await,
+            Cmd::MediatorServer(MediatorCmd::Run {
+                page_size,
+                require_room_token,
+            }) => mediator_server_run(app.mediator_addr, page_size, require_room_token).await,
+            Cmd::MediatorServer(MediatorCmd::CreateRoom { room_id }) => {
+                create_room(app.mediator_addr, room_id).await
+            }
             Cmd::Keygen(args) => keygen(app.mediator_addr, args).await,
             Cmd::Sign(args) => sign(app.mediator_addr, args).await,
             Cmd::Verify(args) => verify(args),
+            Cmd::Inspect(args) => inspect(args).await,
         }
     });
 
@@ -48,7 +55,11 @@ fn main() {
     }
 }
 
-async fn mediator_server_run(addr: SocketAddr) -> Result<()> {
+async fn mediator_server_run(
+    addr: SocketAddr,
+    page_size: Option<usize>,
+    require_room_token: bool,
+) -> Result<()> {
     use std::sync::Arc;
 
     use tokio::net;
@@ -56,8 +67,15 @@ async fn mediator_server_run(addr: SocketAddr) -> Result<()> {
     use tonic::transport;
 
     let incoming_clients = net::TcpListener::bind(addr).await.unwrap();
-    let mediator =
-        mediator::proto::mediator_server::MediatorServer::new(Arc::new(mediator::Server::new()));
+    let server = match page_size {
+        Some(0) => bail!("page size must be positive"),
+        Some(page_size) => mediator::Server::with_page_size(page_size),
+        None => mediator::Server::new(),
+    };
+    let server = server.require_room_token(require_room_token);
+    #[cfg(feature = "metrics")]
+    let server = server.with_metrics(Arc::new(mediator::metrics::MetricsFacade));
+    let mediator = mediator::proto::mediator_server::MediatorServer::new(Arc::new(server));
     info!("Starting mediator server");
     transport::Server::builder()
         .add_service(mediator)
@@ -66,6 +84,13 @@ async fn mediator_server_run(addr: SocketAddr) -> Result<()> {
         .context("running mediator-server")
 }
 
+async fn create_room(mediator_addr: SocketAddr, room_id: String) -> Result<()> {
+    let client = mediator::Client::connect(mediator_addr).await?;
+    let token = client.create_room(&room_id).await?;
+    println!("Room token: {}", token);
+    Ok(())
+}
+
 async fn keygen(
     mediator_addr: SocketAddr,
     KeygenArgs {
@@ -73,10 +98,11 @@ async fn keygen(
         parties: n,
         output: output_path,
         room_id,
+        room_token,
     }: KeygenArgs,
 ) -> Result<()> {
     let client = mediator::Client::connect(mediator_addr).await?;
-    let (i, incoming, outcoming) = client.join(&room_id).await?;
+    let (i, incoming, outcoming) = client.join(&room_id, room_token.as_deref()).await?;
     if i > n {
         bail!(
             "too many party joint to perform keygen (at least {} whereas only {} expected)",
@@ -118,6 +144,7 @@ async fn sign(
         parties: n,
         digits: digest,
         room_id,
+        room_token,
     }: SignArgs,
 ) -> Result<()> {
     let secret = tokio::fs::read(secret_key)
@@ -126,7 +153,7 @@ async fn sign(
     let secret = serde_json::from_slice(&secret).context("deserialize local secret key")?;
 
     let client = mediator::Client::connect(mediator_addr).await?;
-    let (i, incoming, outcoming) = client.join(&room_id).await?;
+    let (i, incoming, outcoming) = client.join(&room_id, room_token.as_deref()).await?;
     if i > n {
         bail!(
             "too many party joint to perform keygen (at least {} whereas only {} expected)",
@@ -139,13 +166,14 @@ async fn sign(
         .context("construct signing initial state")?;
 
     info!("Start signing");
-    let (_, sig) = round_based::AsyncProtocol::new(signing, incoming, outcoming)
+    let signed = round_based::AsyncProtocol::new(signing, incoming, outcoming)
         .run()
         .await
         .context("sign execution error")?;
     info!("Signing successfully finished!");
 
-    let public_key = curv::elliptic::curves::traits::ECPoint::pk_to_key_slice(&sig.sigma);
+    let public_key =
+        curv::elliptic::curves::traits::ECPoint::pk_to_key_slice(&signed.signature().sigma);
     println!("Signature: {}", hex::encode(public_key));
     Ok(())
 }
@@ -157,20 +185,15 @@ fn verify(
         digits: digest,
     }: VerifyArgs,
 ) -> Result<()> {
-    use curv::elliptic::curves::bls12_381::{g1::GE as GE1, g2::GE as GE2};
-    use curv::elliptic::curves::traits::ECPoint;
-
-    use bls::basic_bls::BLSSignature;
+    use bls::basic_bls::{g1_from_bytes, g2_from_bytes, BLSSignature};
 
     let public_key =
         hex::decode(public_key).context("public key is not valid hex encoded string")?;
     let signature =
         hex::decode(signature).context("signature key is not valid hex encoded string")?;
 
-    let signature = GE1::from_bytes(&signature)
-        .map_err(|e| anyhow!("signature is not valid g1 point: {:?}", e))?;
-    let public_key = GE2::from_bytes(&public_key)
-        .map_err(|e| anyhow!("public key is not valid g2 point: {:?}", e))?;
+    let signature = g1_from_bytes(&signature).context("signature is not valid g1 point")?;
+    let public_key = g2_from_bytes(&public_key).context("public key is not valid g2 point")?;
 
     let valid = BLSSignature { sigma: signature }.verify(&digest, &public_key);
     if valid {
@@ -181,3 +204,18 @@ fn verify(
 
     Ok(())
 }
+
+async fn inspect(InspectArgs { key }: InspectArgs) -> Result<()> {
+    use bls::threshold_bls::state_machine::keygen::LocalKey;
+
+    let key = tokio::fs::read(key)
+        .await
+        .context("read file with local secret key")?;
+    let key: LocalKey = serde_json::from_slice(&key).context("deserialize local secret key")?;
+
+    let public_key = curv::elliptic::curves::traits::ECPoint::pk_to_key_slice(&key.public_key());
+    println!("Party index: {}", key.index());
+    println!("Threshold: t={} n={}", key.t(), key.n());
+    println!("Public key: {}", hex::encode(public_key));
+    Ok(())
+}
@@ -24,6 +24,7 @@ pub enum Cmd {
     Keygen(KeygenArgs),
     Sign(SignArgs),
     Verify(VerifyArgs),
+    Inspect(InspectArgs),
 }
 
 #[derive(StructOpt, Debug)]
@@ -49,6 +50,11 @@ pub struct KeygenArgs {
     /// specify room id as long as you don't execute several protocols simultaneously.
     #[structopt(long, default_value = "default-room")]
     pub room_id: String,
+    /// Capability token protecting the room
+    ///
+    /// Issued by `mediator-server create-room`, every party must present the same one.
+    #[structopt(long)]
+    pub room_token: Option<String>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -72,6 +78,11 @@ pub struct SignArgs {
     /// specify room id as long as you don't execute several protocols simultaneously.
     #[structopt(long, default_value = "default-room")]
     pub room_id: String,
+    /// Capability token protecting the room
+    ///
+    /// Issued by `mediator-server create-room`, every party must present the same one.
+    #[structopt(long)]
+    pub room_token: Option<String>,
 }
 
 type Bytes = Vec<u8>;
@@ -90,9 +101,32 @@ pub struct VerifyArgs {
     pub digits: Bytes,
 }
 
+#[derive(StructOpt, Debug)]
+/// Prints metadata of local secret key: party index, threshold, and group public key
+pub struct InspectArgs {
+    /// Local secret key path
+    #[structopt(long)]
+    pub key: PathBuf,
+}
+
 #[derive(StructOpt, Debug)]
 /// Manages mediator server (parties' communication layer)
 pub enum MediatorCmd {
     /// Starts mediator server
-    Run,
+    Run {
+        /// Maximum number of messages forwarded to a client at once
+        ///
+        /// Bounds memory used to deliver room history to lately joined parties. Defaults to 1000.
+        #[structopt(long)]
+        page_size: Option<usize>,
+        /// Refuses parties which don't present a room token
+        #[structopt(long)]
+        require_room_token: bool,
+    },
+    /// Creates a room on running mediator server and prints its token
+    CreateRoom {
+        /// Room identifier
+        #[structopt(long)]
+        room_id: String,
+    },
 }
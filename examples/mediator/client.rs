@@ -1,19 +1,33 @@
+use std::convert::TryFrom;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
 use futures::stream::FusedStream;
 use futures::{channel::mpsc, future, Sink, SinkExt, Stream};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, Serialize};
+use serde_json::Value;
 use thiserror::Error;
+use tokio::time::{self, Instant};
 use tonic::metadata::MetadataValue;
-use tonic::{transport, Request, Response};
+use tonic::{transport, Request, Response, Status};
+use tracing::debug;
 
 use round_based::Msg;
 
 use super::proto;
 use super::proto::mediator_client::MediatorClient;
 
+/// Default time [Client::connect] keeps trying to reach the server
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first reconnection attempt, doubled after every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Upper bound of delay between reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
 pub struct Client {
     channel: transport::Channel,
 }
@@ -25,18 +39,72 @@ impl From<transport::Channel> for Client {
 }
 
 impl Client {
+    /// Connects to the server, giving up after [DEFAULT_CONNECT_TIMEOUT]
+    ///
+    /// See [connect_with_timeout](Self::connect_with_timeout).
     pub async fn connect(addr: SocketAddr) -> Result<Self> {
-        let channel = transport::Endpoint::from_shared(format!("http://{}", addr))
-            .context("invalid endpoint uri which was built from socket addr")?
-            .connect()
+        Self::connect_with_timeout(addr, DEFAULT_CONNECT_TIMEOUT).await
+    }
+
+    /// Connects to the server, retrying with exponential backoff until `timeout` elapses
+    ///
+    /// Lets parties start before the server is up. Returns [ClientError::Unreachable] if
+    /// connection wasn't established in time.
+    pub async fn connect_with_timeout(addr: SocketAddr, timeout: Duration) -> Result<Self> {
+        let endpoint = transport::Endpoint::from_shared(format!("http://{}", addr))
+            .context("invalid endpoint uri which was built from socket addr")?;
+        let deadline = Instant::now() + timeout;
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let last_error = match time::timeout_at(deadline, endpoint.connect()).await {
+                Ok(Ok(channel)) => return Ok(Client::from(channel)),
+                Ok(Err(err)) => Some(err),
+                Err(_elapsed) => None,
+            };
+            let now = Instant::now();
+            if now + backoff >= deadline {
+                return Err(ClientError::Unreachable {
+                    addr,
+                    attempts,
+                    source: last_error,
+                }
+                .into());
+            }
+            debug!(attempts, ?backoff, "Server unreachable, retrying");
+            time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Creates the room `room_id` and returns its capability token
+    ///
+    /// Token is issued by server and must be handed to every party out of band. Fails with
+    /// [ClientError::CreateRoomFailed] if room already exists.
+    pub async fn create_room(&self, room_id: &str) -> Result<String> {
+        let mut client = MediatorClient::new(self.channel.clone());
+        let response = client
+            .create_room(proto::CreateRoomRequest {
+                room_id: room_id.to_owned(),
+            })
             .await
-            .context("connect to server")?;
-        Ok(Client { channel })
+            .map_err(ClientError::CreateRoomFailed)?;
+        Ok(response.into_inner().room_token)
     }
 
+    /// Joins the room `room_id`
+    ///
+    /// Can be called several times to take part in a few rooms at once, all of them are
+    /// multiplexed over the same connection.
+    ///
+    /// `room_token` must be presented if the room is protected by one. Token is issued by
+    /// [create_room](Self::create_room), server refuses to let in parties presenting another
+    /// token (see [ClientError::JoinFailed]).
     pub async fn join<T>(
-        self,
+        &self,
         room_id: &str,
+        room_token: Option<&str>,
     ) -> Result<(
         u16,
         impl Stream<Item = std::result::Result<Msg<T>, RecvError>> + FusedStream,
@@ -45,7 +113,76 @@ impl Client {
     where
         T: Serialize + DeserializeOwned + Send + 'static,
     {
-        let mut client = MediatorClient::new(self.channel);
+        self.join_room(room_id, room_token, None, None).await
+    }
+
+    /// Joins the room presenting stable identity `party_id`
+    ///
+    /// Party index is derived from position of `party_id` in sorted list of `parties`, so it
+    /// doesn't depend on the order in which parties join. Every party must provide the same
+    /// list of `parties`. See [join](Self::join) for `room_token`.
+    pub async fn join_as<T>(
+        &self,
+        room_id: &str,
+        room_token: Option<&str>,
+        party_id: &str,
+        parties: &[&str],
+    ) -> Result<(
+        u16,
+        impl Stream<Item = std::result::Result<Msg<T>, RecvError>> + FusedStream,
+        impl Sink<Msg<T>, Error = SendError>,
+    )>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.join_room(room_id, room_token, Some((party_id, parties)), None)
+            .await
+    }
+
+    /// Joins the room again under identity `party_id` after being disconnected
+    ///
+    /// Same as [join_as](Self::join_as), but server forwards room history starting at message
+    /// `resume_from`, i.e. skips messages the party received before disconnecting. Note that
+    /// offset counts every message of the room, including ones not addressed to the party.
+    pub async fn rejoin_as<T>(
+        &self,
+        room_id: &str,
+        room_token: Option<&str>,
+        party_id: &str,
+        parties: &[&str],
+        resume_from: usize,
+    ) -> Result<(
+        u16,
+        impl Stream<Item = std::result::Result<Msg<T>, RecvError>> + FusedStream,
+        impl Sink<Msg<T>, Error = SendError>,
+    )>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.join_room(
+            room_id,
+            room_token,
+            Some((party_id, parties)),
+            Some(resume_from),
+        )
+        .await
+    }
+
+    async fn join_room<T>(
+        &self,
+        room_id: &str,
+        room_token: Option<&str>,
+        identity: Option<(&str, &[&str])>,
+        resume_from: Option<usize>,
+    ) -> Result<(
+        u16,
+        impl Stream<Item = std::result::Result<Msg<T>, RecvError>> + FusedStream,
+        impl Sink<Msg<T>, Error = SendError>,
+    )>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        let mut client = MediatorClient::new(self.channel.clone());
 
         let (mut incoming_tx, incoming_rx) = mpsc::channel(10);
         let (outcoming_tx, outcoming_rx) = mpsc::channel(10);
@@ -53,7 +190,26 @@ impl Client {
         let room_id = MetadataValue::from_str(room_id).context("malformed room_id")?;
         let mut request = Request::new(outcoming_rx);
         request.metadata_mut().insert("room-id", room_id);
-        let response: Response<_> = client.join(request).await.context("join room")?;
+        if let Some(token) = room_token {
+            let token = MetadataValue::from_str(token).context("malformed room_token")?;
+            request.metadata_mut().insert("room-token", token);
+        }
+        if let Some((party_id, parties)) = identity {
+            let party_id = MetadataValue::from_str(party_id).context("malformed party_id")?;
+            let parties =
+                MetadataValue::from_str(&parties.join(",")).context("malformed parties")?;
+            request.metadata_mut().insert("party-id", party_id);
+            request.metadata_mut().insert("parties", parties);
+        }
+        if let Some(offset) = resume_from {
+            request
+                .metadata_mut()
+                .insert("resume-from", MetadataValue::from(offset));
+        }
+        let response: Response<_> = client
+            .join(request)
+            .await
+            .map_err(ClientError::JoinFailed)?;
         let client_idx = response
             .metadata()
             .get("party-idx")
@@ -68,9 +224,7 @@ impl Client {
             loop {
                 match server_messages.message().await {
                     Ok(Some(msg)) => {
-                        let m = Self::deserialize::<T>(&msg.payload)
-                            .context("deserialize incoming message")
-                            .map_err(RecvError);
+                        let m = Self::deserialize::<T>(&msg.payload).map_err(RecvError::Message);
                         if let Ok(m) = m.as_ref() {
                             if m.sender == client_idx
                                 || m.receiver.is_some() && m.receiver != Some(client_idx)
@@ -83,7 +237,7 @@ impl Client {
                         }
                     }
                     Err(e) => {
-                        let e = Err(e).context("recv msg").map_err(RecvError);
+                        let e = Err(e).context("recv msg").map_err(RecvError::Transport);
                         if let Err(_) = incoming_tx.send(e).await {
                             break;
                         }
@@ -105,20 +259,199 @@ impl Client {
         Ok(proto::Msg { payload })
     }
 
-    fn deserialize<T: DeserializeOwned>(buf: &[u8]) -> Result<Msg<T>> {
-        serde_json::from_slice(buf).context("deserialize msg")
+    fn deserialize<T: DeserializeOwned>(buf: &[u8]) -> std::result::Result<Msg<T>, MessageError> {
+        let msg: Msg<Value> = serde_json::from_slice(buf).map_err(MessageError::Malformed)?;
+        Ok(Msg {
+            sender: msg.sender,
+            receiver: msg.receiver,
+            body: decode_body(msg.body)?,
+        })
     }
 }
 
-/// Wraps [anyhow::Error] and implements [std::error::Error] trait
+/// Decodes message body, telling an unknown round apart from an invalid value
+///
+/// Round the body is tagged with is decoded explicitly: protocol messages are serialized as
+/// `[version, {"<round>": ...}]`, plain round enums as `{"<round>": ...}`. Body tagged with a
+/// round which `T` doesn't have is rejected with [MessageError::UnknownRound] before decoding.
+pub(super) fn decode_body<T: DeserializeOwned>(
+    body: Value,
+) -> std::result::Result<T, MessageError> {
+    if let Some((version, round)) = round_tag(&body) {
+        match RoundsProbe::rounds_of::<T>(version) {
+            Some(rounds) if !rounds.contains(&round) => {
+                return Err(MessageError::UnknownRound {
+                    round: round.to_owned(),
+                })
+            }
+            _ => (),
+        }
+    }
+    serde_json::from_value(body).map_err(MessageError::InvalidValue)
+}
+
+/// Protocol version (if message is versioned) and name of the round the body is tagged with
+fn round_tag(body: &Value) -> Option<(Option<u8>, &str)> {
+    let (version, tagged) = match body {
+        Value::Array(fields) if fields.len() == 2 => {
+            let version = fields[0].as_u64().and_then(|v| u8::try_from(v).ok())?;
+            (Some(version), &fields[1])
+        }
+        _ => (None, body),
+    };
+    match tagged {
+        Value::Object(round) if round.len() == 1 => round.keys().next().map(|r| (version, &r[..])),
+        Value::String(round) => Some((version, &round[..])),
+        _ => None,
+    }
+}
+
+/// Deserializer which feeds `T` with the explicitly decoded protocol version, and records
+/// names of the rounds once `T` asks for one
+struct RoundsProbe<'a> {
+    version: Option<u8>,
+    rounds: &'a mut Option<&'static [&'static str]>,
+}
+
+impl RoundsProbe<'_> {
+    /// Names of the rounds `T` has at given protocol `version`, `None` if `T` isn't a round
+    /// enum (optionally preceded by version)
+    fn rounds_of<T: DeserializeOwned>(version: Option<u8>) -> Option<&'static [&'static str]> {
+        let mut rounds = None;
+        let _ = T::deserialize(RoundsProbe {
+            version,
+            rounds: &mut rounds,
+        });
+        rounds
+    }
+}
+
+impl<'de> Deserializer<'de> for RoundsProbe<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        Err(de::Error::custom("probe doesn't carry message values"))
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.version {
+            Some(version) => visitor.visit_u8(version),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        *self.rounds = Some(variants);
+        self.deserialize_any(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple map struct identifier ignored_any
+    }
+}
+
+impl<'de> SeqAccess<'de> for RoundsProbe<'_> {
+    type Error = de::value::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> std::result::Result<Option<S::Value>, Self::Error> {
+        // Version goes first, round follows
+        let probe = RoundsProbe {
+            version: self.version.take(),
+            rounds: &mut *self.rounds,
+        };
+        seed.deserialize(probe).map(Some)
+    }
+}
+
+/// Errors returned by [Client], can be obtained from [anyhow::Error] via downcasting
 #[derive(Error, Debug)]
-#[error(transparent)]
-pub struct RecvError(anyhow::Error);
+pub enum ClientError {
+    /// Connection to the server couldn't be established before timeout
+    #[error("server {addr} is unreachable (gave up after {attempts} attempts)")]
+    Unreachable {
+        addr: SocketAddr,
+        attempts: u32,
+        #[source]
+        source: Option<transport::Error>,
+    },
+    /// Connection was established, but server refused to let party join the room
+    #[error("connected to server, but failed to join the room")]
+    JoinFailed(#[source] Status),
+    /// Server refused to create the room, e.g. because it already exists
+    #[error("failed to create the room")]
+    CreateRoomFailed(#[source] Status),
+}
+
+/// Error of receiving a message
+#[derive(Error, Debug)]
+pub enum RecvError {
+    /// Message was received, but it cannot be deserialized
+    ///
+    /// Affects only this message, so it may be dropped without tearing down the connection.
+    #[error("received invalid message: {0}")]
+    Message(#[source] MessageError),
+    /// Message was received, but it didn't pass security checks (see
+    /// [MessageSecurity](super::MessageSecurity))
+    #[error("received message rejected: {0:#}")]
+    Rejected(anyhow::Error),
+    /// Receiving from the server failed, connection is unusable
+    #[error(transparent)]
+    Transport(anyhow::Error),
+}
+
+impl RecvError {
+    /// Whether connection can't be used anymore
+    ///
+    /// Non-fatal errors affect only one message, so driver may skip it and keep receiving.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, RecvError::Transport(_))
+    }
+}
+
+/// Reason why received message cannot be deserialized
+#[derive(Error, Debug)]
+pub enum MessageError {
+    /// Message is not a valid JSON, or lacks sender/receiver
+    #[error("malformed json: {0}")]
+    Malformed(#[source] serde_json::Error),
+    /// Message is tagged with a round (enum variant) which is unknown to this party, e.g. it was
+    /// sent by party running a different protocol version
+    #[error("unknown round {round:?}")]
+    UnknownRound { round: String },
+    /// Message structure is valid, but some value cannot be parsed, e.g. bytes are not a valid
+    /// curve point
+    #[error("invalid value: {0}")]
+    InvalidValue(#[source] serde_json::Error),
+}
 
 /// Wraps [anyhow::Error] and implements [std::error::Error] trait
 #[derive(Error, Debug)]
 #[error(transparent)]
-pub struct SendError(anyhow::Error);
+pub struct SendError(pub(super) anyhow::Error);
 
 impl From<mpsc::SendError> for SendError {
     fn from(err: mpsc::SendError) -> SendError {
@@ -143,11 +476,11 @@ mod test {
         let party3 = stand.connect_client().await;
 
         let (party1_idx, mut party1_incoming, mut party1_outcoming) =
-            party1.join("testing-room").await.unwrap();
+            party1.join("testing-room", None).await.unwrap();
         let (party2_idx, mut party2_incoming, mut party2_outcoming) =
-            party2.join("testing-room").await.unwrap();
+            party2.join("testing-room", None).await.unwrap();
         let (party3_idx, mut party3_incoming, _party3_outcoming) =
-            party3.join("testing-room").await.unwrap();
+            party3.join("testing-room", None).await.unwrap();
 
         assert_eq!(party1_idx, 1);
         assert_eq!(party2_idx, 2);
@@ -202,9 +535,9 @@ mod test {
         let party2 = stand.connect_client().await;
 
         let (party1_idx, mut party1_incoming, mut party1_outcoming) =
-            party1.join("testing-room").await.unwrap();
+            party1.join("testing-room", None).await.unwrap();
         let (party2_idx, mut party2_incoming, mut party2_outcoming) =
-            party2.join("testing-room").await.unwrap();
+            party2.join("testing-room", None).await.unwrap();
 
         assert_eq!(party1_idx, 1);
         assert_eq!(party2_idx, 2);
@@ -241,6 +574,182 @@ mod test {
         };
     }
 
+    #[tokio::test]
+    async fn client_joins_several_rooms() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let party1 = stand.connect_client().await;
+        let party2 = stand.connect_client().await;
+
+        let (_, _room_a_incoming, mut room_a_outcoming) =
+            party1.join::<String>("room-a", None).await.unwrap();
+        let (_, mut room_b_incoming, _room_b_outcoming) =
+            party1.join::<String>("room-b", None).await.unwrap();
+        let (idx_a, mut party2_incoming, _) = party2.join::<String>("room-a", None).await.unwrap();
+        assert_eq!(idx_a, 2);
+
+        let msg = Msg {
+            sender: 1,
+            receiver: None,
+            body: "Only for room A".to_string(),
+        };
+        room_a_outcoming.send(msg.clone()).await.unwrap();
+        assert_eq!(Some(msg), party2_incoming.next().await.transpose().unwrap());
+        futures::select! {
+            _ = room_b_incoming.next() => panic!("message leaked into another room"),
+            _ = time::sleep(time::Duration::from_millis(100)).fuse() => (),
+        };
+    }
+
+    #[tokio::test]
+    async fn client_joins_rooms_with_different_tokens() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let party = stand.connect_client().await;
+        let token_a = party.create_room("room-a").await.unwrap();
+        let token_b = party.create_room("room-b").await.unwrap();
+        assert_ne!(token_a, token_b);
+
+        let (idx_a, _, _) = party
+            .join::<String>("room-a", Some(&token_a))
+            .await
+            .unwrap();
+        let (idx_b, _, _) = party
+            .join::<String>("room-b", Some(&token_b))
+            .await
+            .unwrap();
+        assert_eq!((idx_a, idx_b), (1, 1));
+
+        let result = party.join::<String>("room-a", Some(&token_b)).await;
+        let err = match result {
+            Ok(_) => panic!("joined with token of another room"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::JoinFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn join_as_assigns_idx_independent_of_join_order() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+        let parties = ["alice", "bob", "carol"];
+
+        let (bob_idx, _, _) = stand
+            .connect_client()
+            .await
+            .join_as::<String>("testing-room", None, "bob", &parties)
+            .await
+            .unwrap();
+        let (carol_idx, _, _) = stand
+            .connect_client()
+            .await
+            .join_as::<String>("testing-room", None, "carol", &parties)
+            .await
+            .unwrap();
+        let (alice_idx, _, _) = stand
+            .connect_client()
+            .await
+            .join_as::<String>("testing-room", None, "alice", &parties)
+            .await
+            .unwrap();
+
+        assert_eq!((alice_idx, bob_idx, carol_idx), (1, 2, 3));
+    }
+
+    #[tokio::test]
+    async fn connect_to_unreachable_server_times_out() {
+        let _ = tracing_subscriber::fmt::try_init();
+        // Reserve free port and release it, so nobody listens on it
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let err = match Client::connect_with_timeout(addr, Duration::from_millis(500)).await {
+            Ok(_) => panic!("connected to unreachable server"),
+            Err(err) => err,
+        };
+        assert!(started.elapsed() < Duration::from_secs(5));
+        match err.downcast_ref::<ClientError>() {
+            Some(ClientError::Unreachable {
+                addr: reported,
+                attempts,
+                ..
+            }) => {
+                assert_eq!(*reported, addr);
+                assert!(*attempts > 1);
+            }
+            _ => panic!("unexpected error: {:#}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn refused_join_is_distinguished_from_unreachable_server() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let client = stand.connect_client().await;
+        let result = client
+            .join_as::<String>("testing-room", None, "mallory", &["alice", "bob"])
+            .await;
+        let err = match result {
+            Ok(_) => panic!("joined as unknown party"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::JoinFailed(_))
+        ));
+    }
+
+    #[test]
+    fn deserialization_errors_are_classified() {
+        #[derive(Debug, serde::Deserialize)]
+        enum Body {
+            #[allow(dead_code)]
+            Round1(u16),
+        }
+        let deserialize = |json: &str| Client::deserialize::<Body>(json.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            deserialize(r#"{"sender":1,"receiver":null,"body":{"Round1""#),
+            MessageError::Malformed(_)
+        ));
+        assert!(matches!(
+            deserialize(r#"{"sender":1,"receiver":null,"body":{"Round2":1}}"#),
+            MessageError::UnknownRound { round } if round == "Round2"
+        ));
+        assert!(matches!(
+            deserialize(r#"{"sender":1,"receiver":null,"body":{"Round1":"not a number"}}"#),
+            MessageError::InvalidValue(_)
+        ));
+
+        // Round of versioned protocol message is found after the version
+        #[derive(Debug, serde::Deserialize)]
+        struct Versioned(#[allow(dead_code)] u8, #[allow(dead_code)] Body);
+        let deserialize =
+            |json: &str| Client::deserialize::<Versioned>(json.as_bytes()).unwrap_err();
+        assert!(matches!(
+            deserialize(r#"{"sender":1,"receiver":null,"body":[1,{"Round2":1}]}"#),
+            MessageError::UnknownRound { round } if round == "Round2"
+        ));
+        assert!(matches!(
+            deserialize(r#"{"sender":1,"receiver":null,"body":[1,{"Round1":"x"}]}"#),
+            MessageError::InvalidValue(_)
+        ));
+
+        let err = RecvError::Message(deserialize("{"));
+        assert!(!err.is_fatal());
+        assert!(RecvError::Transport(anyhow!("connection reset")).is_fatal());
+    }
+
     struct Stand(crate::mediator::server::test::Stand);
 
     impl Stand {
@@ -0,0 +1,103 @@
+//! Metrics reported by mediator server
+//!
+//! Server reports metrics via [Metrics] trait. By default they're discarded ([NoopMetrics]),
+//! with `metrics` feature enabled they can be forwarded to [metrics] crate facade
+//! ([MetricsFacade]) and exported from there, e.g. to Prometheus.
+//!
+//! [metrics]: https://docs.rs/metrics
+
+/// Monotonically increasing counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// Messages received from clients
+    MessagesReceived,
+    /// Messages forwarded to clients
+    MessagesForwarded,
+}
+
+/// Values that can go up and down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gauge {
+    /// Rooms kept by server
+    RoomsActive,
+    /// Total size of messages kept in rooms history
+    BytesBuffered,
+}
+
+/// Distributions of observed values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Histogram {
+    /// Time spent on joining a room (in seconds)
+    JoinLatency,
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+impl Counter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::MessagesReceived => "mediator_messages_received_total",
+            Counter::MessagesForwarded => "mediator_messages_forwarded_total",
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+impl Gauge {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gauge::RoomsActive => "mediator_rooms_active",
+            Gauge::BytesBuffered => "mediator_bytes_buffered",
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+impl Histogram {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Histogram::JoinLatency => "mediator_join_latency_seconds",
+        }
+    }
+}
+
+/// Sink of server metrics
+pub trait Metrics: Send + Sync {
+    /// Increments `counter` by `value`
+    fn increment(&self, counter: Counter, value: u64);
+    /// Adds `delta` to `gauge`, `delta` is negative if value goes down
+    fn adjust(&self, gauge: Gauge, delta: f64);
+    /// Records `value` in `histogram`
+    fn observe(&self, histogram: Histogram, value: f64);
+}
+
+/// Discards all metrics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {
+    fn increment(&self, _counter: Counter, _value: u64) {}
+    fn adjust(&self, _gauge: Gauge, _delta: f64) {}
+    fn observe(&self, _histogram: Histogram, _value: f64) {}
+}
+
+/// Forwards metrics to global recorder of [metrics](https://docs.rs/metrics) crate
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl Metrics for MetricsFacade {
+    fn increment(&self, counter: Counter, value: u64) {
+        ::metrics::counter!(counter.name(), value)
+    }
+    fn adjust(&self, gauge: Gauge, delta: f64) {
+        if delta >= 0. {
+            ::metrics::increment_gauge!(gauge.name(), delta)
+        } else {
+            ::metrics::decrement_gauge!(gauge.name(), -delta)
+        }
+    }
+    fn observe(&self, histogram: Histogram, value: f64) {
+        ::metrics::histogram!(histogram.name(), value)
+    }
+}
@@ -1,7 +1,11 @@
 mod client;
+pub mod metrics;
+mod secure;
 mod server;
 
-pub use client::Client;
+pub use client::{Client, ClientError};
+pub use metrics::Metrics;
+pub use secure::{DefaultSecurity, MessageSecurity, PeerKeys, SecureClient};
 pub use server::Server;
 
 pub mod proto {
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use ed25519_dalek::{Signer, Verifier};
+use futures::stream::FusedStream;
+use futures::{future, Sink, SinkExt, Stream, StreamExt};
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use round_based::Msg;
+
+use super::client::{decode_body, Client, MessageError, RecvError, SendError};
+
+/// Enforces security assumptions on messages delivery
+///
+/// Any P2P message must be encrypted so no one can read it except recipient, and any broadcast
+/// message must be signed so no one can forge its sender.
+///
+/// `context` is [message context](message_context) that must be authenticated along with the
+/// message: opening a message under different context must fail.
+pub trait MessageSecurity: Send + Sync + 'static {
+    fn encrypt_p2p(&self, receiver: u16, context: &[u8], plaintext: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt_p2p(&self, sender: u16, context: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>>;
+    fn sign_broadcast(&self, context: &[u8], msg: &[u8]) -> Result<Vec<u8>>;
+    /// Verifies signature of broadcast message and returns the message itself
+    fn verify_broadcast(&self, sender: u16, context: &[u8], signed_msg: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Encodes `(room_id, sender, receiver, counter)` of a message
+///
+/// Binding it to the message prevents replaying the message in another room, reflecting a P2P
+/// message back to its sender, and replaying it within the same room.
+pub fn message_context(room_id: &str, sender: u16, receiver: Option<u16>, counter: u64) -> Vec<u8> {
+    let mut context = vec![];
+    context.extend_from_slice(&(room_id.len() as u64).to_be_bytes());
+    context.extend_from_slice(room_id.as_bytes());
+    context.extend_from_slice(&sender.to_be_bytes());
+    match receiver {
+        Some(receiver) => {
+            context.push(1);
+            context.extend_from_slice(&receiver.to_be_bytes());
+        }
+        None => context.push(0),
+    }
+    context.extend_from_slice(&counter.to_be_bytes());
+    context
+}
+
+const COUNTER_SIZE: usize = 8;
+
+/// Wraps [Client] making every message go through [MessageSecurity]
+///
+/// Every outgoing message is numbered by a counter, which is sent along with the message and
+/// authenticated as part of its [context](message_context). Messages failing decryption or
+/// signature verification, and messages whose counter isn't greater than the last one seen
+/// from the same sender, are reported as errors in incoming stream.
+pub struct SecureClient<S> {
+    client: Client,
+    security: Arc<S>,
+}
+
+impl<S: MessageSecurity> SecureClient<S> {
+    pub fn new(client: Client, security: S) -> Self {
+        Self {
+            client,
+            security: Arc::new(security),
+        }
+    }
+
+    /// Joins the room, see [Client::join]
+    pub async fn join<T>(
+        &self,
+        room_id: &str,
+        room_token: Option<&str>,
+    ) -> Result<(
+        u16,
+        impl Stream<Item = std::result::Result<Msg<T>, RecvError>> + FusedStream,
+        impl Sink<Msg<T>, Error = SendError>,
+    )>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        let (idx, incoming, outcoming) = self.client.join::<Vec<u8>>(room_id, room_token).await?;
+
+        let security = self.security.clone();
+        let room = room_id.to_string();
+        let mut last_seen = HashMap::new();
+        let incoming = incoming.map(move |msg| {
+            msg.and_then(|msg| Self::open(&*security, &room, idx, &mut last_seen, msg))
+        });
+        let security = self.security.clone();
+        let room = room_id.to_string();
+        let mut counter = 0;
+        let outcoming = outcoming.with(move |msg| {
+            counter += 1;
+            future::ready(Self::seal(&*security, &room, counter, msg).map_err(SendError))
+        });
+
+        Ok((idx, incoming, outcoming))
+    }
+
+    fn seal<T: Serialize>(
+        security: &S,
+        room_id: &str,
+        counter: u64,
+        msg: Msg<T>,
+    ) -> Result<Msg<Vec<u8>>> {
+        let body = serde_json::to_vec(&msg.body).context("serialize msg body")?;
+        let context = message_context(room_id, msg.sender, msg.receiver, counter);
+        let body = match msg.receiver {
+            Some(receiver) => security.encrypt_p2p(receiver, &context, &body)?,
+            None => security.sign_broadcast(&context, &body)?,
+        };
+        Ok(Msg {
+            sender: msg.sender,
+            receiver: msg.receiver,
+            body: [&counter.to_be_bytes()[..], &body].concat(),
+        })
+    }
+
+    /// Opens message received by party `idx`
+    ///
+    /// `last_seen` keeps the last counter of every sender and is updated once the message is
+    /// authenticated.
+    fn open<T: DeserializeOwned>(
+        security: &S,
+        room_id: &str,
+        idx: u16,
+        last_seen: &mut HashMap<u16, u64>,
+        msg: Msg<Vec<u8>>,
+    ) -> std::result::Result<Msg<T>, RecvError> {
+        if msg.body.len() < COUNTER_SIZE {
+            return Err(RecvError::Rejected(anyhow!("message is too short")));
+        }
+        let (counter, body) = msg.body.split_at(COUNTER_SIZE);
+        let counter = u64::from_be_bytes(<[u8; COUNTER_SIZE]>::try_from(counter).unwrap());
+        // P2P message is bound to us as a receiver, so it can't be reflected to its sender
+        let receiver = msg.receiver.map(|_| idx);
+        let context = message_context(room_id, msg.sender, receiver, counter);
+        let body = match receiver {
+            Some(_) => security
+                .decrypt_p2p(msg.sender, &context, body)
+                .context("decrypt p2p message"),
+            None => security
+                .verify_broadcast(msg.sender, &context, body)
+                .context("verify broadcast message"),
+        }
+        .map_err(RecvError::Rejected)?;
+        match last_seen.get(&msg.sender) {
+            Some(&last) if counter <= last => {
+                return Err(RecvError::Rejected(anyhow!(
+                    "replayed message: counter {} isn't greater than {}",
+                    counter,
+                    last
+                )))
+            }
+            _ => {
+                last_seen.insert(msg.sender, counter);
+            }
+        }
+        let body = serde_json::from_slice(&body)
+            .map_err(|err| RecvError::Message(MessageError::Malformed(err)))?;
+        let body = decode_body(body).map_err(RecvError::Message)?;
+        Ok(Msg {
+            sender: msg.sender,
+            receiver: msg.receiver,
+            body,
+        })
+    }
+}
+
+/// Public keys of a party known in advance
+#[derive(Clone)]
+pub struct PeerKeys {
+    pub encryption: x25519_dalek::PublicKey,
+    pub signing: ed25519_dalek::PublicKey,
+}
+
+/// [MessageSecurity] based on long-term keys distributed out of band
+///
+/// P2P messages are encrypted with ChaCha20-Poly1305 under a key derived from static X25519
+/// Diffie-Hellman between sender and recipient, with message context as associated data.
+/// Broadcast messages are signed with Ed25519 along with their context.
+///
+/// Peers are identified by party index, so parties should join the room via
+/// [Client::join_as] to get indexes known in advance.
+pub struct DefaultSecurity {
+    encryption: x25519_dalek::StaticSecret,
+    signing: ed25519_dalek::Keypair,
+    peers: HashMap<u16, PeerKeys>,
+}
+
+const NONCE_SIZE: usize = 12;
+
+impl DefaultSecurity {
+    pub fn new(
+        encryption: x25519_dalek::StaticSecret,
+        signing: ed25519_dalek::Keypair,
+        peers: HashMap<u16, PeerKeys>,
+    ) -> Self {
+        Self {
+            encryption,
+            signing,
+            peers,
+        }
+    }
+
+    fn peer(&self, idx: u16) -> Result<&PeerKeys> {
+        self.peers
+            .get(&idx)
+            .ok_or_else(|| anyhow!("unknown party {}", idx))
+    }
+
+    fn cipher(&self, peer: u16) -> Result<ChaCha20Poly1305> {
+        let shared_secret = self.encryption.diffie_hellman(&self.peer(peer)?.encryption);
+        let key = Sha256::digest(shared_secret.as_bytes());
+        Ok(ChaCha20Poly1305::new(GenericArray::from_slice(
+            key.as_slice(),
+        )))
+    }
+}
+
+impl MessageSecurity for DefaultSecurity {
+    fn encrypt_p2p(&self, receiver: u16, context: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad: context,
+        };
+        let ciphertext = self
+            .cipher(receiver)?
+            .encrypt(GenericArray::from_slice(&nonce), payload)
+            .map_err(|_| anyhow!("encryption failed"))?;
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    fn decrypt_p2p(&self, sender: u16, context: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < NONCE_SIZE {
+            bail!("ciphertext is too short")
+        }
+        let (nonce, ciphertext) = ciphertext.split_at(NONCE_SIZE);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: context,
+        };
+        self.cipher(sender)?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .map_err(|_| anyhow!("decryption failed"))
+    }
+
+    fn sign_broadcast(&self, context: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+        let signature = self.signing.sign(&[context, msg].concat());
+        Ok([&signature.to_bytes()[..], msg].concat())
+    }
+
+    fn verify_broadcast(&self, sender: u16, context: &[u8], signed_msg: &[u8]) -> Result<Vec<u8>> {
+        if signed_msg.len() < ed25519_dalek::SIGNATURE_LENGTH {
+            bail!("signed message is too short")
+        }
+        let (signature, msg) = signed_msg.split_at(ed25519_dalek::SIGNATURE_LENGTH);
+        let signature =
+            ed25519_dalek::Signature::try_from(signature).context("malformed signature")?;
+        self.peer(sender)?
+            .signing
+            .verify(&[context, msg].concat(), &signature)
+            .context("invalid signature")?;
+        Ok(msg.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{FutureExt, SinkExt, StreamExt};
+    use tokio::time;
+
+    use super::*;
+
+    struct Party {
+        encryption: x25519_dalek::StaticSecret,
+        signing: ed25519_dalek::Keypair,
+    }
+
+    impl Party {
+        fn generate() -> Self {
+            Self {
+                encryption: x25519_dalek::StaticSecret::new(rand::rngs::OsRng),
+                signing: ed25519_dalek::Keypair::generate(&mut rand::rngs::OsRng),
+            }
+        }
+
+        fn public_keys(&self) -> PeerKeys {
+            PeerKeys {
+                encryption: x25519_dalek::PublicKey::from(&self.encryption),
+                signing: self.signing.public,
+            }
+        }
+
+        fn security(self, peers: HashMap<u16, PeerKeys>) -> DefaultSecurity {
+            DefaultSecurity::new(self.encryption, self.signing, peers)
+        }
+    }
+
+    #[tokio::test]
+    async fn secure_messages_are_delivered() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = crate::mediator::server::test::Stand::new().await;
+
+        let alice = Party::generate();
+        let bob = Party::generate();
+        let peers: HashMap<_, _> = vec![(1, alice.public_keys()), (2, bob.public_keys())]
+            .into_iter()
+            .collect();
+
+        let alice = SecureClient::new(
+            Client::connect(stand.server_addr()).await.unwrap(),
+            alice.security(peers.clone()),
+        );
+        let bob = SecureClient::new(
+            Client::connect(stand.server_addr()).await.unwrap(),
+            bob.security(peers),
+        );
+
+        let (alice_idx, _alice_incoming, mut alice_outcoming) =
+            alice.join::<String>("testing-room", None).await.unwrap();
+        let (bob_idx, mut bob_incoming, _bob_outcoming) =
+            bob.join::<String>("testing-room", None).await.unwrap();
+        assert_eq!((alice_idx, bob_idx), (1, 2));
+
+        let broadcast = Msg {
+            sender: alice_idx,
+            receiver: None,
+            body: "Hello everyone".to_string(),
+        };
+        let p2p = Msg {
+            sender: alice_idx,
+            receiver: Some(bob_idx),
+            body: "Hello Bob".to_string(),
+        };
+        alice_outcoming.send(broadcast.clone()).await.unwrap();
+        alice_outcoming.send(p2p.clone()).await.unwrap();
+
+        assert_eq!(
+            Some(broadcast),
+            bob_incoming.next().await.transpose().unwrap()
+        );
+        assert_eq!(Some(p2p), bob_incoming.next().await.transpose().unwrap());
+    }
+
+    #[test]
+    fn messages_are_bound_to_their_context() {
+        type Secure = SecureClient<DefaultSecurity>;
+
+        let alice = Party::generate();
+        let bob = Party::generate();
+        let peers: HashMap<_, _> = vec![(1, alice.public_keys()), (2, bob.public_keys())]
+            .into_iter()
+            .collect();
+        let alice = alice.security(peers.clone());
+        let bob = bob.security(peers);
+
+        for receiver in vec![None, Some(2)] {
+            let msg = Msg {
+                sender: 1,
+                receiver,
+                body: "Hello".to_string(),
+            };
+            let sealed = Secure::seal(&alice, "room-a", 1, msg.clone()).unwrap();
+
+            // Can't be replayed in another room
+            let opened =
+                Secure::open::<String>(&bob, "room-b", 2, &mut HashMap::new(), sealed.clone());
+            assert!(matches!(opened, Err(RecvError::Rejected(_))));
+
+            // Can't be replayed within the same room
+            let mut last_seen = HashMap::new();
+            let opened = Secure::open(&bob, "room-a", 2, &mut last_seen, sealed.clone()).unwrap();
+            assert_eq!(opened, msg);
+            let opened = Secure::open::<String>(&bob, "room-a", 2, &mut last_seen, sealed.clone());
+            assert!(matches!(opened, Err(RecvError::Rejected(_))));
+
+            // Can't be claimed by another sender
+            let mut tampered = sealed.clone();
+            tampered.sender = 2;
+            let opened = Secure::open::<String>(&alice, "room-a", 1, &mut HashMap::new(), tampered);
+            assert!(matches!(opened, Err(RecvError::Rejected(_))));
+        }
+
+        // P2P message can't be reflected back to its sender
+        let msg = Msg {
+            sender: 1,
+            receiver: Some(2),
+            body: "Hello Bob".to_string(),
+        };
+        let mut reflected = Secure::seal(&alice, "room-a", 1, msg).unwrap();
+        reflected.sender = 2;
+        reflected.receiver = Some(1);
+        let opened = Secure::open::<String>(&alice, "room-a", 1, &mut HashMap::new(), reflected);
+        assert!(matches!(opened, Err(RecvError::Rejected(_))));
+    }
+
+    #[tokio::test]
+    async fn forged_messages_are_rejected() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = crate::mediator::server::test::Stand::new().await;
+
+        let alice = Party::generate();
+        let bob = Party::generate();
+        let mallory = Party::generate();
+        // Bob believes that party 1 is Alice, but Mallory took her place
+        let bob_peers: HashMap<_, _> = vec![(1, alice.public_keys()), (2, bob.public_keys())]
+            .into_iter()
+            .collect();
+        let mallory_peers: HashMap<_, _> = vec![(1, mallory.public_keys()), (2, bob.public_keys())]
+            .into_iter()
+            .collect();
+
+        let mallory = SecureClient::new(
+            Client::connect(stand.server_addr()).await.unwrap(),
+            mallory.security(mallory_peers),
+        );
+        let bob = SecureClient::new(
+            Client::connect(stand.server_addr()).await.unwrap(),
+            bob.security(bob_peers),
+        );
+
+        let (mallory_idx, _mallory_incoming, mut mallory_outcoming) =
+            mallory.join::<String>("testing-room", None).await.unwrap();
+        let (bob_idx, mut bob_incoming, _bob_outcoming) =
+            bob.join::<String>("testing-room", None).await.unwrap();
+
+        let broadcast = Msg {
+            sender: mallory_idx,
+            receiver: None,
+            body: "I'm Alice".to_string(),
+        };
+        let p2p = Msg {
+            sender: mallory_idx,
+            receiver: Some(bob_idx),
+            body: "I'm Alice".to_string(),
+        };
+        mallory_outcoming.send(broadcast).await.unwrap();
+        mallory_outcoming.send(p2p).await.unwrap();
+
+        assert!(bob_incoming.next().await.unwrap().is_err());
+        assert!(bob_incoming.next().await.unwrap().is_err());
+        futures::select! {
+            _ = bob_incoming.next() => panic!("bob received unexpected message"),
+            _ = time::sleep(time::Duration::from_millis(100)).fuse() => (),
+        };
+    }
+}
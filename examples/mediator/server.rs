@@ -1,21 +1,53 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use futures::future::FutureExt;
 use futures::stream::{Stream, StreamExt};
+use rand::RngCore;
 use tokio::sync::{Notify, RwLock};
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{error, trace};
 
-use super::proto::{self, Msg};
+use super::metrics::{Counter, Gauge, Histogram, Metrics, NoopMetrics};
+use super::proto::{self, CreateRoomRequest, CreateRoomResponse, Msg, PingRequest, PingResponse};
+
+/// Default maximum number of messages forwarded to a client at once
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// How far behind the furthest message already delivered to a party it may ask to receive
+/// messages from again
+///
+/// Bounds how much history a party can make server replay, so it can't amplify traffic by
+/// requesting the whole history over and over.
+pub const RECV_REWIND_WINDOW: usize = 16;
+
+/// Size of room token issued by server (in bytes, before hex encoding)
+const ROOM_TOKEN_SIZE: usize = 32;
 
-#[derive(Default)]
 pub struct Server {
     rooms: RwLock<HashMap<Vec<u8>, Arc<Room>>>,
     garbage: AtomicBool,
+    page_size: usize,
+    require_room_token: bool,
+    metrics: Arc<dyn Metrics>,
+    started: Instant,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self {
+            rooms: Default::default(),
+            garbage: Default::default(),
+            page_size: DEFAULT_PAGE_SIZE,
+            require_room_token: false,
+            metrics: Arc::new(NoopMetrics),
+            started: Instant::now(),
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -30,21 +62,58 @@ impl proto::mediator_server::Mediator for Arc<Server> {
             Some(id) => id.as_bytes(),
             None => return Err(Status::invalid_argument("room-id is not provided")),
         };
-        let room = self.join_room(room_id).await;
+        let identity = match (
+            req.metadata().get("party-id"),
+            req.metadata().get("parties"),
+        ) {
+            (Some(id), Some(parties)) => Some(Identity {
+                id: id.as_bytes().to_vec(),
+                parties: parties
+                    .as_bytes()
+                    .split(|&b| b == b',')
+                    .map(|p| p.to_vec())
+                    .collect(),
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "party-id and parties must be provided together",
+                ))
+            }
+        };
+        let token = req
+            .metadata()
+            .get("room-token")
+            .map(|t| t.as_bytes().to_vec());
+        let resume_from = match req.metadata().get("resume-from") {
+            Some(offset) => offset
+                .to_str()
+                .ok()
+                .and_then(|offset| offset.parse::<usize>().ok())
+                .ok_or_else(|| Status::invalid_argument("malformed resume-from"))?,
+            None => 0,
+        };
+        let room = self.join_room(room_id, identity, token).await?;
         let party_idx = room.join_idx();
+        let page_size = self.page_size;
 
         let mut msgs = vec![];
-        let mut next_msg_idx = 0;
+        let mut next_msg_idx = resume_from;
         let mut stream = req.into_inner().fuse();
 
         let response_stream = async_stream::stream! {
             loop {
                 let event: Event = futures::select! {
-                    idx = room.recv(next_msg_idx, &mut msgs).fuse() => Event::ForwardMessagesToClient(idx),
+                    idx = room.recv(party_idx, next_msg_idx, page_size, &mut msgs).fuse() => Event::ForwardMessagesToClient(idx),
                     msg = stream.next() => Event::ClientSentMessage(msg),
                 };
                 match event {
-                    Event::ForwardMessagesToClient(idx) => {
+                    Event::ForwardMessagesToClient(Err(err)) => {
+                        error!(%err, "Refused to forward messages");
+                        yield Err(err);
+                        break
+                    }
+                    Event::ForwardMessagesToClient(Ok(idx)) => {
                         trace!("Forwarding messages to the client...");
                         next_msg_idx = idx;
                         for payload in msgs.drain(..) {
@@ -74,10 +143,29 @@ impl proto::mediator_server::Mediator for Arc<Server> {
             .insert("party-idx", tonic::metadata::MetadataValue::from(party_idx));
         Ok(response)
     }
+
+    async fn create_room(
+        &self,
+        req: Request<CreateRoomRequest>,
+    ) -> Result<Response<CreateRoomResponse>, Status> {
+        let room_id = req.into_inner().room_id;
+        if room_id.is_empty() {
+            return Err(Status::invalid_argument("room_id is empty"));
+        }
+        let room_token = self.create_room(room_id.as_bytes()).await?;
+        Ok(Response::new(CreateRoomResponse { room_token }))
+    }
+
+    async fn ping(&self, _req: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
+        Ok(Response::new(PingResponse {
+            uptime_secs: self.started.elapsed().as_secs(),
+            rooms_active: self.rooms.read().await.len() as u64,
+        }))
+    }
 }
 
 enum Event {
-    ForwardMessagesToClient(usize),
+    ForwardMessagesToClient(Result<usize, Status>),
     ClientSentMessage(Option<Result<Msg, Status>>),
 }
 
@@ -86,16 +174,91 @@ impl Server {
         Self::default()
     }
 
+    /// Limits number of messages forwarded to a client at once
+    ///
+    /// A party joining a long-lived room receives its history page by page rather than all at
+    /// once. Defaults to [DEFAULT_PAGE_SIZE]. Panics if `page_size` is zero.
+    pub fn with_page_size(page_size: usize) -> Self {
+        assert!(page_size > 0, "page size must be positive");
+        Self {
+            page_size,
+            ..Self::default()
+        }
+    }
+
+    /// Requires every room to be protected by a capability token
+    ///
+    /// Protected rooms are created explicitly via `CreateRoom`, which issues the room token.
+    /// Every party must present it as `room-token`, otherwise it's rejected with
+    /// [Status::permission_denied]. Without this option, created rooms are still protected,
+    /// but parties may also join a room that wasn't created, in which case it's created
+    /// without a token.
+    pub fn require_room_token(mut self, require: bool) -> Self {
+        self.require_room_token = require;
+        self
+    }
+
+    /// Reports server metrics to `metrics`
+    ///
+    /// Metrics are discarded by default.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     fn trigger_garbage_collection(&self) {
         self.garbage.store(true, Ordering::SeqCst)
     }
 
     async fn collect_garbage(&self) {
         let mut rooms = self.rooms.write().await;
-        rooms.retain(|_, room| room.is_empty());
+        let (mut rooms_removed, mut bytes_removed) = (0, 0);
+        rooms.retain(|_, room| {
+            let keep = room.is_empty();
+            if !keep {
+                rooms_removed += 1;
+                bytes_removed += room.bytes_buffered();
+            }
+            keep
+        });
+        if rooms_removed > 0 {
+            self.metrics
+                .adjust(Gauge::RoomsActive, -(rooms_removed as f64));
+            self.metrics
+                .adjust(Gauge::BytesBuffered, -(bytes_removed as f64));
+        }
+    }
+
+    /// Creates a room and issues its token
+    ///
+    /// Fails with [Status::already_exists] if room is already kept by server.
+    async fn create_room(&self, room_id: &[u8]) -> Result<String, Status> {
+        self.collect_garbage().await;
+
+        let mut token = [0u8; ROOM_TOKEN_SIZE];
+        rand::thread_rng().fill_bytes(&mut token);
+        let token = hex::encode(token);
+
+        let mut rooms = self.rooms.write().await;
+        if rooms.contains_key(room_id) {
+            return Err(Status::already_exists("room already exists"));
+        }
+        let room = Room::new(self.metrics.clone()).with_token(Some(token.clone().into_bytes()));
+        rooms.insert(room_id.to_vec(), Arc::new(room));
+        self.metrics.adjust(Gauge::RoomsActive, 1.);
+        Ok(token)
     }
 
-    async fn join_room(self: &Arc<Self>, room_id: &[u8]) -> JoinHandler {
+    async fn join_room(
+        self: &Arc<Self>,
+        room_id: &[u8],
+        identity: Option<Identity>,
+        token: Option<Vec<u8>>,
+    ) -> Result<JoinHandler, Status> {
+        let started = Instant::now();
+        if self.require_room_token && token.is_none() {
+            return Err(Status::permission_denied("room-token is not provided"));
+        }
         self.collect_garbage().await;
 
         // At first we optimistically check if room exists
@@ -103,27 +266,51 @@ impl Server {
             let rooms = self.rooms.read().await;
             match rooms.get(room_id) {
                 Some(room) => room.clone(),
+                // Token is issued along with the room, a party can't choose its own
+                None if token.is_some() => {
+                    return Err(Status::not_found(
+                        "room doesn't exist, it must be created first",
+                    ))
+                }
                 None => {
                     // Optimistic check failed. Go pessimistically
                     drop(rooms);
                     let mut rooms = self.rooms.write().await;
                     rooms
                         .entry(room_id.to_vec())
-                        .or_insert_with(|| Arc::new(Room::default()))
+                        .or_insert_with(|| {
+                            self.metrics.adjust(Gauge::RoomsActive, 1.);
+                            Arc::new(Room::new(self.metrics.clone()))
+                        })
                         .clone()
                 }
             }
         };
-        JoinHandler {
-            idx: room.issue_next_party_idx(),
+        room.check_token(token.as_deref())?;
+        let (idx, identity) = match identity {
+            Some(identity) => {
+                let id = identity.id.clone();
+                (room.issue_party_idx_for(identity)?, Some(id))
+            }
+            None => (room.issue_next_party_idx()?, None),
+        };
+        // Index derived from identity may be reused, a rejoining party starts afresh
+        room.forget_delivered(idx);
+        self.metrics
+            .observe(Histogram::JoinLatency, started.elapsed().as_secs_f64());
+        Ok(JoinHandler {
+            idx,
+            seat: Some(Arc::new(Seat { identity })),
             server: self.clone(),
             room,
-        }
+        })
     }
 }
 
 struct JoinHandler {
     idx: u32,
+    /// Released once the last handler is dropped
+    seat: Option<Arc<Seat>>,
     server: Arc<Server>,
     room: Arc<Room>,
 }
@@ -133,6 +320,7 @@ impl Clone for JoinHandler {
         self.room.party_connected();
         Self {
             idx: self.idx,
+            seat: self.seat.clone(),
             server: self.server.clone(),
             room: self.room.clone(),
         }
@@ -154,23 +342,152 @@ impl ops::Deref for JoinHandler {
 
 impl ops::Drop for JoinHandler {
     fn drop(&mut self) {
+        if let Some(Ok(seat)) = self.seat.take().map(Arc::try_unwrap) {
+            self.room.leave(self.idx, seat.identity.as_deref())
+        }
         if self.party_disconnected() {
             self.server.trigger_garbage_collection()
         }
     }
 }
 
-#[derive(Default)]
+/// Party's seat in a room
+struct Seat {
+    /// Identity the party joined with
+    identity: Option<Vec<u8>>,
+}
+
+/// Stable identity presented by a party
+///
+/// Party index is derived from position of `id` in sorted list of `parties`, so it doesn't
+/// depend on the order in which parties join the room.
+struct Identity {
+    id: Vec<u8>,
+    parties: Vec<Vec<u8>>,
+}
+
+/// Identities of parties in a room which assigns indexes deterministically
+struct Identities {
+    sorted: Vec<Vec<u8>>,
+    joined: HashSet<Vec<u8>>,
+}
+
 struct Room {
     idx: AtomicU32,
+    identities: Mutex<Option<Identities>>,
     parties_count: AtomicU32,
     messages: RwLock<Vec<Vec<u8>>>,
+    /// Index of the furthest message delivered to each party (exclusive)
+    delivered: Mutex<HashMap<u32, usize>>,
+    bytes_buffered: AtomicUsize,
     changed: Notify,
+    token: Option<Vec<u8>>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl Default for Room {
+    fn default() -> Self {
+        Self::new(Arc::new(NoopMetrics))
+    }
 }
 
 impl Room {
-    fn issue_next_party_idx(&self) -> u32 {
-        self.idx.fetch_add(1, Ordering::SeqCst) + 1
+    fn new(metrics: Arc<dyn Metrics>) -> Self {
+        Self {
+            idx: Default::default(),
+            identities: Default::default(),
+            parties_count: Default::default(),
+            messages: Default::default(),
+            delivered: Default::default(),
+            bytes_buffered: Default::default(),
+            changed: Default::default(),
+            token: None,
+            metrics,
+        }
+    }
+
+    /// Sets capability token that parties must present to join the room
+    fn with_token(mut self, token: Option<Vec<u8>>) -> Self {
+        self.token = token;
+        self
+    }
+
+    fn check_token(&self, token: Option<&[u8]>) -> Result<(), Status> {
+        let valid = match (&self.token, token) {
+            (None, None) => true,
+            (Some(expected), Some(token)) => {
+                // Compare in constant time not to reveal how many leading bytes were guessed
+                expected.len() == token.len()
+                    && expected
+                        .iter()
+                        .zip(token)
+                        .fold(0, |acc, (a, b)| acc | (a ^ b))
+                        == 0
+            }
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Status::permission_denied("invalid room-token"))
+        }
+    }
+
+    fn issue_next_party_idx(&self) -> Result<u32, Status> {
+        let identities = self.identities.lock().expect("identities mutex poisoned");
+        if identities.is_some() {
+            return Err(Status::failed_precondition(
+                "room assigns indexes by identity, party-id must be provided",
+            ));
+        }
+        Ok(self.idx.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn issue_party_idx_for(&self, identity: Identity) -> Result<u32, Status> {
+        let mut parties = identity.parties;
+        parties.sort();
+        parties.dedup();
+
+        let mut identities = self.identities.lock().expect("identities mutex poisoned");
+        if identities.is_none() && self.idx.load(Ordering::SeqCst) > 0 {
+            return Err(Status::failed_precondition(
+                "room assigns indexes by join order, party-id must not be provided",
+            ));
+        }
+        let identities = identities.get_or_insert_with(|| Identities {
+            sorted: parties.clone(),
+            joined: HashSet::new(),
+        });
+        if identities.sorted != parties {
+            return Err(Status::invalid_argument(
+                "list of parties differs from one the room was created with",
+            ));
+        }
+        let idx = identities
+            .sorted
+            .binary_search(&identity.id)
+            .map_err(|_| Status::invalid_argument("party-id is not in the list of parties"))?;
+        if !identities.joined.insert(identity.id) {
+            return Err(Status::already_exists("party with this id already joined"));
+        }
+        Ok(idx as u32 + 1)
+    }
+
+    /// Forgets party `party_idx` that disconnected, so it can join the room again under the
+    /// same `identity`
+    fn leave(&self, party_idx: u32, identity: Option<&[u8]>) {
+        if let Some(id) = identity {
+            let mut identities = self.identities.lock().expect("identities mutex poisoned");
+            if let Some(identities) = identities.as_mut() {
+                identities.joined.remove(id);
+            }
+        }
+        self.forget_delivered(party_idx)
+    }
+
+    fn forget_delivered(&self, party_idx: u32) {
+        let mut delivered = self.delivered.lock().expect("delivered mutex poisoned");
+        delivered.remove(&party_idx);
     }
 
     fn party_connected(&self) {
@@ -185,15 +502,41 @@ impl Room {
         self.parties_count.load(Ordering::SeqCst) == 0
     }
 
+    fn bytes_buffered(&self) -> usize {
+        self.bytes_buffered.load(Ordering::SeqCst)
+    }
+
     async fn add_msg(&self, msg: Vec<u8>) {
+        let len = msg.len();
         let mut history = self.messages.write().await;
         history.push(msg);
         drop(history);
+        self.bytes_buffered.fetch_add(len, Ordering::SeqCst);
+        self.metrics.increment(Counter::MessagesReceived, 1);
+        self.metrics.adjust(Gauge::BytesBuffered, len as f64);
         self.changed.notify_waiters()
     }
 
-    async fn recv(&self, msg_id: usize, buffer: &mut Vec<Vec<u8>>) -> usize {
-        loop {
+    /// Waits for messages starting at `msg_id`, and puts at most `page_size` of them into the
+    /// buffer. Returns index of the next message to receive.
+    ///
+    /// Party `party_idx` may go back at most [RECV_REWIND_WINDOW] messages behind the furthest
+    /// message it has been delivered, otherwise [Status::out_of_range] is returned.
+    async fn recv(
+        &self,
+        party_idx: u32,
+        msg_id: usize,
+        page_size: usize,
+        buffer: &mut Vec<Vec<u8>>,
+    ) -> Result<usize, Status> {
+        let delivered = self.delivered_to(party_idx);
+        if msg_id.saturating_add(RECV_REWIND_WINDOW) < delivered {
+            return Err(Status::out_of_range(format!(
+                "party {} was already delivered messages up to {}, can't rewind to {}",
+                party_idx, delivered, msg_id
+            )));
+        }
+        let end = loop {
             let history = self.messages.read().await;
             if history.len() <= msg_id {
                 let notified = self.changed.notified();
@@ -201,12 +544,23 @@ impl Room {
                 notified.await;
                 continue;
             }
-            buffer.extend_from_slice(&history[msg_id..]);
-            let len = history.len();
+            let end = history.len().min(msg_id.saturating_add(page_size));
+            buffer.extend_from_slice(&history[msg_id..end]);
 
             drop(history);
-            break len;
-        }
+            self.metrics
+                .increment(Counter::MessagesForwarded, (end - msg_id) as u64);
+            break end;
+        };
+        let mut delivered = self.delivered.lock().expect("delivered mutex poisoned");
+        let furthest = delivered.entry(party_idx).or_insert(0);
+        *furthest = (*furthest).max(end);
+        Ok(end)
+    }
+
+    fn delivered_to(&self, party_idx: u32) -> usize {
+        let delivered = self.delivered.lock().expect("delivered mutex poisoned");
+        delivered.get(&party_idx).copied().unwrap_or(0)
     }
 }
 
@@ -292,6 +646,277 @@ pub mod test {
         assert_eq!(indexes, expected_indexes)
     }
 
+    #[tokio::test]
+    async fn server_assigns_idx_by_sorted_identity() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        for (id, expected_idx) in [("carol", 3u32), ("alice", 1), ("bob", 2)].iter() {
+            let mut client = stand.connect_client().await;
+            let response: tonic::Response<_> = client
+                .join(join_room_as(
+                    "testing-room",
+                    id,
+                    "alice,bob,carol",
+                    stream::pending(),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(
+                response.metadata().get("party-idx"),
+                Some(&tonic::metadata::MetadataValue::from(*expected_idx))
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn server_rejects_unknown_or_repeated_identity() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let mut client = stand.connect_client().await;
+        let _alice = client
+            .join(join_room_as(
+                "testing-room",
+                "alice",
+                "alice,bob",
+                stream::pending(),
+            ))
+            .await
+            .unwrap();
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(join_room_as(
+                "testing-room",
+                "alice",
+                "alice,bob",
+                stream::pending(),
+            ))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::AlreadyExists);
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(join_room_as(
+                "testing-room",
+                "mallory",
+                "alice,bob",
+                stream::pending(),
+            ))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(join_room("testing-room", stream::pending()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn party_can_rejoin_after_disconnect() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let (alice_outcoming, alice_rx) = mpsc::unbounded::<Msg>();
+        let mut client = stand.connect_client().await;
+        let mut alice_incoming = client
+            .join(join_room_as("testing-room", "alice", "alice,bob", alice_rx))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // Server closes the stream once it handles EOF, and releases alice's identity
+        drop(alice_outcoming);
+        assert_eq!(alice_incoming.message().await.unwrap(), None);
+
+        let mut client = stand.connect_client().await;
+        let response: tonic::Response<_> = client
+            .join(join_room_as(
+                "testing-room",
+                "alice",
+                "alice,bob",
+                stream::pending(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.metadata().get("party-idx"),
+            Some(&tonic::metadata::MetadataValue::from(1u32))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejoining_party_resumes_from_its_offset() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let (bob_outcoming, bob_rx) = mpsc::unbounded();
+        let mut client = stand.connect_client().await;
+        let _bob = client
+            .join(join_room_as("testing-room", "bob", "alice,bob", bob_rx))
+            .await
+            .unwrap();
+
+        let msgs: Vec<_> = (0u8..3)
+            .map(|i| Msg {
+                payload: vec![i; 4],
+            })
+            .collect();
+        bob_outcoming.unbounded_send(msgs[0].clone()).unwrap();
+        bob_outcoming.unbounded_send(msgs[1].clone()).unwrap();
+
+        let (alice_outcoming, alice_rx) = mpsc::unbounded::<Msg>();
+        let mut client = stand.connect_client().await;
+        let mut alice_incoming = client
+            .join(join_room_as("testing-room", "alice", "alice,bob", alice_rx))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            alice_incoming.message().await.unwrap(),
+            Some(msgs[0].clone())
+        );
+        assert_eq!(
+            alice_incoming.message().await.unwrap(),
+            Some(msgs[1].clone())
+        );
+        drop(alice_outcoming);
+        assert_eq!(alice_incoming.message().await.unwrap(), None);
+
+        bob_outcoming.unbounded_send(msgs[2].clone()).unwrap();
+
+        let mut client = stand.connect_client().await;
+        let mut alice_incoming = client
+            .join(resume_from(
+                join_room_as("testing-room", "alice", "alice,bob", stream::pending()),
+                2,
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            alice_incoming.message().await.unwrap(),
+            Some(msgs[2].clone())
+        );
+    }
+
+    #[tokio::test]
+    async fn server_answers_ping() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let mut client = stand.connect_client().await;
+        let pong = client.ping(PingRequest {}).await.unwrap().into_inner();
+        assert_eq!(pong.rooms_active, 0);
+
+        let _party = client
+            .join(join_room("testing-room", stream::pending()))
+            .await
+            .unwrap();
+        let pong = client.ping(PingRequest {}).await.unwrap().into_inner();
+        assert_eq!(pong.rooms_active, 1);
+    }
+
+    #[tokio::test]
+    async fn server_rejects_wrong_room_token() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        let mut client = stand.connect_client().await;
+        let token = create_room(&mut client, "testing-room").await;
+        let _first = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                &token,
+            ))
+            .await
+            .unwrap();
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                "guess",
+            ))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(join_room("testing-room", stream::pending()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        let mut client = stand.connect_client().await;
+        let response: tonic::Response<_> = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.metadata().get("party-idx"),
+            Some(&tonic::metadata::MetadataValue::from(2u32))
+        );
+    }
+
+    #[tokio::test]
+    async fn room_token_is_issued_by_server() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::new().await;
+
+        // Party can't claim a room by joining it first with its own token
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                "secret",
+            ))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+        let token = create_room(&mut client, "testing-room").await;
+        let result = client
+            .create_room(CreateRoomRequest {
+                room_id: "testing-room".to_string(),
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::AlreadyExists);
+
+        let _party = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                &token,
+            ))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn server_requires_room_token_if_configured() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::with_server(Server::new().require_room_token(true)).await;
+
+        let mut client = stand.connect_client().await;
+        let result = client
+            .join(join_room("testing-room", stream::pending()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::PermissionDenied);
+
+        let mut client = stand.connect_client().await;
+        let token = create_room(&mut client, "testing-room").await;
+        let _party = client
+            .join(with_token(
+                join_room("testing-room", stream::pending()),
+                &token,
+            ))
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn delivers_message_to_everyone() {
         let _ = tracing_subscriber::fmt::try_init();
@@ -491,6 +1116,174 @@ pub mod test {
         };
     }
 
+    #[tokio::test]
+    async fn room_delivers_history_page_by_page() {
+        const MESSAGES: usize = 5000;
+        const PAGE_SIZE: usize = 128;
+
+        let room = Room::default();
+        for i in 0..MESSAGES {
+            room.add_msg((i as u32).to_be_bytes().to_vec()).await;
+        }
+
+        let mut received = vec![];
+        let mut next_msg_idx = 0;
+        let mut buffer = vec![];
+        while next_msg_idx < MESSAGES {
+            let idx = room
+                .recv(1, next_msg_idx, PAGE_SIZE, &mut buffer)
+                .await
+                .unwrap();
+            assert!(buffer.len() <= PAGE_SIZE);
+            assert_eq!(idx - next_msg_idx, buffer.len());
+            next_msg_idx = idx;
+            received.extend(buffer.drain(..));
+        }
+
+        assert_eq!(received.len(), MESSAGES);
+        for (i, msg) in received.iter().enumerate() {
+            assert_eq!(msg[..], (i as u32).to_be_bytes()[..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn room_bounds_rewinding_of_history() {
+        const MESSAGES: usize = 100;
+
+        let room = Room::default();
+        for i in 0..MESSAGES {
+            room.add_msg((i as u32).to_be_bytes().to_vec()).await;
+        }
+
+        let mut buffer = vec![];
+        assert_eq!(
+            room.recv(1, 0, MESSAGES, &mut buffer).await.unwrap(),
+            MESSAGES
+        );
+        buffer.clear();
+
+        // Replaying the whole history is refused, repeatedly
+        for _ in 0..2 {
+            let err = room.recv(1, 0, MESSAGES, &mut buffer).await.unwrap_err();
+            assert_eq!(err.code(), tonic::Code::OutOfRange);
+            assert!(buffer.is_empty());
+        }
+
+        // Going back within the window is fine
+        let rewind_to = MESSAGES - RECV_REWIND_WINDOW;
+        assert_eq!(
+            room.recv(1, rewind_to, MESSAGES, &mut buffer)
+                .await
+                .unwrap(),
+            MESSAGES
+        );
+        assert_eq!(buffer.len(), RECV_REWIND_WINDOW);
+        let err = room
+            .recv(1, rewind_to - 1, MESSAGES, &mut buffer)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::OutOfRange);
+
+        // Other parties are tracked separately
+        buffer.clear();
+        assert_eq!(room.recv(2, 0, 10, &mut buffer).await.unwrap(), 10);
+    }
+
+    #[derive(Default)]
+    struct RecordedMetrics {
+        counters: Mutex<HashMap<Counter, u64>>,
+        gauges: Mutex<HashMap<Gauge, f64>>,
+        observations: Mutex<HashMap<Histogram, usize>>,
+    }
+
+    impl Metrics for RecordedMetrics {
+        fn increment(&self, counter: Counter, value: u64) {
+            *self.counters.lock().unwrap().entry(counter).or_default() += value
+        }
+        fn adjust(&self, gauge: Gauge, delta: f64) {
+            *self.gauges.lock().unwrap().entry(gauge).or_default() += delta
+        }
+        fn observe(&self, histogram: Histogram, _value: f64) {
+            *self
+                .observations
+                .lock()
+                .unwrap()
+                .entry(histogram)
+                .or_default() += 1
+        }
+    }
+
+    #[tokio::test]
+    async fn server_reports_metrics() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let metrics = Arc::new(RecordedMetrics::default());
+        let stand = Stand::with_server(Server::new().with_metrics(metrics.clone())).await;
+
+        let mut party1 = stand.connect_client().await;
+        let (party1_outcoming, party1_rx) = mpsc::unbounded();
+        let mut party1_incoming = party1
+            .join(join_room("testing-room", party1_rx))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut party2 = stand.connect_client().await;
+        let mut party2_incoming = party2
+            .join(join_room("testing-room", stream::pending()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let msg = Msg {
+            payload: b"hello".to_vec(),
+        };
+        party1_outcoming.unbounded_send(msg.clone()).unwrap();
+        assert_eq!(party1_incoming.message().await.unwrap(), Some(msg.clone()));
+        assert_eq!(party2_incoming.message().await.unwrap(), Some(msg));
+
+        let counters = metrics.counters.lock().unwrap().clone();
+        assert_eq!(counters.get(&Counter::MessagesReceived), Some(&1));
+        assert_eq!(counters.get(&Counter::MessagesForwarded), Some(&2));
+        let gauges = metrics.gauges.lock().unwrap().clone();
+        assert_eq!(gauges.get(&Gauge::RoomsActive), Some(&1.));
+        assert_eq!(gauges.get(&Gauge::BytesBuffered), Some(&5.));
+        let observations = metrics.observations.lock().unwrap().clone();
+        assert_eq!(observations.get(&Histogram::JoinLatency), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn lately_joint_party_receives_long_history() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let stand = Stand::with_server(Server::with_page_size(10)).await;
+
+        let mut party1 = stand.connect_client().await;
+        let (party1_outcoming, party1_rx) = mpsc::unbounded();
+        let mut party1_incoming = party1
+            .join(join_room("testing-room", party1_rx))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let msgs: Vec<_> = (0..35u32)
+            .map(|i| Msg {
+                payload: i.to_be_bytes().to_vec(),
+            })
+            .collect();
+        for msg in &msgs {
+            party1_outcoming.unbounded_send(msg.clone()).unwrap();
+            assert_eq!(party1_incoming.message().await.unwrap(), Some(msg.clone()));
+        }
+
+        let mut party2 = stand.connect_client().await;
+        let mut party2_incoming = party2
+            .join(join_room("testing-room", stream::pending()))
+            .await
+            .unwrap()
+            .into_inner();
+        for msg in &msgs {
+            assert_eq!(party2_incoming.message().await.unwrap(), Some(msg.clone()));
+        }
+    }
+
     pub struct Stand {
         server_handler: tokio::task::JoinHandle<Result<(), tonic::transport::Error>>,
         server_addr: std::net::SocketAddr,
@@ -498,9 +1291,13 @@ pub mod test {
 
     impl Stand {
         pub async fn new() -> Self {
+            Self::with_server(Server::new()).await
+        }
+
+        pub async fn with_server(server: Server) -> Self {
             let incoming_clients = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
             let server_addr = incoming_clients.local_addr().unwrap();
-            let mediator = proto::mediator_server::MediatorServer::new(Arc::new(Server::new()));
+            let mediator = proto::mediator_server::MediatorServer::new(Arc::new(server));
             let serve = transport::Server::builder()
                 .add_service(mediator)
                 .serve_with_incoming(wrappers::TcpListenerStream::new(incoming_clients));
@@ -541,4 +1338,43 @@ pub mod test {
             .insert("room-id", MetadataValue::from_str(room_id).unwrap());
         request
     }
+
+    async fn create_room(
+        client: &mut proto::mediator_client::MediatorClient<tonic::transport::Channel>,
+        room_id: &str,
+    ) -> String {
+        client
+            .create_room(CreateRoomRequest {
+                room_id: room_id.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .room_token
+    }
+
+    fn with_token<S>(mut request: Request<S>, token: &str) -> Request<S> {
+        request
+            .metadata_mut()
+            .insert("room-token", MetadataValue::from_str(token).unwrap());
+        request
+    }
+
+    fn resume_from<S>(mut request: Request<S>, offset: usize) -> Request<S> {
+        request
+            .metadata_mut()
+            .insert("resume-from", MetadataValue::from(offset));
+        request
+    }
+
+    fn join_room_as<S>(room_id: &str, party_id: &str, parties: &str, outcoming: S) -> Request<S> {
+        let mut request = join_room(room_id, outcoming);
+        request
+            .metadata_mut()
+            .insert("party-id", MetadataValue::from_str(party_id).unwrap());
+        request
+            .metadata_mut()
+            .insert("parties", MetadataValue::from_str(parties).unwrap());
+        request
+    }
 }
@@ -0,0 +1,44 @@
+//! Runs keygen between parties living in different threads without any async runtime
+//!
+//! Every party is driven by [run_sync] over a blocking in-memory transport built on
+//! `std::sync::mpsc`. A real deployment would use e.g. a plain TCP loop instead.
+
+use std::sync::mpsc;
+use std::thread;
+
+use bls::threshold_bls::state_machine::keygen::Keygen;
+use bls::threshold_bls::state_machine::sync::run_sync;
+
+fn main() {
+    let (t, n) = (1, 3);
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::channel()).unzip();
+
+    let parties: Vec<_> = (1..=n)
+        .zip(receivers)
+        .map(|(i, incoming)| {
+            let outgoing = senders.clone();
+            thread::spawn(move || {
+                let keygen = Keygen::new(i, t, n).expect("construct keygen");
+                // Every message is sent to everyone, run_sync filters out messages that are
+                // not addressed to this party
+                run_sync(
+                    keygen,
+                    || incoming.recv().expect("channel closed"),
+                    |msg| {
+                        for party in &outgoing {
+                            party.send(msg.clone()).expect("channel closed")
+                        }
+                    },
+                )
+            })
+        })
+        .collect();
+
+    for (i, party) in (1..).zip(parties) {
+        let local_key = party
+            .join()
+            .expect("party panicked")
+            .expect("keygen failed");
+        println!("Party {} public key: {:?}", i, local_key.public_key());
+    }
+}
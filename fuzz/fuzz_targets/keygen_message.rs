@@ -0,0 +1,26 @@
+//! Feeds arbitrary bytes received from a peer to keygen
+//!
+//! Malformed message must be rejected with an error, it must never crash a party.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use round_based::{Msg, StateMachine};
+
+use bls::threshold_bls::party_i::{KeyGenComm, KeyGenDecom};
+use bls::threshold_bls::state_machine::keygen::{Keygen, ProtocolMessage};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = serde_json::from_slice::<KeyGenComm>(data);
+    let _ = serde_json::from_slice::<KeyGenDecom>(data);
+
+    if let Ok(body) = serde_json::from_slice::<ProtocolMessage>(data) {
+        let mut party = Keygen::new(1, 1, 3).unwrap();
+        for &receiver in &[None, Some(1)] {
+            let _ = party.handle_incoming(Msg {
+                sender: 2,
+                receiver,
+                body: body.clone(),
+            });
+        }
+    }
+});
@@ -0,0 +1,25 @@
+//! Parses partial signatures from arbitrary bytes, both JSON and binary encodings
+//!
+//! Parsing must never panic, and successfully parsed partial signature must survive
+//! serialization round trip.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bls::threshold_bls::party_i::PartialSignature;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(partial) = serde_json::from_slice::<PartialSignature>(data) {
+        let json = serde_json::to_vec(&partial).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<PartialSignature>(&json).unwrap(),
+            partial
+        );
+    }
+    if let Ok(partial) = PartialSignature::from_bytes(data) {
+        assert_eq!(
+            PartialSignature::from_bytes(&partial.to_bytes()).unwrap(),
+            partial
+        );
+    }
+});
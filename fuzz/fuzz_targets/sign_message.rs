@@ -0,0 +1,26 @@
+//! Feeds arbitrary bytes received from a peer to signing protocol
+//!
+//! Malformed message must be rejected with an error, it must never crash a party.
+
+#![no_main]
+use curv::elliptic::curves::traits::ECScalar;
+use libfuzzer_sys::fuzz_target;
+use round_based::{Msg, StateMachine};
+
+use bls::threshold_bls::state_machine::keygen::deal;
+use bls::threshold_bls::state_machine::sign::{ProtocolMessage, Sign};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(body) = serde_json::from_slice::<ProtocolMessage>(data) {
+        let keys = deal(&ECScalar::new_random(), 1, 2).unwrap();
+        let mut party = Sign::new(b"fuzz".to_vec(), 1, 2, keys[0].clone()).unwrap();
+        let _ = party.handle_incoming(Msg {
+            sender: 2,
+            receiver: None,
+            body,
+        });
+        if party.wants_to_proceed() {
+            let _ = party.proceed();
+        }
+    }
+});
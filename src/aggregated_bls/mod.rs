@@ -3,17 +3,52 @@
 use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
 use curv::cryptographic_primitives::hashing::traits::Hash;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
-use curv::elliptic::curves::traits::ECScalar;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 use curv::BigInt;
+use pairing_plus::bls12_381::G2Affine;
+use pairing_plus::serdes::SerDes;
 
 pub mod party_i;
+pub mod state_machine;
 #[cfg(any(test, feature = "dev"))]
 pub mod test;
 
+/// Computes coefficient of party `pk_vec[index]` in aggregated public key
+///
+/// `pk_vec` may be given in any order: the coefficient is computed over
+/// [canonicalized](canonicalize) public keys, the same way as in
+/// [aggregate](party_i::Keys::aggregate).
 pub fn h1(index: usize, pk_vec: &[GE2]) -> BigInt {
-    let mut pk = vec![&pk_vec[index]];
-    let pk_ref_vec: Vec<_> = pk_vec.iter().map(|k| k).collect();
-    pk.extend_from_slice(&pk_ref_vec[..]);
-    let result1 = HSha256::create_hash_from_ge(&pk);
-    result1.to_big_int()
+    coefficient(&pk_vec[index], &canonicalize(pk_vec))
+}
+
+/// Computes coefficient of party with public key `party_pk` in aggregated public key
+///
+/// Unlike [h1], takes the party public key by value, so coefficients can be recomputed by anyone
+/// knowing only the public keys of the group. `all_pks` must be in the same order as used in
+/// aggregation, i.e. [canonicalized](canonicalize).
+pub fn coefficient(party_pk: &GE2, all_pks: &[GE2]) -> BigInt {
+    let pk: Vec<_> = std::iter::once(party_pk).chain(all_pks).collect();
+    HSha256::create_hash_from_ge(&pk).to_big_int()
+}
+
+/// Sorts public keys by their compressed encoding
+///
+/// Coefficients of the aggregated public key depend on the order of public keys, so parties
+/// must agree on it. [aggregate](party_i::Keys::aggregate) and
+/// [local_sign](party_i::Keys::local_sign) canonicalize public keys themselves, so parties
+/// computing APK from the same set of keys get the same result regardless of the order they
+/// received keys in.
+pub fn canonicalize(pk_vec: &[GE2]) -> Vec<GE2> {
+    let mut keyed: Vec<_> = pk_vec
+        .iter()
+        .map(|pk| {
+            let mut bytes = vec![];
+            G2Affine::serialize(&pk.get_element(), &mut bytes, true)
+                .expect("serialization into vec never fails");
+            (bytes, *pk)
+        })
+        .collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.into_iter().map(|(_, pk)| pk).collect()
 }
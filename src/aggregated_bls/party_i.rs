@@ -7,9 +7,12 @@ use curv::elliptic::curves::bls12_381::Pair;
 use curv::elliptic::curves::traits::ECPoint;
 use curv::elliptic::curves::traits::ECScalar;
 use curv::BigInt;
+use pairing_plus::bls12_381::G2Affine;
+use pairing_plus::serdes::SerDes;
+use pairing_plus::CurveAffine;
 
-use crate::aggregated_bls::h1;
-use crate::basic_bls::BLSSignature;
+use crate::aggregated_bls::{canonicalize, coefficient};
+use crate::basic_bls::{hash_to_g1, hash_to_g1_with_dst, BLSSignature};
 
 /// This is an implementation of BDN18 [https://eprint.iacr.org/2018/483.pdf]
 /// protocol 3.1 (MSP): pairing-based multi-signature with public-key aggregation
@@ -23,6 +26,9 @@ pub struct Keys {
 pub type APK = GE2;
 pub type SIG = GE1;
 
+/// Prefix of message signed in proof of possession, separates it from ordinary signatures
+const POP_PREFIX: &[u8] = b"MULTI_PARTY_BLS_POP_";
+
 impl Keys {
     pub fn new(index: usize) -> Self {
         let u = ECScalar::new_random();
@@ -35,32 +41,80 @@ impl Keys {
         }
     }
 
+    /// Aggregates public keys into APK `sum(a_i * pk_i)`
+    ///
+    /// Public keys are [canonicalized](canonicalize) first, so the result doesn't depend on
+    /// their order. Note that APKs computed before canonicalization was introduced were
+    /// weighted in the order keys were given, and generally differ from the ones computed now.
     pub fn aggregate(pk_vec: &[GE2]) -> APK {
-        let apk_plus_g = pk_vec.iter().fold(GE2::generator(), |acc, x| {
-            let i = pk_vec.iter().position(|y| y == x).unwrap();
-            acc + (pk_vec[i] * &ECScalar::from(&h1(i, pk_vec)))
+        let pk_vec = canonicalize(pk_vec);
+        let apk_plus_g = pk_vec.iter().fold(GE2::generator(), |acc, pk| {
+            acc + (*pk * &ECScalar::from(&coefficient(pk, &pk_vec)))
         });
         apk_plus_g.sub_point(&GE2::generator().get_element())
     }
 
+    /// Signs `message` with secret key weighted by coefficient `a_i = H1(pk_i, pk_vec)`
+    ///
+    /// `pk_vec` is [canonicalized](canonicalize) the same way as in [aggregate](Self::aggregate),
+    /// so it may be given in any order, but it must contain `pk_i`.
     pub fn local_sign(&self, message: &[u8], pk_vec: &[GE2]) -> SIG {
-        let a_i = h1(self.party_index.clone(), pk_vec);
+        self.local_sign_hashed(hash_to_g1(message), pk_vec)
+    }
+
+    /// Same as [local_sign](Self::local_sign), but message is hashed under domain separation
+    /// tag `dst` (see [hash_to_g1_with_dst])
+    pub fn local_sign_with_dst(&self, message: &[u8], dst: &[u8], pk_vec: &[GE2]) -> SIG {
+        self.local_sign_hashed(hash_to_g1_with_dst(message, dst), pk_vec)
+    }
+
+    fn local_sign_hashed(&self, h_0_m: GE1, pk_vec: &[GE2]) -> SIG {
+        let a_i = coefficient(&self.pk_i, &canonicalize(pk_vec));
         let exp = BigInt::mod_mul(&a_i, &self.sk_i.to_big_int(), &FE1::q());
         let exp_fe1: FE1 = ECScalar::from(&exp);
-        let h_0_m = GE1::hash_to_curve(message);
         h_0_m * exp_fe1
     }
 
+    /// Sums up local signatures
+    ///
+    /// If all parties signed the same message, the result is an ordinary BLS signature under
+    /// secret key `sum(a_i * sk_i)`, whose public key is exactly the APK. Thus it verifies via
+    /// [BLSSignature::verify] with APK as a public key.
     pub fn combine_local_signatures(sigs: &[SIG]) -> BLSSignature {
         let (head, tail) = sigs.split_at(1);
         let sig_sum = tail.iter().fold(head[0], |acc, x| acc + x);
         BLSSignature { sigma: sig_sum }
     }
 
+    /// Proves possession of secret key by signing own public key
+    ///
+    /// Parties must check proofs of possession of each other (see
+    /// [verify_possession](Self::verify_possession)) before aggregating public keys, otherwise
+    /// a party may choose its public key as a function of others' keys.
+    pub fn prove_possession(&self) -> BLSSignature {
+        let sk: FE1 = ECScalar::from(&self.sk_i.to_big_int());
+        BLSSignature {
+            sigma: hash_to_g1(&pop_message(&self.pk_i)) * &sk,
+        }
+    }
+
+    /// Verifies proof of possession produced by [prove_possession](Self::prove_possession)
+    pub fn verify_possession(pk_i: &GE2, pop: &BLSSignature) -> bool {
+        pop.verify(&pop_message(pk_i), pk_i)
+    }
+
     pub fn verify(signature: &BLSSignature, message: &[u8], apk: &APK) -> bool {
         signature.verify(message, apk)
     }
 
+    /// Verifies multisignature produced by parties who all signed the same `message`
+    ///
+    /// This is the common n-of-n case: signature is checked against aggregated public key
+    /// (see [aggregate](Self::aggregate)) with a single pairing check.
+    pub fn verify_same_message(apk: &APK, message: &[u8], sig: &BLSSignature) -> bool {
+        sig.verify(message, apk)
+    }
+
     pub fn batch_aggregate_bls(sig_vec: &[BLSSignature]) -> BLSSignature {
         let (head, tail) = sig_vec.split_at(1);
         BLSSignature {
@@ -68,10 +122,9 @@ impl Keys {
         }
     }
 
-    fn core_aggregate_verify(apk_vec: &[APK], msg_vec: &[&[u8]], sig: &BLSSignature) -> bool {
+    fn core_aggregate_verify(apk_vec: &[APK], vec_g1: &[GE1], sig: &BLSSignature) -> bool {
         assert!(apk_vec.len() >= 1);
         let product_c2 = Pair::compute_pairing(&sig.sigma, &GE2::generator());
-        let vec_g1: Vec<GE1> = msg_vec.iter().map(|&x| GE1::hash_to_curve(&x)).collect();
         let vec: Vec<_> = vec_g1.iter().zip(apk_vec.iter()).collect();
         let (head, tail) = vec.split_at(1);
         let product_c1 = tail
@@ -82,8 +135,16 @@ impl Keys {
         product_c1.e == product_c2.e
     }
 
+    /// Verifies aggregated signature of distinct messages, `msg_vec[i]` is signed under `apk_vec[i]`
+    ///
+    /// Verification fails if any message is repeated, if any APK is the identity point (it's
+    /// satisfied by a signature that doesn't depend on the message), or if any APK is repeated
+    /// (every group is expected to contribute exactly one signature to the aggregate).
     pub fn aggregate_verify(apk_vec: &[APK], msg_vec: &[&[u8]], sig: &BLSSignature) -> bool {
         assert!(apk_vec.len() == msg_vec.len());
+        if !Keys::apks_are_valid(apk_vec) {
+            return false;
+        }
         if {
             let mut tmp = msg_vec.to_vec();
             tmp.sort();
@@ -92,6 +153,111 @@ impl Keys {
         } {
             return false; // verification fails if there is a repeated message
         }
-        Keys::core_aggregate_verify(apk_vec, msg_vec, sig)
+        let vec_g1: Vec<GE1> = msg_vec.iter().map(|&x| hash_to_g1(&x)).collect();
+        Keys::core_aggregate_verify(apk_vec, &vec_g1, sig)
+    }
+
+    /// Same as [aggregate_verify](Self::aggregate_verify), but each message is hashed under its
+    /// own domain separation tag: `msg_and_dst[i] = (message, dst)` is signed under `apk_vec[i]`
+    ///
+    /// Allows verifying signatures from different contexts in a single pairing check while
+    /// keeping their domains separated. Signers should use
+    /// [local_sign_with_dst](Self::local_sign_with_dst). The same message may appear under
+    /// distinct tags, but verification fails if any `(message, dst)` pair is repeated.
+    pub fn aggregate_verify_with_dsts(
+        apk_vec: &[APK],
+        msg_and_dst: &[(&[u8], &[u8])],
+        sig: &BLSSignature,
+    ) -> bool {
+        assert!(apk_vec.len() == msg_and_dst.len());
+        if !Keys::apks_are_valid(apk_vec) {
+            return false;
+        }
+        if msg_and_dst
+            .iter()
+            .enumerate()
+            .any(|(i, pair)| msg_and_dst[..i].contains(pair))
+        {
+            return false;
+        }
+        let vec_g1: Vec<GE1> = msg_and_dst
+            .iter()
+            .map(|&(message, dst)| hash_to_g1_with_dst(message, dst))
+            .collect();
+        Keys::core_aggregate_verify(apk_vec, &vec_g1, sig)
+    }
+
+    /// Rejects identity APK (it's satisfied by a signature that doesn't depend on the message)
+    /// and repeated APKs
+    fn apks_are_valid(apk_vec: &[APK]) -> bool {
+        !apk_vec.iter().any(|apk| apk.get_element().is_zero())
+            && !apk_vec
+                .iter()
+                .enumerate()
+                .any(|(i, apk)| apk_vec[..i].contains(apk))
+    }
+}
+
+/// Aggregated public key of a group whose membership changes over time
+///
+/// Caches the set of members' public keys along with their APK. Note that coefficient of every
+/// member `a_i = H1(pk_i, pk_vec)` depends on the whole set, so any membership change alters
+/// every term of the APK: [add](Self::add) and [remove](Self::remove) have to recompute it
+/// from scratch, at a cost of `n` scalar multiplications. The state spares only bookkeeping
+/// of the set. Result always equals [Keys::aggregate] of [pk_vec](Self::pk_vec).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateState {
+    pk_vec: Vec<GE2>,
+    apk: APK,
+}
+
+impl AggregateState {
+    /// Constructs aggregation state of group `pk_vec`
+    ///
+    /// Repeated public keys are kept only once.
+    pub fn new(pk_vec: &[GE2]) -> Self {
+        let mut pk_vec = canonicalize(pk_vec);
+        pk_vec.dedup();
+        let apk = Keys::aggregate(&pk_vec);
+        Self { pk_vec, apk }
+    }
+
+    /// Current aggregated public key
+    pub fn apk(&self) -> &APK {
+        &self.apk
     }
+
+    /// Public keys of current members in [canonical](canonicalize) order
+    pub fn pk_vec(&self) -> &[GE2] {
+        &self.pk_vec
+    }
+
+    /// Adds a member, returns `false` if `pk` is already a member
+    pub fn add(&mut self, pk: GE2) -> bool {
+        if self.pk_vec.contains(&pk) {
+            return false;
+        }
+        self.pk_vec.push(pk);
+        self.pk_vec = canonicalize(&self.pk_vec);
+        self.apk = Keys::aggregate(&self.pk_vec);
+        true
+    }
+
+    /// Removes a member, returns `false` if `pk` is not a member
+    pub fn remove(&mut self, pk: &GE2) -> bool {
+        let position = match self.pk_vec.iter().position(|member| member == pk) {
+            Some(position) => position,
+            None => return false,
+        };
+        self.pk_vec.remove(position);
+        self.apk = Keys::aggregate(&self.pk_vec);
+        true
+    }
+}
+
+fn pop_message(pk_i: &GE2) -> Vec<u8> {
+    let mut message = POP_PREFIX.to_vec();
+    G2Affine::serialize(&pk_i.get_element(), &mut message, true)
+        .expect("serialization into vec never fails");
+    message
 }
@@ -0,0 +1,456 @@
+//! Distributed setup of multisignature scheme
+//!
+//! Every party generates its key pair and broadcasts public key along with proof of
+//! possession. Once all public keys are received and their proofs are verified, every party
+//! computes the same aggregated public key.
+
+use std::fmt;
+use std::mem::replace;
+use std::time::Duration;
+
+use curv::elliptic::curves::bls12_381::g1::GE as GE1;
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use round_based::containers::{
+    push::{Push, PushExt},
+    *,
+};
+use round_based::{IsCritical, Msg, StateMachine};
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::threshold_bls::state_machine::{
+    check_msg_kind, deserialize_versioned, UnexpectedMessageKind,
+};
+
+mod rounds;
+pub use rounds::{AggregatedKey, ProceedError};
+use rounds::{Round0, Round1};
+
+/// Keygen protocol state machine of multisignature scheme
+///
+/// Successfully completed protocol produces [AggregatedKey] holding public keys of all the
+/// parties and their aggregated public key. Public key of a party is accepted only along with
+/// valid proof of possession of the secret key, which prevents rogue key attacks.
+pub struct Keygen {
+    round: R,
+
+    msgs1: Option<Store<BroadcastMsgs<(GE2, GE1)>>>,
+
+    msgs_queue: Vec<Msg<ProtocolMessage>>,
+
+    party_i: u16,
+    party_n: u16,
+}
+
+impl Keygen {
+    /// Constructs a party of keygen protocol
+    ///
+    /// Takes party index `i` (in range `[1; n]`) and total number of parties `n`. Party index
+    /// identifies this party in the protocol, so it must be guaranteed to be unique.
+    ///
+    /// Returns error if:
+    /// * `n` is less than 2, returns [Error::TooFewParties]
+    /// * `i` is not in range `[1; n]`, returns [Error::InvalidPartyIndex]
+    pub fn new(i: u16, n: u16) -> Result<Self> {
+        if n < 2 {
+            return Err(Error::TooFewParties);
+        }
+        if i == 0 || i > n {
+            return Err(Error::InvalidPartyIndex);
+        }
+        let mut state = Self {
+            round: R::Round0(Round0 { party_i: i }),
+
+            msgs1: Some(Round1::expects_messages(i, n)),
+
+            msgs_queue: vec![],
+
+            party_i: i,
+            party_n: n,
+        };
+
+        state.proceed_round(false)?;
+        Ok(state)
+    }
+
+    /// Whether protocol is completed and output can be taken via
+    /// [pick_output](StateMachine::pick_output)
+    ///
+    /// Output can be taken only once: after that, `output_ready` returns `false` again.
+    pub fn output_ready(&self) -> bool {
+        matches!(self.round, R::Final(_))
+    }
+
+    fn gmap_queue<'a, T, F>(&'a mut self, mut f: F) -> impl Push<Msg<T>> + 'a
+    where
+        F: FnMut(T) -> M + 'a,
+    {
+        (&mut self.msgs_queue)
+            .gmap(move |m: Msg<T>| m.map_body(|m| ProtocolMessage(PROTOCOL_VERSION, f(m))))
+    }
+
+    /// Proceeds round state if it received enough messages and if it's cheap to compute or
+    /// `may_block == true`
+    fn proceed_round(&mut self, may_block: bool) -> Result<()> {
+        let store1_wants_more = self.msgs1.as_ref().map(|s| s.wants_more()).unwrap_or(false);
+
+        let next_state: R;
+        let try_again: bool = match replace(&mut self.round, R::Gone) {
+            R::Round0(round) if !round.is_expensive() || may_block => {
+                next_state = round
+                    .proceed(self.gmap_queue(M::Round1))
+                    .map(R::Round1)
+                    .map_err(Error::ProceedRound)?;
+                true
+            }
+            s @ R::Round0(_) => {
+                next_state = s;
+                false
+            }
+            R::Round1(round) if !store1_wants_more && (!round.is_expensive() || may_block) => {
+                let store = self.msgs1.take().ok_or(InternalError::StoreGone)?;
+                let msgs = store
+                    .finish()
+                    .map_err(InternalError::RetrieveRoundMessages)?;
+                next_state = round
+                    .proceed(msgs)
+                    .map(R::Final)
+                    .map_err(Error::ProceedRound)?;
+                true
+            }
+            s @ R::Round1(_) => {
+                next_state = s;
+                false
+            }
+            s @ R::Final(_) | s @ R::Gone => {
+                next_state = s;
+                false
+            }
+        };
+
+        self.round = next_state;
+        if try_again {
+            self.proceed_round(may_block)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl StateMachine for Keygen {
+    type MessageBody = ProtocolMessage;
+    type Err = Error;
+    type Output = AggregatedKey;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<()> {
+        let current_round = self.current_round();
+
+        if msg.body.0 != PROTOCOL_VERSION {
+            return Err(Error::UnsupportedProtocolVersion {
+                version: msg.body.0,
+            });
+        }
+
+        match msg.body {
+            ProtocolMessage(_, M::Round1(m)) => {
+                check_msg_kind(1, msg.receiver, true)?;
+                let store = self
+                    .msgs1
+                    .as_mut()
+                    .ok_or(Error::ReceivedOutOfOrderMessage {
+                        current_round,
+                        msg_round: 1,
+                    })?;
+                store
+                    .push_msg(Msg {
+                        sender: msg.sender,
+                        receiver: msg.receiver,
+                        body: m,
+                    })
+                    .map_err(Error::HandleMessage)?;
+                self.proceed_round(false)
+            }
+            ProtocolMessage(version, M::Unsupported) => {
+                Err(Error::UnsupportedProtocolVersion { version })
+            }
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.msgs_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        let store1_wants_more = self.msgs1.as_ref().map(|s| s.wants_more()).unwrap_or(false);
+
+        match &self.round {
+            R::Round0(_) => true,
+            R::Round1(_) => !store1_wants_more,
+            R::Final(_) | R::Gone => false,
+        }
+    }
+
+    fn proceed(&mut self) -> Result<()> {
+        self.proceed_round(true)
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, R::Final(_))
+    }
+
+    /// Takes protocol output
+    ///
+    /// One-shot: returns output once protocol is completed (see
+    /// [output_ready](Keygen::output_ready)), and error on any subsequent call. Returns `None`
+    /// without affecting protocol state if protocol is not completed yet.
+    fn pick_output(&mut self) -> Option<Result<Self::Output>> {
+        match self.round {
+            R::Final(_) => (),
+            R::Gone => return Some(Err(Error::DoublePickOutput)),
+            _ => return None,
+        }
+
+        match replace(&mut self.round, R::Gone) {
+            R::Final(result) => Some(Ok(result)),
+            _ => unreachable!("guaranteed by match expression above"),
+        }
+    }
+
+    fn current_round(&self) -> u16 {
+        match &self.round {
+            R::Round0(_) => 0,
+            R::Round1(_) => 1,
+            R::Final(_) | R::Gone => 2,
+        }
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(1)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.party_i
+    }
+
+    fn parties(&self) -> u16 {
+        self.party_n
+    }
+}
+
+impl fmt::Debug for Keygen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let current_round = match &self.round {
+            R::Round0(_) => "0",
+            R::Round1(_) => "1",
+            R::Final(_) => "[Final]",
+            R::Gone => "[Gone]",
+        };
+        let msgs1 = match self.msgs1.as_ref() {
+            Some(msgs) => format!("[{}/{}]", msgs.messages_received(), msgs.messages_total()),
+            None => "[None]".into(),
+        };
+        write!(
+            f,
+            "{{AggregatedKeygen at round={} msgs1={} queue=[len={}]}}",
+            current_round,
+            msgs1,
+            self.msgs_queue.len()
+        )
+    }
+}
+
+// Rounds
+
+enum R {
+    Round0(Round0),
+    Round1(Round1),
+    Final(AggregatedKey),
+    Gone,
+}
+
+// Messages
+
+/// Protocol message which parties send on wire
+///
+/// Hides actual messages structure so it could be changed without breaking semver policy.
+/// Serialized message is tagged with protocol version, so parties running incompatible versions
+/// of the protocol reject each other's messages. Body of a message of another version isn't
+/// decoded, so such message is reported as [Error::UnsupportedProtocolVersion] rather than
+/// failing to deserialize.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProtocolMessage(u8, M);
+
+impl<'de> Deserialize<'de> for ProtocolMessage {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (version, body) =
+            deserialize_versioned(deserializer, "ProtocolMessage", PROTOCOL_VERSION)?;
+        Ok(ProtocolMessage(version, body.unwrap_or(M::Unsupported)))
+    }
+}
+
+/// Version of protocol messages, must be bumped on every change of message structure
+const PROTOCOL_VERSION: u8 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum M {
+    Round1((GE2, GE1)),
+    /// Body of a message of unsupported protocol version, which is not decoded
+    #[serde(skip)]
+    Unsupported,
+}
+
+// Error
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Error type of multisignature keygen protocol
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Round proceeding resulted in error
+    #[error("proceed round: {0}")]
+    ProceedRound(#[source] ProceedError),
+
+    /// Too few parties (`n < 2`)
+    #[error("at least 2 parties are required for keygen")]
+    TooFewParties,
+    /// Party index `i` is not in range `[1; n]`
+    #[error("party index is not in range [1; n]")]
+    InvalidPartyIndex,
+
+    /// Received message didn't pass pre-validation
+    #[error("received message didn't pass pre-validation: {0}")]
+    HandleMessage(#[source] StoreErr),
+    /// Received message of a round that's already completed
+    ///
+    /// Messages of future rounds are not an error: they're kept until their round begins.
+    #[error(
+        "didn't expect to receive message from round {msg_round} (being at round {current_round})"
+    )]
+    ReceivedOutOfOrderMessage { current_round: u16, msg_round: u16 },
+    /// Received message of protocol version which is not supported by this party
+    #[error("received message of unsupported protocol version {version}")]
+    UnsupportedProtocolVersion { version: u8 },
+    /// Received P2P message in a round that expects broadcast messages, or vice versa
+    #[error(
+        "received message of unexpected kind at round {round} (expected_broadcast={expected_broadcast})"
+    )]
+    UnexpectedMessageKind {
+        round: u16,
+        expected_broadcast: bool,
+    },
+    /// [Keygen::pick_output] called twice
+    #[error("pick_output called twice")]
+    DoublePickOutput,
+
+    /// Some internal assertions were failed, which is a bug
+    #[doc(hidden)]
+    #[error("internal error: {0:?}")]
+    InternalError(InternalError),
+}
+
+impl From<UnexpectedMessageKind> for Error {
+    fn from(err: UnexpectedMessageKind) -> Self {
+        Self::UnexpectedMessageKind {
+            round: err.round,
+            expected_broadcast: err.expected_broadcast,
+        }
+    }
+}
+
+impl IsCritical for Error {
+    fn is_critical(&self) -> bool {
+        true
+    }
+}
+
+impl From<InternalError> for Error {
+    fn from(err: InternalError) -> Self {
+        Self::InternalError(err)
+    }
+}
+
+use private::InternalError;
+mod private {
+    #[derive(Debug)]
+    #[non_exhaustive]
+    pub enum InternalError {
+        /// [Messages store](super::MessageStore) reported that it received all messages it wanted to receive,
+        /// but refused to return message container
+        RetrieveRoundMessages(super::StoreErr),
+        #[doc(hidden)]
+        StoreGone,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use curv::elliptic::curves::traits::ECPoint;
+    use round_based::dev::Simulation;
+
+    use super::*;
+    use crate::aggregated_bls::party_i::Keys;
+
+    #[test]
+    fn simulate_aggregated_keygen() {
+        let mut simulation = Simulation::new();
+        for i in 1..=3 {
+            simulation.add_party(Keygen::new(i, 3).unwrap());
+        }
+        let keys = simulation.run().unwrap();
+
+        assert!(keys.iter().all(|k| k.apk == keys[0].apk));
+        assert!(keys.iter().all(|k| k.pk_vec == keys[0].pk_vec));
+        assert_eq!(keys[0].apk, Keys::aggregate(&keys[0].pk_vec));
+
+        let message = b"~~ MESSAGE ~~";
+        let sigs: Vec<_> = keys.iter().map(|k| k.local_sign(message)).collect();
+        let sig = Keys::combine_local_signatures(&sigs);
+        assert!(Keys::verify(&sig, message, &keys[0].apk));
+    }
+
+    #[test]
+    fn aggregated_keygen_rejects_key_without_pop() {
+        let mut party1 = Keygen::new(1, 2).unwrap();
+        let mut party2 = Keygen::new(2, 2).unwrap();
+        party1.proceed().unwrap();
+        party2.proceed().unwrap();
+
+        // Party 2 claims public key it doesn't know secret key of
+        let mut msg2 = party2.message_queue().pop().unwrap();
+        match &mut msg2.body {
+            ProtocolMessage(_, M::Round1((pk_i, _))) => *pk_i = GE2::generator(),
+            _ => panic!("expected round 1 message"),
+        }
+
+        party1.handle_incoming(msg2).unwrap();
+        match party1.proceed() {
+            Err(Error::ProceedRound(ProceedError::InvalidProofOfPossession { party: 2 })) => (),
+            result => panic!("expected InvalidProofOfPossession, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn aggregated_key_debug_omits_secret_key() {
+        let mut simulation = Simulation::new();
+        for i in 1..=2 {
+            simulation.add_party(Keygen::new(i, 2).unwrap());
+        }
+        let keys = simulation.run().unwrap();
+
+        let debug = format!("{:?}", keys[0]);
+        assert!(!debug.contains("sk_i"));
+        assert!(!debug.contains(&format!("{:?}", keys[0].keys.sk_i)));
+    }
+}
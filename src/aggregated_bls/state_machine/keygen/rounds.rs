@@ -0,0 +1,113 @@
+use std::fmt;
+
+use curv::elliptic::curves::bls12_381::g1::GE as GE1;
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use round_based::containers::push::Push;
+use round_based::containers::{self, BroadcastMsgs, Store};
+use round_based::Msg;
+use thiserror::Error;
+
+use crate::aggregated_bls::party_i::{Keys, APK};
+use crate::basic_bls::BLSSignature;
+
+pub struct Round0 {
+    pub party_i: u16,
+}
+
+impl Round0 {
+    pub fn proceed<O>(self, mut output: O) -> Result<Round1>
+    where
+        O: Push<Msg<(GE2, GE1)>>,
+    {
+        let keys = Keys::new(usize::from(self.party_i) - 1);
+        let pop = keys.prove_possession();
+        output.push(Msg {
+            sender: self.party_i,
+            receiver: None,
+            body: (keys.pk_i, pop.sigma),
+        });
+        Ok(Round1 { keys, pop })
+    }
+    pub fn is_expensive(&self) -> bool {
+        true
+    }
+}
+
+pub struct Round1 {
+    keys: Keys,
+    pop: BLSSignature,
+}
+
+impl Round1 {
+    pub fn proceed(self, input: BroadcastMsgs<(GE2, GE1)>) -> Result<AggregatedKey> {
+        let received = input.into_vec_including_me((self.keys.pk_i, self.pop.sigma));
+
+        let mut pk_vec = Vec::with_capacity(received.len());
+        for (party_i, (pk_i, pop)) in received.into_iter().enumerate() {
+            if !Keys::verify_possession(&pk_i, &BLSSignature { sigma: pop }) {
+                return Err(ProceedError::InvalidProofOfPossession {
+                    party: party_i as u16 + 1,
+                });
+            }
+            pk_vec.push(pk_i);
+        }
+
+        let apk = Keys::aggregate(&pk_vec);
+        Ok(AggregatedKey {
+            keys: self.keys,
+            pk_vec,
+            apk,
+        })
+    }
+    pub fn is_expensive(&self) -> bool {
+        true
+    }
+    pub fn expects_messages(i: u16, n: u16) -> Store<BroadcastMsgs<(GE2, GE1)>> {
+        containers::BroadcastMsgsStore::new(i, n)
+    }
+}
+
+/// Local key of multisignature scheme obtained via distributed [keygen](super::Keygen)
+///
+/// `Debug` output omits the secret key.
+#[derive(Clone)]
+pub struct AggregatedKey {
+    /// Party's key pair, `keys.party_index` is a position of party's public key in `pk_vec`
+    pub keys: Keys,
+    /// Public keys of all parties ordered by party index
+    pub pk_vec: Vec<GE2>,
+    /// Aggregated public key
+    pub apk: APK,
+}
+
+impl fmt::Debug for AggregatedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AggregatedKey")
+            .field("party_index", &self.keys.party_index)
+            .field("n", &self.pk_vec.len())
+            .field("apk", &self.apk)
+            .finish()
+    }
+}
+
+impl AggregatedKey {
+    /// Signs `message`, see [Keys::local_sign]
+    pub fn local_sign(&self, message: &[u8]) -> GE1 {
+        self.keys.local_sign(message, &self.pk_vec)
+    }
+}
+
+// Errors
+
+type Result<T> = std::result::Result<T, ProceedError>;
+
+/// Proceeding protocol error
+///
+/// Subset of [keygen errors](enum@super::Error) that can occur at protocol proceeding (i.e. after
+/// every message was received and pre-validated).
+#[derive(Debug, Error)]
+pub enum ProceedError {
+    /// Party sent public key with invalid proof of possession
+    #[error("party {party} sent invalid proof of possession")]
+    InvalidProofOfPossession { party: u16 },
+}
@@ -1,6 +1,10 @@
-use crate::aggregated_bls::party_i::{Keys, APK};
-use crate::basic_bls::BLSSignature;
+use crate::aggregated_bls::party_i::{AggregateState, Keys, APK};
+use crate::aggregated_bls::{canonicalize, coefficient, h1};
+use crate::basic_bls::{hash_to_g1, BLSSignature};
+use curv::elliptic::curves::bls12_381::g1::FE as FE1;
+use curv::elliptic::curves::bls12_381::g2::FE as FE2;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 
 // test 3 out of 3
 #[test]
@@ -31,6 +35,52 @@ fn agg_sig_test_3() {
     assert_ne!(bls_sig.verify(&[10, 11, 12], &apk), true);
 }
 
+#[test]
+fn agg_sig_same_message() {
+    let (keys_vec, pk_vec, apk) = keygen(4);
+
+    let message = b"block hash";
+    let sig_vec: Vec<_> = keys_vec
+        .iter()
+        .map(|k| k.local_sign(&message[..], &pk_vec))
+        .collect();
+    let bls_sig = Keys::combine_local_signatures(&sig_vec);
+
+    assert!(Keys::verify_same_message(&apk, &message[..], &bls_sig));
+    assert!(!Keys::verify_same_message(&apk, b"other block", &bls_sig));
+    assert!(!Keys::verify_same_message(
+        &keys_vec[0].pk_i,
+        &message[..],
+        &bls_sig
+    ));
+}
+
+#[test]
+fn agg_sig_is_basic_bls_under_apk() {
+    let (keys_vec, pk_vec, apk) = keygen(5);
+
+    let message = b"same message";
+    let sig_vec: Vec<_> = keys_vec
+        .iter()
+        .map(|k| k.local_sign(&message[..], &pk_vec))
+        .collect();
+    let bls_sig = Keys::combine_local_signatures(&sig_vec);
+
+    // aggregated secret key sum(a_i * sk_i) corresponds to APK ...
+    let sk = keys_vec.iter().fold(FE1::zero(), |acc, k| {
+        let a_i: FE1 = ECScalar::from(&coefficient(&k.pk_i, &canonicalize(&pk_vec)));
+        let sk_i: FE1 = ECScalar::from(&k.sk_i.to_big_int());
+        acc.add(&a_i.mul(&sk_i.get_element()).get_element())
+    });
+    let sk_fe2: FE2 = ECScalar::from(&sk.to_big_int());
+    assert_eq!(GE2::generator() * &sk_fe2, apk);
+
+    // ... and combined signature is a basic BLS signature under that secret key
+    assert_eq!(bls_sig.sigma, hash_to_g1(&message[..]) * &sk);
+    assert!(bls_sig.verify(&message[..], &apk));
+    assert!(Keys::verify(&bls_sig, &message[..], &apk));
+}
+
 // test batch 3 out of 3 for 3 messages
 #[test]
 pub fn test_agg_sig_3_batch_3() {
@@ -140,3 +190,150 @@ fn sign_batch(
         .collect();
     Keys::batch_aggregate_bls(&bls_sig_vec)
 }
+
+#[test]
+fn aggregate_verify_rejects_identity_apk() {
+    use pairing_plus::bls12_381::G2Affine;
+    use pairing_plus::CurveAffine;
+
+    let (keys_vec, pk_vec, apk) = keygen(3);
+    let message = b"first";
+    let sig_vec: Vec<_> = keys_vec
+        .iter()
+        .map(|k| k.local_sign(&message[..], &pk_vec))
+        .collect();
+    let bls_sig = Keys::combine_local_signatures(&sig_vec);
+
+    // e(H(m), 0) = 1, so identity APK "signs" any message for free
+    let identity = GE2::from(G2Affine::zero());
+    let apk_vec = [apk, identity];
+    let msg_vec: [&[u8]; 2] = [&message[..], b"second"];
+    assert!(!Keys::aggregate_verify(&apk_vec, &msg_vec, &bls_sig));
+}
+
+#[test]
+fn aggregate_verify_rejects_repeated_apk() {
+    let (keys_vec, pk_vec, apk) = keygen(3);
+    let msg_vec: [&[u8]; 2] = [b"first", b"second"];
+    let bls_sig_vec: Vec<_> = msg_vec
+        .iter()
+        .map(|msg| {
+            let sig_vec: Vec<_> = keys_vec
+                .iter()
+                .map(|k| k.local_sign(msg, &pk_vec))
+                .collect();
+            Keys::combine_local_signatures(&sig_vec)
+        })
+        .collect();
+    let agg_sig = Keys::batch_aggregate_bls(&bls_sig_vec);
+
+    assert!(!Keys::aggregate_verify(&[apk, apk], &msg_vec, &agg_sig));
+}
+
+#[test]
+fn agg_sig_empty_message() {
+    let (keys_vec, pk_vec, apk) = keygen(3);
+    let sig_vec: Vec<_> = keys_vec
+        .iter()
+        .map(|k| k.local_sign(&[], &pk_vec))
+        .collect();
+    let bls_sig = Keys::combine_local_signatures(&sig_vec);
+    assert!(Keys::verify(&bls_sig, &[], &apk));
+    assert!(!Keys::verify(&bls_sig, &[0], &apk));
+}
+
+#[test]
+fn aggregate_verify_with_dsts() {
+    let (keys_vec, pk_vec, apk) = keygen(3);
+    let (other_keys_vec, other_pk_vec, other_apk) = keygen(2);
+    let attestation: (&[u8], &[u8]) = (b"message", b"ATTESTATION");
+    let deposit: (&[u8], &[u8]) = (b"message", b"DEPOSIT");
+
+    let sign = |keys_vec: &[Keys], pk_vec: &[GE2], (message, dst): (&[u8], &[u8])| {
+        let sig_vec: Vec<_> = keys_vec
+            .iter()
+            .map(|k| k.local_sign_with_dst(message, dst, pk_vec))
+            .collect();
+        Keys::combine_local_signatures(&sig_vec)
+    };
+    let attestation_sig = sign(&keys_vec, &pk_vec, attestation);
+    let agg_sig = Keys::batch_aggregate_bls(&[
+        attestation_sig,
+        sign(&other_keys_vec, &other_pk_vec, deposit),
+    ]);
+
+    // The same message is fine as long as domains differ
+    let apk_vec = [apk, other_apk];
+    assert!(Keys::aggregate_verify_with_dsts(
+        &apk_vec,
+        &[attestation, deposit],
+        &agg_sig
+    ));
+    assert!(!Keys::aggregate_verify_with_dsts(
+        &apk_vec,
+        &[deposit, attestation],
+        &agg_sig
+    ));
+    assert!(!Keys::aggregate_verify_with_dsts(
+        &apk_vec,
+        &[attestation, attestation],
+        &agg_sig
+    ));
+    // Domain is not ignored: signature doesn't verify under the default hashing
+    assert!(!Keys::verify(&attestation_sig, b"message", &apk));
+}
+
+#[test]
+fn aggregate_state_follows_membership() {
+    let (_, pk_vec, apk) = keygen(4);
+    let mut state = AggregateState::new(&pk_vec[..3]);
+    assert_eq!(*state.apk(), Keys::aggregate(&pk_vec[..3]));
+
+    assert!(state.add(pk_vec[3]));
+    assert!(!state.add(pk_vec[3]));
+    assert_eq!(*state.apk(), apk);
+    assert_eq!(state.pk_vec(), &canonicalize(&pk_vec)[..]);
+
+    assert!(state.remove(&pk_vec[1]));
+    assert!(!state.remove(&pk_vec[1]));
+    let remaining = [pk_vec[0], pk_vec[2], pk_vec[3]];
+    assert_eq!(*state.apk(), Keys::aggregate(&remaining));
+    assert_eq!(state, AggregateState::new(&remaining));
+}
+
+#[test]
+fn coefficient_matches_h1() {
+    let (_, pk_vec, _) = keygen(4);
+    let canonical = canonicalize(&pk_vec);
+    for (i, pk) in pk_vec.iter().enumerate() {
+        assert_eq!(coefficient(pk, &canonical), h1(i, &pk_vec));
+    }
+    // Coefficient depends on the order of public keys, but h1 canonicalizes them
+    let mut reversed = pk_vec.clone();
+    reversed.reverse();
+    assert_ne!(coefficient(&pk_vec[0], &reversed), coefficient(&pk_vec[0], &pk_vec));
+    assert_eq!(h1(3, &reversed), h1(0, &pk_vec));
+}
+
+#[test]
+fn aggregation_doesnt_depend_on_pk_order() {
+    let (keys_vec, pk_vec, apk) = keygen(4);
+    let mut shuffled = pk_vec.clone();
+    shuffled.reverse();
+    shuffled.swap(0, 2);
+    assert_eq!(Keys::aggregate(&shuffled), apk);
+    assert_eq!(canonicalize(&shuffled), canonicalize(&pk_vec));
+
+    // Parties may sign with keys listed in different order
+    let message = b"message";
+    let sig_vec: Vec<_> = keys_vec
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            let pk_vec = if i % 2 == 0 { &pk_vec } else { &shuffled };
+            k.local_sign(&message[..], pk_vec)
+        })
+        .collect();
+    let bls_sig = Keys::combine_local_signatures(&sig_vec);
+    assert!(Keys::verify(&bls_sig, &message[..], &apk));
+}
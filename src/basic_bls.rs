@@ -1,15 +1,105 @@
 #![allow(non_snake_case)]
 
+use curv::arithmetic::traits::*;
 use curv::elliptic::curves::bls12_381::g1::FE as FE1;
 use curv::elliptic::curves::bls12_381::g1::GE as GE1;
 use curv::elliptic::curves::bls12_381::g2::FE as FE2;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
 use curv::elliptic::curves::bls12_381::Pair;
 use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
 
 use ff_zeroize::Field;
-use pairing_plus::bls12_381::{Fq12, G1Affine};
+use pairing_plus::bls12_381::{
+    Bls12, Fq12, G1Affine, G1Compressed, G1Uncompressed, G2Affine, G2Compressed, G2Uncompressed, G1,
+};
+use pairing_plus::hash_to_curve::HashToCurve;
+use pairing_plus::hash_to_field::ExpandMsgXmd;
 use pairing_plus::serdes::SerDes;
+use pairing_plus::{CurveAffine, CurveProjective, EncodedPoint, Engine, GroupDecodingError};
+
+use crate::ParseError;
+
+#[cfg(test)]
+mod vectors;
+
+/// Hashes message to a point on G1
+///
+/// This is the hashing used everywhere across the crate to map a message before signing or
+/// verifying it: suite `BLS12381G1_XMD:SHA-256_SSWU_RO_` with domain separation tag `[1]`.
+///
+/// Message may be of any length, including zero: empty message is hashed to a regular point
+/// like any other, so it can be signed and verified across the crate.
+pub fn hash_to_g1(message: &[u8]) -> GE1 {
+    GE1::hash_to_curve(message)
+}
+
+/// Hashes message to a point on G1 under domain separation tag `dst`
+///
+/// Uses the same suite as [hash_to_g1], which is this function instantiated with `dst = [1]`.
+/// Applications mixing signatures from different contexts should give each context its own tag.
+pub fn hash_to_g1_with_dst(message: &[u8], dst: &[u8]) -> GE1 {
+    let point = <G1 as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(message, dst);
+    GE1::from(point.into_affine())
+}
+
+/// Parses G1 point (e.g. signature) from its compressed or uncompressed encoding
+///
+/// Point is checked to be on curve and in the prime order subgroup.
+pub fn g1_from_bytes(bytes: &[u8]) -> Result<GE1, ParseError> {
+    match bytes.len() {
+        48 => decode_point::<G1Compressed>(bytes),
+        96 => decode_point::<G1Uncompressed>(bytes),
+        _ => Err(ParseError::InvalidLength),
+    }
+    .map(GE1::from)
+}
+
+/// Parses G2 point (e.g. public key) from its compressed or uncompressed encoding
+///
+/// Point is checked to be on curve and in the prime order subgroup.
+pub fn g2_from_bytes(bytes: &[u8]) -> Result<GE2, ParseError> {
+    match bytes.len() {
+        96 => decode_point::<G2Compressed>(bytes),
+        192 => decode_point::<G2Uncompressed>(bytes),
+        _ => Err(ParseError::InvalidLength),
+    }
+    .map(GE2::from)
+}
+
+fn decode_point<E: EncodedPoint>(bytes: &[u8]) -> Result<E::Affine, ParseError> {
+    let mut encoded = E::empty();
+    if encoded.as_ref().len() != bytes.len() {
+        return Err(ParseError::InvalidLength);
+    }
+    encoded.as_mut().copy_from_slice(bytes);
+    encoded.into_affine().map_err(ParseError::from)
+}
+
+impl From<GroupDecodingError> for ParseError {
+    fn from(err: GroupDecodingError) -> Self {
+        match err {
+            GroupDecodingError::NotOnCurve => ParseError::NotOnCurve,
+            GroupDecodingError::NotInSubgroup => ParseError::NotInSubgroup,
+            _ => ParseError::InvalidEncoding,
+        }
+    }
+}
+
+/// Verifies serialized signature of `message` under serialized public key
+///
+/// Public key is a G2 point and signature is a G1 point, both in compressed or uncompressed
+/// form. Returns error if any of them cannot be parsed, otherwise returns result of
+/// verification.
+pub fn verify_bytes(
+    pubkey_bytes: &[u8],
+    message: &[u8],
+    sig_bytes: &[u8],
+) -> Result<bool, ParseError> {
+    let pubkey = g2_from_bytes(pubkey_bytes)?;
+    let sigma = g1_from_bytes(sig_bytes)?;
+    Ok(BLSSignature { sigma }.verify(message, &pubkey))
+}
 
 /// Based on https://eprint.iacr.org/2018/483.pdf
 
@@ -24,18 +114,67 @@ pub struct BLSSignature {
     pub sigma: GE1,
 }
 
+/// Checks applied to public key and signature before verification
+///
+/// Default validation performs every check, as required by `KeyValidate` of the
+/// [IETF draft](https://tools.ietf.org/html/draft-irtf-cfrg-bls-signature-04#section-2.5).
+/// High-throughput callers that already validated points (e.g. at deserialization) may opt out
+/// via [BLSSignature::verify_with].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignatureValidation {
+    /// Reject public key and signature that are not in the prime order subgroup
+    pub check_subgroup: bool,
+    /// Reject public key and signature that are the point at infinity
+    pub check_infinity: bool,
+}
+
+impl Default for SignatureValidation {
+    fn default() -> Self {
+        SignatureValidation {
+            check_subgroup: true,
+            check_infinity: true,
+        }
+    }
+}
+
+impl SignatureValidation {
+    /// Validation that doesn't perform any checks
+    pub fn unchecked() -> Self {
+        SignatureValidation {
+            check_subgroup: false,
+            check_infinity: false,
+        }
+    }
+}
+
 impl KeyPairG2 {
     pub fn new() -> Self {
         let x: FE2 = ECScalar::new_random();
         let Y = GE2::generator() * &x;
         KeyPairG2 { x, Y }
     }
+
+    /// Public key `x·G`
+    pub fn public_key(&self) -> GE2 {
+        self.Y
+    }
+
+    /// Constructs key pair from existing secret key `x`
+    ///
+    /// Lets applications import a key produced elsewhere, e.g. derived by the draft's `KeyGen`
+    /// or exported from another BLS implementation. [new](Self::new) only generates a fresh one.
+    pub fn from_secret_key(x: FE2) -> Self {
+        let Y = GE2::generator() * &x;
+        KeyPairG2 { x, Y }
+    }
 }
 
 impl BLSSignature {
-    // compute sigma  = x H(m)
+    /// Computes `sigma = x·H(m)`
+    ///
+    /// `message` may be empty (see [hash_to_g1]).
     pub fn sign(message: &[u8], keys: &KeyPairG2) -> Self {
-        let H_m = GE1::hash_to_curve(message);
+        let H_m = hash_to_g1(message);
         let fe1_x: FE1 = ECScalar::from(&ECScalar::to_big_int(&keys.x));
         BLSSignature {
             sigma: H_m * &fe1_x,
@@ -44,9 +183,72 @@ impl BLSSignature {
 
     // check e(H(m), Y) == e(sigma, g2)
     pub fn verify(&self, message: &[u8], pubkey: &GE2) -> bool {
-        let H_m = GE1::hash_to_curve(message);
-        let product = Pair::efficient_pairing_mul(&H_m, pubkey, &self.sigma, &(-GE2::generator()));
-        product.e == Fq12::one()
+        self.verify_with(message, pubkey, SignatureValidation::default())
+    }
+
+    /// Same as [verify](Self::verify), but performs only checks enabled in `validation`
+    pub fn verify_with(
+        &self,
+        message: &[u8],
+        pubkey: &GE2,
+        validation: SignatureValidation,
+    ) -> bool {
+        self.verify_detailed_with(message, pubkey, validation)
+            .is_ok()
+    }
+
+    /// Same as [verify](Self::verify), but tells why verification failed
+    ///
+    /// Note that signature produced under a different domain separation tag (see
+    /// [hash_to_g1]) is indistinguishable from a forged one: both fail with
+    /// [VerifyError::PairingMismatch].
+    pub fn verify_detailed(&self, message: &[u8], pubkey: &GE2) -> Result<(), VerifyError> {
+        self.verify_detailed_with(message, pubkey, SignatureValidation::default())
+    }
+
+    /// Same as [verify_detailed](Self::verify_detailed), but performs only checks enabled in
+    /// `validation`
+    pub fn verify_detailed_with(
+        &self,
+        message: &[u8],
+        pubkey: &GE2,
+        validation: SignatureValidation,
+    ) -> Result<(), VerifyError> {
+        self.verify_hashed(&hash_to_g1(message), pubkey, validation)
+    }
+
+    /// Verifies signature of a message that is already a point on G1
+    ///
+    /// For protocols where signed message is mapped to the curve externally (e.g. input of a
+    /// distributed VRF): `point` is used as is instead of [hash_to_g1] of the message.
+    pub fn verify_point(&self, point: &GE1, pubkey: &GE2) -> bool {
+        self.verify_hashed(point, pubkey, SignatureValidation::default())
+            .is_ok()
+    }
+
+    fn verify_hashed(
+        &self,
+        H_m: &GE1,
+        pubkey: &GE2,
+        validation: SignatureValidation,
+    ) -> Result<(), VerifyError> {
+        if validation.check_infinity
+            && (self.sigma.get_element().is_zero() || pubkey.get_element().is_zero())
+        {
+            return Err(VerifyError::IdentityElement);
+        }
+        if validation.check_subgroup && !g1_in_subgroup(&self.sigma) {
+            return Err(VerifyError::SignatureNotInSubgroup);
+        }
+        if validation.check_subgroup && !g2_in_subgroup(pubkey) {
+            return Err(VerifyError::PublicKeyNotInSubgroup);
+        }
+        let product = Pair::efficient_pairing_mul(H_m, pubkey, &self.sigma, &(-GE2::generator()));
+        if product.e == Fq12::one() {
+            Ok(())
+        } else {
+            Err(VerifyError::PairingMismatch)
+        }
     }
 
     pub fn to_bytes(&self, compressed: bool) -> Vec<u8> {
@@ -55,6 +257,197 @@ impl BLSSignature {
             .expect("serialize to vec should always succeed");
         pk
     }
+
+    /// Affine coordinates of the signature as field elements `[x, y]`
+    ///
+    /// This is the decomposition expected by circuits verifying BLS signatures inside a SNARK.
+    /// Signature at infinity is encoded as `[0, 0]`. Use [split_into_limbs] to further break
+    /// each coordinate into limbs of the size used by the circuit.
+    pub fn to_field_elements(&self) -> Vec<BigInt> {
+        coordinates(&self.to_bytes(false))
+    }
+}
+
+/// Affine coordinates of public key as field elements `[x.c0, x.c1, y.c0, y.c1]`
+///
+/// Coordinates of G2 point are elements of `Fq2 = Fq[u]/(u² + 1)`, each of them is written as
+/// `c0 + c1·u`. Note that the order differs from the serialized point, where `c1` goes first.
+/// Public key at infinity is encoded as `[0, 0, 0, 0]`.
+pub fn pubkey_to_field_elements(pubkey: &GE2) -> Vec<BigInt> {
+    let mut bytes = vec![];
+    G2Affine::serialize(&pubkey.get_element(), &mut bytes, false)
+        .expect("serialize to vec should always succeed");
+    let mut elements = coordinates(&bytes);
+    elements.swap(0, 1);
+    elements.swap(2, 3);
+    elements
+}
+
+/// Splits a field element into `limbs` limbs of `limb_bits` bits each, least significant first
+///
+/// Panics if `element` doesn't fit into `limbs * limb_bits` bits.
+pub fn split_into_limbs(element: &BigInt, limb_bits: usize, limbs: usize) -> Vec<BigInt> {
+    assert!(limb_bits > 0, "limb must be at least one bit long");
+    assert!(
+        element.bit_length() <= limb_bits * limbs,
+        "element doesn't fit into {} limbs of {} bits",
+        limbs,
+        limb_bits
+    );
+    let bytes = element.to_vec();
+    let bit = |i: usize| i / 8 < bytes.len() && bytes[bytes.len() - 1 - i / 8] >> (i % 8) & 1 == 1;
+    (0..limbs)
+        .map(|limb| {
+            let mut limb_bytes = vec![0u8; (limb_bits + 7) / 8];
+            let len = limb_bytes.len();
+            for i in (0..limb_bits).filter(|i| bit(limb * limb_bits + i)) {
+                limb_bytes[len - 1 - i / 8] |= 1 << (i % 8);
+            }
+            BigInt::from(&limb_bytes[..])
+        })
+        .collect()
+}
+
+/// Splits uncompressed encoding of a point into 48 bytes long coordinates, clearing flag bits
+fn coordinates(uncompressed: &[u8]) -> Vec<BigInt> {
+    uncompressed
+        .chunks(48)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut chunk = chunk.to_vec();
+            if i == 0 {
+                chunk[0] &= 0x1f;
+            }
+            BigInt::from(&chunk[..])
+        })
+        .collect()
+}
+
+/// Reason of signature verification failure, see [BLSSignature::verify_detailed]
+#[derive(Copy, PartialEq, Eq, Clone, Debug, thiserror::Error)]
+pub enum VerifyError {
+    /// Signature or public key is the point at infinity
+    #[error("signature or public key is the identity element")]
+    IdentityElement,
+    /// Signature doesn't belong to the prime order subgroup of G1
+    #[error("signature is not in the prime order subgroup")]
+    SignatureNotInSubgroup,
+    /// Public key doesn't belong to the prime order subgroup of G2
+    #[error("public key is not in the prime order subgroup")]
+    PublicKeyNotInSubgroup,
+    /// Pairing check failed: signature doesn't match the message and public key
+    #[error("signature doesn't match message and public key")]
+    PairingMismatch,
+}
+
+/// Checks that `q*P` is the point at infinity, where `q` is order of the group
+pub(crate) fn g1_in_subgroup(point: &GE1) -> bool {
+    let q_minus_one: FE1 = ECScalar::from(&(FE1::q() - BigInt::one()));
+    (point * &q_minus_one + point).get_element().is_zero()
+}
+
+/// Checks that `q*P` is the point at infinity, where `q` is order of the group
+fn g2_in_subgroup(point: &GE2) -> bool {
+    let q_minus_one: FE2 = ECScalar::from(&(FE2::q() - BigInt::one()));
+    (point * &q_minus_one + point).get_element().is_zero()
+}
+
+/// Verifier of signatures under a fixed public key
+///
+/// Public key and `-g2` are prepared for the Miller loop once at construction, so verifying
+/// many signatures under the same key is cheaper than calling [BLSSignature::verify]
+/// repeatedly.
+///
+/// Performs the same checks as [BLSSignature::verify]: public key is validated once at
+/// construction, signature is validated on every call.
+pub struct PreparedVerifier {
+    pubkey: <G2Affine as CurveAffine>::Prepared,
+    pubkey_valid: bool,
+    neg_g2: <G2Affine as CurveAffine>::Prepared,
+}
+
+impl PreparedVerifier {
+    /// Prepares verifier of signatures under `pubkey`
+    pub fn new(pubkey: &GE2) -> Self {
+        let mut neg_g2 = G2Affine::one();
+        neg_g2.negate();
+        PreparedVerifier {
+            pubkey: pubkey.get_element().prepare(),
+            pubkey_valid: !pubkey.get_element().is_zero() && g2_in_subgroup(pubkey),
+            neg_g2: neg_g2.prepare(),
+        }
+    }
+
+    /// Checks that `e(H(m), Y) == e(sigma, g2)`, same as [BLSSignature::verify]
+    pub fn verify(&self, message: &[u8], sig: &BLSSignature) -> bool {
+        if !self.pubkey_valid || sig.sigma.get_element().is_zero() || !g1_in_subgroup(&sig.sigma) {
+            return false;
+        }
+        let H_m = hash_to_g1(message).get_element().prepare();
+        let sigma = sig.sigma.get_element().prepare();
+        let product = Bls12::final_exponentiation(&Bls12::miller_loop(
+            [(&H_m, &self.pubkey), (&sigma, &self.neg_g2)].iter(),
+        ));
+        product == Some(Fq12::one())
+    }
+}
+
+/// Cache of signature verification results
+///
+/// Memoizes result of [BLSSignature::verify] by SHA-256 hash of `(compressed pubkey, message,
+/// compressed signature)`, so verifying the same signature repeatedly (e.g. once received via
+/// gossip and once again at block import) costs a single pairing check. Least recently used
+/// entries are evicted once cache reaches its capacity.
+#[cfg(feature = "lru")]
+pub struct VerificationCache {
+    results: lru::LruCache<BigInt, bool>,
+}
+
+#[cfg(feature = "lru")]
+impl VerificationCache {
+    /// Constructs a cache holding at most `capacity` verification results
+    pub fn new(capacity: usize) -> Self {
+        VerificationCache {
+            results: lru::LruCache::new(capacity),
+        }
+    }
+
+    /// Verifies signature, same as [BLSSignature::verify], reusing cached result if any
+    pub fn verify(&mut self, pubkey: &GE2, message: &[u8], sig: &BLSSignature) -> bool {
+        use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
+        use curv::cryptographic_primitives::hashing::traits::Hash;
+
+        let mut pubkey_bytes = vec![];
+        G2Affine::serialize(&pubkey.get_element(), &mut pubkey_bytes, true)
+            .expect("serialize to vec should always succeed");
+        let sig_bytes = sig.to_bytes(true);
+        // Lengths are hashed as well, as leading zero bytes are lost in conversion to BigInt
+        let key = HSha256::create_hash(&[
+            &BigInt::from(pubkey_bytes.len() as u32),
+            &BigInt::from(&pubkey_bytes[..]),
+            &BigInt::from(message.len() as u32),
+            &BigInt::from(message),
+            &BigInt::from(sig_bytes.len() as u32),
+            &BigInt::from(&sig_bytes[..]),
+        ]);
+
+        if let Some(&valid) = self.results.get(&key) {
+            return valid;
+        }
+        let valid = sig.verify(message, pubkey);
+        self.results.put(key, valid);
+        valid
+    }
+
+    /// Number of cached verification results
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Whether cache holds no verification results
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
 }
 
 mod test {
@@ -70,6 +463,144 @@ mod test {
         assert!(signature.verify(&message_bytes[..], &Y));
     }
 
+    #[test]
+    pub fn test_empty_message() {
+        let keypair = KeyPairG2::new();
+        let signature = BLSSignature::sign(&[], &keypair);
+        assert!(!signature.sigma.get_element().is_zero());
+        assert!(signature.verify(&[], &keypair.Y));
+        assert!(!signature.verify(&[0], &keypair.Y));
+    }
+
+    #[test]
+    pub fn test_verify_rejects_infinity() {
+        let infinity_pk = GE2::from(G2Affine::zero());
+        let signature = BLSSignature {
+            sigma: GE1::from(G1Affine::zero()),
+        };
+        // e(H(m), 0) == e(0, g2) holds for any message
+        assert!(signature.verify_with(&[1, 2, 3], &infinity_pk, SignatureValidation::unchecked()));
+        assert!(!signature.verify(&[1, 2, 3], &infinity_pk));
+    }
+
+    #[test]
+    pub fn test_verify_rejects_point_out_of_subgroup() {
+        // Find a point on curve which is not in the prime order subgroup
+        let outside_subgroup = (1..=255u8)
+            .find_map(|x| {
+                let mut encoded = G1Compressed::empty();
+                encoded.as_mut()[0] = 0x80;
+                encoded.as_mut()[47] = x;
+                match encoded.into_affine() {
+                    Err(GroupDecodingError::NotInSubgroup) => encoded.into_affine_unchecked().ok(),
+                    _ => None,
+                }
+            })
+            .expect("point out of subgroup must be found");
+        let signature = BLSSignature {
+            sigma: GE1::from(outside_subgroup),
+        };
+        let keypair = KeyPairG2::new();
+        assert!(!g1_in_subgroup(&signature.sigma));
+        assert!(g2_in_subgroup(&keypair.Y));
+        assert!(!signature.verify(&[1, 2, 3], &keypair.Y));
+        assert_eq!(
+            signature.verify_detailed(&[1, 2, 3], &keypair.Y),
+            Err(VerifyError::SignatureNotInSubgroup)
+        );
+    }
+
+    #[test]
+    pub fn test_verify_detailed() {
+        let keypair = KeyPairG2::new();
+        let signature = BLSSignature::sign(&[1, 2, 3], &keypair);
+        assert_eq!(signature.verify_detailed(&[1, 2, 3], &keypair.Y), Ok(()));
+        assert_eq!(
+            signature.verify_detailed(&[3, 2, 1], &keypair.Y),
+            Err(VerifyError::PairingMismatch)
+        );
+        assert_eq!(
+            signature.verify_detailed(&[1, 2, 3], &GE2::from(G2Affine::zero())),
+            Err(VerifyError::IdentityElement)
+        );
+        assert_eq!(
+            signature.verify_detailed_with(
+                &[3, 2, 1],
+                &keypair.Y,
+                SignatureValidation::unchecked()
+            ),
+            Err(VerifyError::PairingMismatch)
+        );
+    }
+
+    #[test]
+    pub fn test_prepared_verifier() {
+        let keypair = KeyPairG2::new();
+        let verifier = PreparedVerifier::new(&keypair.Y);
+        for message_bytes in [[1, 2, 3, 4, 5], [5, 4, 3, 2, 1]].iter() {
+            let signature = BLSSignature::sign(&message_bytes[..], &keypair);
+            assert!(verifier.verify(&message_bytes[..], &signature));
+            assert!(!verifier.verify(&[0, 2, 3, 4, 5], &signature));
+        }
+        let other_keypair = KeyPairG2::new();
+        let signature = BLSSignature::sign(&[1, 2, 3], &other_keypair);
+        assert!(!verifier.verify(&[1, 2, 3], &signature));
+    }
+
+    #[test]
+    pub fn test_prepared_verifier_rejects_infinity() {
+        let infinity_sig = BLSSignature {
+            sigma: GE1::from(G1Affine::zero()),
+        };
+        let verifier = PreparedVerifier::new(&GE2::from(G2Affine::zero()));
+        assert!(!verifier.verify(&[1, 2, 3], &infinity_sig));
+
+        let keypair = KeyPairG2::new();
+        let verifier = PreparedVerifier::new(&keypair.Y);
+        assert!(!verifier.verify(&[1, 2, 3], &infinity_sig));
+        let signature = BLSSignature::sign(&[1, 2, 3], &keypair);
+        assert!(verifier.verify(&[1, 2, 3], &signature));
+    }
+
+    #[test]
+    pub fn test_verify_bytes() {
+        let keypair = KeyPairG2::new();
+        let mut pubkey_bytes = vec![];
+        G2Affine::serialize(&keypair.Y.get_element(), &mut pubkey_bytes, true)
+            .expect("serialize to vec should always succeed");
+        let message_bytes = [1, 2, 3, 4, 5];
+        let signature = BLSSignature::sign(&message_bytes[..], &keypair);
+
+        for &compressed in [true, false].iter() {
+            let sig_bytes = signature.to_bytes(compressed);
+            assert_eq!(
+                verify_bytes(&pubkey_bytes, &message_bytes[..], &sig_bytes),
+                Ok(true)
+            );
+            assert_eq!(
+                verify_bytes(&pubkey_bytes, &[0, 2, 3, 4, 5], &sig_bytes),
+                Ok(false)
+            );
+        }
+
+        let sig_bytes = signature.to_bytes(true);
+        assert_eq!(
+            verify_bytes(&pubkey_bytes[1..], &message_bytes[..], &sig_bytes),
+            Err(ParseError::InvalidLength)
+        );
+        assert!(verify_bytes(&pubkey_bytes, &message_bytes[..], &[0xff; 48]).is_err());
+    }
+
+    #[test]
+    pub fn test_parse_error_converts_into_crate_error() {
+        let parse = |bytes: &[u8]| -> Result<GE1, crate::Error> { Ok(g1_from_bytes(bytes)?) };
+        assert_eq!(
+            parse(&[0u8; 47]),
+            Err(crate::Error::Parse(ParseError::InvalidLength))
+        );
+        assert!(matches!(parse(&[0xff; 48]), Err(crate::Error::Parse(_))));
+    }
+
     #[test]
     #[should_panic]
     pub fn test_bad_simple_bls() {
@@ -80,4 +611,70 @@ mod test {
         let message_bytes_corrupt = [0, 2, 3, 4, 5];
         assert!(signature.verify(&message_bytes_corrupt[..], &Y));
     }
+
+    #[test]
+    pub fn test_field_elements() {
+        let keypair = KeyPairG2::new();
+        let signature = BLSSignature::sign(&[1, 2, 3], &keypair);
+
+        let sig_elements = signature.to_field_elements();
+        assert_eq!(
+            sig_elements,
+            vec![
+                signature.sigma.x_coor().unwrap(),
+                signature.sigma.y_coor().unwrap()
+            ]
+        );
+
+        let pk_elements = pubkey_to_field_elements(&keypair.Y);
+        assert_eq!(pk_elements.len(), 4);
+        let mut uncompressed = vec![];
+        // Serialized point puts c1 before c0
+        for &i in [1, 0, 3, 2].iter() {
+            let bytes = pk_elements[i].to_vec();
+            uncompressed.extend(std::iter::repeat(0).take(48 - bytes.len()));
+            uncompressed.extend(bytes);
+        }
+        assert_eq!(g2_from_bytes(&uncompressed).unwrap(), keypair.Y);
+
+        let infinity = BLSSignature {
+            sigma: GE1::from(G1Affine::zero()),
+        };
+        assert_eq!(infinity.to_field_elements(), vec![BigInt::zero(); 2]);
+
+        // Limbs recombine into the original coordinate
+        for (limb_bits, limbs) in [(64, 6), (55, 7), (381, 1)].iter() {
+            let split = split_into_limbs(&sig_elements[0], *limb_bits, *limbs);
+            assert_eq!(split.len(), *limbs);
+            assert!(split.iter().all(|limb| limb.bit_length() <= *limb_bits));
+            let recombined = split
+                .iter()
+                .enumerate()
+                .fold(BigInt::zero(), |acc, (i, limb)| {
+                    acc + (limb.clone() << (i * limb_bits))
+                });
+            assert_eq!(recombined, sig_elements[0]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "lru")]
+    pub fn test_verification_cache() {
+        let keypair = KeyPairG2::new();
+        let signature = BLSSignature::sign(&[1, 2, 3], &keypair);
+        let mut cache = VerificationCache::new(2);
+
+        assert!(cache.verify(&keypair.Y, &[1, 2, 3], &signature));
+        assert!(cache.verify(&keypair.Y, &[1, 2, 3], &signature));
+        assert_eq!(cache.len(), 1);
+
+        // Invalid results are cached as well, and message with extra leading zero is distinct
+        assert!(!cache.verify(&keypair.Y, &[0, 1, 2, 3], &signature));
+        assert!(!cache.verify(&keypair.Y, &[0, 1, 2, 3], &signature));
+        assert_eq!(cache.len(), 2);
+
+        // Least recently used entry is evicted
+        assert!(!cache.verify(&keypair.Y, &[3, 2, 1], &signature));
+        assert_eq!(cache.len(), 2);
+    }
 }
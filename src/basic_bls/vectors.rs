@@ -0,0 +1,74 @@
+//! Hash-to-curve test vectors for suite `BLS12381G1_XMD:SHA-256_SSWU_RO_`
+//!
+//! Vectors are taken from [draft-irtf-cfrg-hash-to-curve] (appendix "BLS12381G1_XMD:SHA-256_SSWU_RO_").
+//! Expected points are given in compressed form.
+//!
+//! Note that [hash_to_g1](super::hash_to_g1) uses the same suite, but with domain separation tag `[1]`
+//! instead of the one used in the draft vectors. So we check the suite against vectors, and
+//! then check that [hash_to_g1](super::hash_to_g1) is exactly this suite instantiated with `[1]` tag.
+//!
+//! [draft-irtf-cfrg-hash-to-curve]: https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10
+
+/// Domain separation tag used in the draft test vectors
+pub const DST: &[u8] = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+
+/// Domain separation tag used by [hash_to_g1](super::hash_to_g1)
+pub const CRATE_DST: &[u8] = &[1u8];
+
+/// `(message, expected compressed point)` pairs
+pub const HASH_TO_G1: &[(&[u8], &str)] = &[
+    (
+        b"",
+        "852926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a1",
+    ),
+    (
+        b"abc",
+        "83567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903",
+    ),
+    (
+        b"abcdef0123456789",
+        "91e0b079dea29a68f0383ee94fed1b940995272407e3bb916bbf268c263ddd57a6a27200a784cbc248e84f357ce82d98",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::traits::ECPoint;
+    use pairing_plus::bls12_381::{G1Affine, G1};
+    use pairing_plus::hash_to_curve::HashToCurve;
+    use pairing_plus::hash_to_field::ExpandMsgXmd;
+    use pairing_plus::serdes::SerDes;
+    use pairing_plus::CurveProjective;
+
+    use super::*;
+
+    fn suite_hash_to_g1(message: &[u8], dst: &[u8]) -> G1Affine {
+        <G1 as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(message, dst).into_affine()
+    }
+
+    fn compressed(point: &G1Affine) -> String {
+        let mut bytes = vec![];
+        G1Affine::serialize(point, &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        hex::encode(bytes)
+    }
+
+    #[test]
+    fn hash_to_curve_matches_draft_vectors() {
+        for (message, expected) in HASH_TO_G1 {
+            let point = suite_hash_to_g1(message, DST);
+            assert_eq!(&compressed(&point), expected, "message: {:?}", message);
+        }
+    }
+
+    #[test]
+    fn crate_hash_to_curve_is_the_same_suite() {
+        for (message, _) in HASH_TO_G1 {
+            let expected = suite_hash_to_g1(message, CRATE_DST);
+            let actual = crate::basic_bls::hash_to_g1(message).get_element();
+            let with_dst = crate::basic_bls::hash_to_g1_with_dst(message, CRATE_DST).get_element();
+            assert_eq!(with_dst, actual);
+            assert_eq!(compressed(&actual), compressed(&expected));
+        }
+    }
+}
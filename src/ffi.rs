@@ -0,0 +1,383 @@
+//! C API for signing and verification
+//!
+//! Available with `ffi` feature. Every function takes byte buffers as `(pointer, length)`
+//! pairs and returns status code: [BLS_OK] on success, or one of negative `BLS_ERR_*` codes.
+//! [bls_verify] additionally returns [BLS_INVALID_SIGNATURE] if signature doesn't verify.
+//! Null pointer is accepted for empty buffer.
+//!
+//! Encodings:
+//! * Public keys and verification keys are compressed G2 points (96 bytes)
+//! * Signatures are compressed G1 points (48 bytes)
+//! * Secret shares are 32 bytes big-endian scalars
+//! * Partial signatures are serialized via [PartialSignature::to_bytes]
+//!
+//! To link it from C, build the crate as `cdylib` or `staticlib`, e.g.
+//! `cargo rustc --release --features ffi --crate-type cdylib`.
+
+#![allow(non_snake_case)]
+
+use std::convert::TryFrom;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::ShamirSecretSharing;
+use curv::elliptic::curves::bls12_381::g2::FE as FE2;
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::ECScalar;
+
+use crate::basic_bls::{g2_from_bytes, hash_to_g1, verify_bytes, BLSSignature};
+use crate::threshold_bls::party_i::{PartialSignature, SharedKeys};
+use crate::threshold_bls::secret_scalar;
+use crate::threshold_bls::KeygenIndex;
+use crate::ParseError;
+
+/// Returns early with error code if expression evaluates to `Err(code)`
+macro_rules! try_code {
+    ($e:expr) => {
+        match $e {
+            Ok(x) => x,
+            Err(code) => return code,
+        }
+    };
+}
+
+/// Operation succeeded (for [bls_verify]: signature is valid)
+pub const BLS_OK: i32 = 0;
+/// Signature is well-formed but doesn't verify
+pub const BLS_INVALID_SIGNATURE: i32 = 1;
+/// Null pointer given for non-empty buffer
+pub const BLS_ERR_NULL_POINTER: i32 = -1;
+/// Input buffer cannot be parsed (wrong length, invalid point or scalar)
+pub const BLS_ERR_PARSE: i32 = -2;
+/// Output buffer is too small
+pub const BLS_ERR_BUFFER_TOO_SMALL: i32 = -3;
+/// Partial signatures cannot be combined: too few of them, duplicated signers, some partial
+/// signature is invalid, or combined signature doesn't verify under group public key
+pub const BLS_ERR_COMBINE: i32 = -4;
+/// Unexpected internal error
+pub const BLS_ERR_PANIC: i32 = -5;
+
+/// Size of compressed signature
+pub const BLS_SIGNATURE_LEN: usize = 48;
+/// Size of compressed public key
+pub const BLS_PUBLIC_KEY_LEN: usize = 96;
+/// Size of serialized secret share
+pub const BLS_SECRET_SHARE_LEN: usize = secret_scalar::BYTES_LEN;
+/// Size of serialized partial signature
+pub const BLS_PARTIAL_SIGNATURE_LEN: usize = PartialSignature::BYTES_LEN;
+
+/// Verifies signature `sig` of message `msg` under public key `pk`
+///
+/// Returns [BLS_OK] if signature is valid, [BLS_INVALID_SIGNATURE] if it's not, or error code
+/// if public key or signature cannot be parsed. Both compressed and uncompressed encodings
+/// are accepted.
+///
+/// # Safety
+/// Every `(ptr, len)` pair must be either a valid readable buffer of `len` bytes, or have
+/// `len = 0`.
+#[no_mangle]
+pub unsafe extern "C" fn bls_verify(
+    pk_ptr: *const u8,
+    pk_len: usize,
+    msg_ptr: *const u8,
+    msg_len: usize,
+    sig_ptr: *const u8,
+    sig_len: usize,
+) -> i32 {
+    let pk = try_code!(input(pk_ptr, pk_len));
+    let msg = try_code!(input(msg_ptr, msg_len));
+    let sig = try_code!(input(sig_ptr, sig_len));
+    guard(|| match verify_bytes(pk, msg, sig) {
+        Ok(true) => BLS_OK,
+        Ok(false) => BLS_INVALID_SIGNATURE,
+        Err(err) => parse_error_code(err),
+    })
+}
+
+/// Partially signs message `msg` with secret share `sk` of party with keygen `index`
+///
+/// `index` is 1-based [keygen index](crate::threshold_bls::KeygenIndex) of the party, i.e.
+/// evaluation point of its share. Returns [BLS_ERR_PARSE] if `index` is zero. Writes
+/// [BLS_PARTIAL_SIGNATURE_LEN] bytes to `out`.
+///
+/// # Safety
+/// `(sk_ptr, sk_len)` and `(msg_ptr, msg_len)` must be either valid readable buffers, or have
+/// zero length. `(out_ptr, out_len)` must be a valid writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bls_partial_sign(
+    index: u16,
+    sk_ptr: *const u8,
+    sk_len: usize,
+    msg_ptr: *const u8,
+    msg_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> i32 {
+    let sk = try_code!(input(sk_ptr, sk_len));
+    let msg = try_code!(input(msg_ptr, msg_len));
+    let out = try_code!(output(out_ptr, out_len, BLS_PARTIAL_SIGNATURE_LEN));
+    guard(move || {
+        if index == 0 {
+            return BLS_ERR_PARSE;
+        }
+        let sk_i = try_code!(secret_share_from_bytes(sk).map_err(parse_error_code));
+        let (partial, _) = PartialSignature::new(KeygenIndex(index), &sk_i, msg);
+        let bytes = try_code!(partial.to_bytes().map_err(parse_error_code));
+        out.copy_from_slice(&bytes);
+        BLS_OK
+    })
+}
+
+/// Combines partial signatures of message `msg` into signature under threshold `t` and group
+/// public key `pk`
+///
+/// `partials` is concatenation of serialized partial signatures, and `vks` is concatenation of
+/// verification keys of all `n` parties ordered by keygen index, i.e. key of the party with
+/// index `i` comes `i`-th. Every partial signature is verified under the key matching its
+/// index, at least `t+1` partial signatures from distinct signers are required. Combined
+/// signature is verified under `pk` before it's written to `out` ([BLS_SIGNATURE_LEN] bytes).
+///
+/// # Safety
+/// `(pk_ptr, pk_len)`, `(partials_ptr, partials_len)`, `(vks_ptr, vks_len)`, and
+/// `(msg_ptr, msg_len)` must be either valid readable buffers, or have zero length.
+/// `(out_ptr, out_len)` must be a valid writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bls_combine(
+    t: u16,
+    pk_ptr: *const u8,
+    pk_len: usize,
+    partials_ptr: *const u8,
+    partials_len: usize,
+    vks_ptr: *const u8,
+    vks_len: usize,
+    msg_ptr: *const u8,
+    msg_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> i32 {
+    let pk = try_code!(input(pk_ptr, pk_len));
+    let partials = try_code!(input(partials_ptr, partials_len));
+    let vks = try_code!(input(vks_ptr, vks_len));
+    let msg = try_code!(input(msg_ptr, msg_len));
+    let out = try_code!(output(out_ptr, out_len, BLS_SIGNATURE_LEN));
+    guard(move || {
+        if partials.len() % BLS_PARTIAL_SIGNATURE_LEN != 0 || vks.len() % BLS_PUBLIC_KEY_LEN != 0 {
+            return BLS_ERR_PARSE;
+        }
+        let pk = try_code!(g2_from_bytes(pk).map_err(parse_error_code));
+        let partials: Result<Vec<_>, _> = partials
+            .chunks(BLS_PARTIAL_SIGNATURE_LEN)
+            .map(PartialSignature::from_bytes)
+            .collect();
+        let vks: Result<Vec<_>, _> = vks.chunks(BLS_PUBLIC_KEY_LEN).map(g2_from_bytes).collect();
+        let partials = try_code!(partials.map_err(parse_error_code));
+        let vks = try_code!(vks.map_err(parse_error_code));
+        match combine(usize::from(t), pk, &partials, &vks, msg) {
+            Some(sig) => {
+                out.copy_from_slice(&sig.to_bytes(true));
+                BLS_OK
+            }
+            None => BLS_ERR_COMBINE,
+        }
+    })
+}
+
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], i32> {
+    if len == 0 {
+        Ok(&[])
+    } else if ptr.is_null() {
+        Err(BLS_ERR_NULL_POINTER)
+    } else {
+        Ok(slice::from_raw_parts(ptr, len))
+    }
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: usize, expected: usize) -> Result<&'a mut [u8], i32> {
+    if ptr.is_null() {
+        Err(BLS_ERR_NULL_POINTER)
+    } else if len < expected {
+        Err(BLS_ERR_BUFFER_TOO_SMALL)
+    } else {
+        Ok(slice::from_raw_parts_mut(ptr, expected))
+    }
+}
+
+/// Unwinding across FFI boundary is undefined behaviour, so panics are turned into error code
+///
+/// Nothing is observed after panic except the error code, so it's fine to assert unwind safety.
+fn guard<F: FnOnce() -> i32>(f: F) -> i32 {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(BLS_ERR_PANIC)
+}
+
+/// Every parse failure is reported via the same code regardless of the reason
+fn parse_error_code(_err: ParseError) -> i32 {
+    BLS_ERR_PARSE
+}
+
+fn secret_share_from_bytes(bytes: &[u8]) -> Result<FE2, ParseError> {
+    let bytes =
+        <&[u8; BLS_SECRET_SHARE_LEN]>::try_from(bytes).map_err(|_| ParseError::InvalidLength)?;
+    secret_scalar::from_bytes(bytes)
+}
+
+/// Combines partial signatures via [SharedKeys::combine_verbose]
+///
+/// Combining uses only public parameters of the group, so secret share of [SharedKeys] is
+/// left zero.
+fn combine(
+    t: usize,
+    pk: GE2,
+    partials: &[PartialSignature],
+    vks: &[GE2],
+    msg: &[u8],
+) -> Option<BLSSignature> {
+    let n = u16::try_from(vks.len()).ok()?;
+    let vk_vec = partials
+        .iter()
+        .map(|p| p.index.position(n).map(|j| vks[j]))
+        .collect::<Option<Vec<_>>>()?;
+    let s: Vec<KeygenIndex> = partials.iter().map(|p| p.index).collect();
+    let keys = SharedKeys {
+        index: 0,
+        params: ShamirSecretSharing {
+            threshold: t,
+            share_count: vks.len(),
+        },
+        vk: pk,
+        sk_i: ECScalar::zero(),
+    };
+    let H_x = hash_to_g1(msg);
+    let sig = keys.combine_verbose(&vk_vec, partials, H_x, &s).ok()?;
+    if !sig.verify_point(&H_x, &pk) {
+        return None;
+    }
+    Some(sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+    use curv::elliptic::curves::traits::ECPoint;
+    use curv::BigInt;
+    use pairing_plus::serdes::SerDes;
+
+    use super::*;
+    use crate::basic_bls::KeyPairG2;
+
+    fn scalar_bytes(x: &FE2) -> Vec<u8> {
+        let x = BigInt::to_vec(&x.to_big_int());
+        let mut bytes = vec![0u8; BLS_SECRET_SHARE_LEN - x.len()];
+        bytes.extend_from_slice(&x);
+        bytes
+    }
+
+    fn point_bytes(point: &GE2) -> Vec<u8> {
+        let mut bytes = vec![];
+        SerDes::serialize(&point.get_element(), &mut bytes, true).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn verify_basic_signature() {
+        let keys = KeyPairG2::new();
+        let msg = b"message";
+        let sig = BLSSignature::sign(msg, &keys).to_bytes(true);
+        let pk = point_bytes(&keys.public_key());
+
+        let verify = |msg: &[u8], sig: &[u8]| unsafe {
+            bls_verify(
+                pk.as_ptr(),
+                pk.len(),
+                msg.as_ptr(),
+                msg.len(),
+                sig.as_ptr(),
+                sig.len(),
+            )
+        };
+        assert_eq!(verify(msg, &sig), BLS_OK);
+        assert_eq!(verify(b"another message", &sig), BLS_INVALID_SIGNATURE);
+        assert_eq!(verify(msg, &sig[1..]), BLS_ERR_PARSE);
+
+        let code = unsafe { bls_verify(pk.as_ptr(), pk.len(), ptr::null(), 5, sig.as_ptr(), 48) };
+        assert_eq!(code, BLS_ERR_NULL_POINTER);
+    }
+
+    #[test]
+    fn partial_sign_and_combine() {
+        let secret = FE2::new_random();
+        let (_, shares) = VerifiableSS::<GE2>::share(1, 3, &secret);
+        let msg = b"message";
+
+        let mut partials = vec![];
+        let vks: Vec<u8> = shares
+            .iter()
+            .flat_map(|sk_i| point_bytes(&(GE2::generator() * sk_i)))
+            .collect();
+        for (index, sk_i) in shares.iter().enumerate().skip(1) {
+            let sk = scalar_bytes(sk_i);
+            let mut partial = vec![0u8; BLS_PARTIAL_SIGNATURE_LEN];
+            let code = unsafe {
+                bls_partial_sign(
+                    index as u16 + 1,
+                    sk.as_ptr(),
+                    sk.len(),
+                    msg.as_ptr(),
+                    msg.len(),
+                    partial.as_mut_ptr(),
+                    partial.len(),
+                )
+            };
+            assert_eq!(code, BLS_OK);
+            partials.extend_from_slice(&partial);
+        }
+
+        let pk = point_bytes(&(GE2::generator() * &secret));
+        let combine_under = |pk: &[u8], vks: &[u8], partials: &[u8], sig: &mut [u8]| unsafe {
+            bls_combine(
+                1,
+                pk.as_ptr(),
+                pk.len(),
+                partials.as_ptr(),
+                partials.len(),
+                vks.as_ptr(),
+                vks.len(),
+                msg.as_ptr(),
+                msg.len(),
+                sig.as_mut_ptr(),
+                sig.len(),
+            )
+        };
+
+        let combine = |partials: &[u8], sig: &mut [u8]| combine_under(&pk, &vks, partials, sig);
+
+        let mut sig = [0u8; BLS_SIGNATURE_LEN];
+        assert_eq!(combine(&partials, &mut sig), BLS_OK);
+        assert_eq!(verify_bytes(&pk, msg, &sig), Ok(true));
+
+        // Too small output buffer
+        assert_eq!(combine(&partials, &mut sig[1..]), BLS_ERR_BUFFER_TOO_SMALL);
+
+        // Sigma of the first partial signature replaced by sigma of the second one
+        let mut tampered = partials.clone();
+        tampered[2..50].copy_from_slice(&partials[BLS_PARTIAL_SIGNATURE_LEN + 2..][..48]);
+        assert_eq!(combine(&tampered, &mut sig), BLS_ERR_COMBINE);
+
+        // Verification keys of parties 2 and 3 are swapped, so partial signatures don't match
+        // keys of their indexes
+        let mut swapped = vks.clone();
+        swapped[BLS_PUBLIC_KEY_LEN..].rotate_left(BLS_PUBLIC_KEY_LEN);
+        assert_eq!(
+            combine_under(&pk, &swapped, &partials, &mut sig),
+            BLS_ERR_COMBINE
+        );
+
+        // Partial signatures are valid, but don't combine into signature under another key
+        let another_pk = point_bytes(&(GE2::generator() * &FE2::new_random()));
+        assert_eq!(
+            combine_under(&another_pk, &vks, &partials, &mut sig),
+            BLS_ERR_COMBINE
+        );
+    }
+}
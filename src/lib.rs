@@ -85,15 +85,17 @@
 //! // message - bytes to sign, n - number of parties involved in signing,
 //! // local_key - local secret key obtained by this party at keygen
 //! let signing = Sign::new(message, i, n, local_key)?;
-//! let (_, sig) = AsyncProtocol::new(signing, incoming, outcoming)
+//! let signed = AsyncProtocol::new(signing, incoming, outcoming)
 //!     .run().await?;
-//! println!("Signature: {:?}", sig);
+//! println!("Signature: {:?}", signed.signature());
 //! # Ok(())
 //! # }
 //! ```
 
 pub mod aggregated_bls;
 pub mod basic_bls;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod threshold_bls;
 /// BLS verification should follow the BLS standard:
 /// [https://tools.ietf.org/html/draft-irtf-cfrg-bls-signature-04]
@@ -106,6 +108,40 @@ pub enum Error {
     KeyGenBadCommitment,
     KeyGenInvalidShare,
     KeyGenDlogProofError,
+    KeyGenWeakSecurityParameter,
     PartialSignatureVerificationError,
+    PartialSignatureDuplicate,
     SigningMisMatchedVectors,
+    ImportedShareMismatch,
+    /// Verification keys don't lie on a single polynomial of degree `threshold`, or their number
+    /// doesn't match `share_count`
+    InconsistentVerificationKeys,
+    /// Serialized point or scalar cannot be parsed
+    Parse(ParseError),
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Error of parsing serialized curve point or scalar
+///
+/// Every `from_bytes`-like function of the crate reports failures via this type, and it
+/// converts into [Error] so parsing can be chained with other operations via `?`.
+#[derive(Copy, PartialEq, Eq, Clone, Debug, thiserror::Error)]
+pub enum ParseError {
+    /// Length doesn't match neither compressed nor uncompressed point encoding
+    #[error("invalid length")]
+    InvalidLength,
+    /// Bytes are not a valid point encoding (e.g. wrong flags or coordinate is not a field element)
+    #[error("invalid encoding")]
+    InvalidEncoding,
+    /// Decoded point is not on the curve
+    #[error("point is not on the curve")]
+    NotOnCurve,
+    /// Decoded point doesn't belong to the prime order subgroup
+    #[error("point is not in the prime order subgroup")]
+    NotInSubgroup,
 }
@@ -0,0 +1,104 @@
+use curv::arithmetic::traits::*;
+use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
+use curv::cryptographic_primitives::commitments::traits::Commitment;
+use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::{
+    ShamirSecretSharing, VerifiableSS,
+};
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::threshold_bls::party_i::{KeyGenComm, KeyGenDecom};
+use crate::threshold_bls::{public_share, KeygenIndex};
+
+/// Public messages of completed keygen ceremony
+///
+/// All vectors contain one item per party, ordered by party index: `commitments[i]`,
+/// `decommitments[i]`, `vss_schemes[i]` and `dlog_proofs[i]` are sent by party `i+1`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeygenTranscript {
+    pub params: ShamirSecretSharing,
+    pub commitments: Vec<KeyGenComm>,
+    pub decommitments: Vec<KeyGenDecom>,
+    pub vss_schemes: Vec<VerifiableSS<GE2>>,
+    pub dlog_proofs: Vec<DLogProof<GE2>>,
+}
+
+/// Re-verifies recorded keygen transcript, returns group public key
+///
+/// Checks that every party opened its commitment, shared the committed secret via VSS, and
+/// proved knowledge of the secret share matching the VSS commitments. Auditor doesn't need to
+/// take part in keygen: the transcript consists of public data only. Note that validity of
+/// private shares sent to parties can't be checked from the transcript.
+pub fn audit_keygen(transcript: &KeygenTranscript) -> Result<GE2, AuditError> {
+    let n = transcript.params.share_count;
+    let t = transcript.params.threshold;
+    if n == 0
+        || t >= n
+        || transcript.commitments.len() != n
+        || transcript.decommitments.len() != n
+        || transcript.vss_schemes.len() != n
+        || transcript.dlog_proofs.len() != n
+    {
+        return Err(AuditError::MismatchedLengths);
+    }
+
+    for (i, (comm, decom)) in transcript
+        .commitments
+        .iter()
+        .zip(&transcript.decommitments)
+        .enumerate()
+    {
+        let expected = HashCommitment::create_commitment_with_user_defined_randomness(
+            &(decom.y_i.bytes_compressed_to_big_int() + BigInt::from(i as u32)),
+            &decom.blind_factor,
+        );
+        if expected != comm.com {
+            return Err(AuditError::BadCommitment { party: party(i) });
+        }
+    }
+
+    for (i, (vss, decom)) in transcript
+        .vss_schemes
+        .iter()
+        .zip(&transcript.decommitments)
+        .enumerate()
+    {
+        if vss.parameters.threshold != t
+            || vss.parameters.share_count != n
+            || vss.commitments.len() != t + 1
+            || vss.commitments[0] != decom.y_i
+        {
+            return Err(AuditError::InconsistentVss { party: party(i) });
+        }
+    }
+
+    for (i, proof) in transcript.dlog_proofs.iter().enumerate() {
+        let vk_i = public_share(&transcript.vss_schemes, KeygenIndex(party(i)));
+        if proof.pk != vk_i || DLogProof::verify(proof).is_err() {
+            return Err(AuditError::InvalidDLogProof { party: party(i) });
+        }
+    }
+
+    let (head, tail) = transcript.decommitments.split_at(1);
+    Ok(tail.iter().fold(head[0].y_i, |acc, decom| acc + decom.y_i))
+}
+
+fn party(i: usize) -> u16 {
+    (i + 1) as u16
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum AuditError {
+    #[error("transcript doesn't match keygen parameters")]
+    MismatchedLengths,
+    #[error("party {party} didn't open its commitment")]
+    BadCommitment { party: u16 },
+    #[error("vss scheme of party {party} doesn't match committed key")]
+    InconsistentVss { party: u16 },
+    #[error("dlog proof of party {party} is invalid or doesn't match vss schemes")]
+    InvalidDLogProof { party: u16 },
+}
@@ -1,7 +1,221 @@
 #![allow(non_snake_case)]
 
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::{
+    ShamirSecretSharing, VerifiableSS,
+};
+use std::convert::TryFrom;
+use std::fmt;
+
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregated_bls::party_i::Keys as AggregatedKeys;
+use crate::basic_bls::{hash_to_g1, BLSSignature};
+use crate::threshold_bls::party_i::PartialSignature;
+use crate::Error;
+
+pub use audit::{audit_keygen, AuditError, KeygenTranscript};
+pub use state_machine::keygen::deal;
+
+pub mod audit;
 pub mod party_i;
+pub mod secret_scalar;
 pub mod state_machine;
 #[cfg(any(test, feature = "dev"))]
 pub mod test;
 pub mod utilities;
+
+/// Index of party at keygen, in range `[1; n]` where `n` is number of parties holding a key
+///
+/// Identifies party's share and its verification key. Not to be confused with
+/// [SignerIndex](state_machine::SignerIndex): the same party may have different indexes at
+/// keygen and at signing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeygenIndex(pub u16);
+
+impl KeygenIndex {
+    /// Position of party's verification key in `vk_vec` of `n` parties
+    ///
+    /// Returns `None` if index is not in range `[1; n]`.
+    pub fn position(self, n: u16) -> Option<usize> {
+        if self.0 == 0 || self.0 > n {
+            None
+        } else {
+            Some(usize::from(self.0) - 1)
+        }
+    }
+
+    /// Index of party whose verification key is at `position` in `vk_vec`, i.e. whose share
+    /// has 0-based [index](party_i::SharedKeys::index) `position`
+    ///
+    /// Returns `None` if index doesn't fit into `u16`.
+    pub fn from_position(position: usize) -> Option<Self> {
+        u16::try_from(position)
+            .ok()?
+            .checked_add(1)
+            .map(KeygenIndex)
+    }
+}
+
+impl fmt::Display for KeygenIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Aggregates threshold signatures produced by several independent groups into one signature
+///
+/// Resulting signature can be verified via [verify_aggregate]. Panics if `sigs` is empty.
+pub fn aggregate_signatures(sigs: &[BLSSignature]) -> BLSSignature {
+    AggregatedKeys::batch_aggregate_bls(sigs)
+}
+
+/// Verifies signature obtained by [aggregate_signatures]
+///
+/// `group_keys[i]` is a public key of the group that signed `msgs[i]`. Same as
+/// [aggregate_verify](AggregatedKeys::aggregate_verify), verification fails if any message
+/// is repeated.
+pub fn verify_aggregate(group_keys: &[GE2], msgs: &[&[u8]], agg: &BLSSignature) -> bool {
+    if group_keys.is_empty() || group_keys.len() != msgs.len() {
+        return false;
+    }
+    AggregatedKeys::aggregate_verify(group_keys, msgs, agg)
+}
+
+/// Computes group public key from verification keys of parties
+///
+/// `vk_vec[i]` is a verification key of party with index `i` (as broadcasted in the last round
+/// of keygen). Public key is interpolated from the first `threshold+1` verification keys, and
+/// every other key is checked to lie on the same polynomial of degree `threshold`, so any
+/// quorum of parties agrees with the returned key.
+///
+/// Returns [Error::InconsistentVerificationKeys] if `vk_vec` doesn't have exactly `share_count`
+/// keys, or if they don't lie on a single polynomial of degree `threshold`.
+pub fn group_public_key(vk_vec: &[GE2], params: &ShamirSecretSharing) -> Result<GE2, Error> {
+    let t = params.threshold;
+    if vk_vec.len() != params.share_count || t >= params.share_count {
+        return Err(Error::InconsistentVerificationKeys);
+    }
+    let interpolate = |s: &[usize]| {
+        let (head, tail) = s.split_at(1);
+        tail.iter().fold(
+            vk_vec[head[0]] * &VerifiableSS::<GE2>::map_share_to_new_params(params, head[0], s),
+            |acc, &j| acc + vk_vec[j] * &VerifiableSS::<GE2>::map_share_to_new_params(params, j, s),
+        )
+    };
+    let s: Vec<usize> = (0..=t).collect();
+    let vk = interpolate(&s);
+    // Polynomial of degree t is determined by t+1 points, so vk_j lies on it iff replacing
+    // the last of the first t+1 keys with vk_j gives the same value at zero
+    for j in t + 1..params.share_count {
+        let s: Vec<usize> = (0..t).chain(std::iter::once(j)).collect();
+        if interpolate(&s) != vk {
+            return Err(Error::InconsistentVerificationKeys);
+        }
+    }
+    Ok(vk)
+}
+
+/// Checks that `sig` over `message` was produced by a quorum of at least `threshold+1` parties
+/// holding shares behind `vk_vec`
+///
+/// Signature is checked against group public key interpolated from `vk_vec` (see
+/// [group_public_key]), and every key of `vk_vec` is checked to agree with it, so the guarantee
+/// holds for any quorum of parties. No proof of quorum size is needed on top of that:
+/// producing a valid signature requires `threshold+1` shares, so any coalition of at most
+/// `threshold` parties can't forge it, as long as BLS signatures are unforgeable.
+///
+/// At the same time, signature reveals nothing about who signed: BLS signature is
+/// deterministic, so every quorum obtains exactly the same signature. Identifying signers
+/// requires additional data that must be kept alongside the signature, e.g. their partial
+/// signatures (see [PartialAggregate](party_i::PartialAggregate)).
+///
+/// Returns `false` if `vk_vec` doesn't have exactly `share_count` verification keys, or if
+/// they're inconsistent.
+pub fn verify_threshold_property(
+    vk_vec: &[GE2],
+    params: &ShamirSecretSharing,
+    message: &[u8],
+    sig: &BLSSignature,
+) -> bool {
+    match group_public_key(vk_vec, params) {
+        Ok(vk) => sig.verify(message, &vk),
+        Err(_) => false,
+    }
+}
+
+/// Computes verification key `vk_i = sk_i·G` of party with given `index` from VSS commitments
+///
+/// `vss_commitments` are VSS schemes broadcasted by every party at keygen, `index` is keygen
+/// index of the party (see [SharedKeys::keygen_index](party_i::SharedKeys::keygen_index)).
+/// Lets a verifier that kept only keygen commitments check partial signatures of any party.
+/// Panics if `vss_commitments` is empty.
+pub fn public_share(vss_commitments: &[VerifiableSS<GE2>], index: KeygenIndex) -> GE2 {
+    let point = usize::from(index.0);
+    let (head, tail) = vss_commitments.split_at(1);
+    tail.iter()
+        .fold(head[0].get_point_commitment(point), |acc, vss| {
+            acc + vss.get_point_commitment(point)
+        })
+}
+
+/// Evidence that a party produced valid partial signatures over two different messages
+///
+/// Obtained via [detect_equivocation]. Anyone knowing verification keys of the group can
+/// re-check the evidence via [verify](Self::verify). Proof doesn't carry verification key of
+/// the party, otherwise anyone could make up a proof under their own key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EquivocationProof {
+    pub msg_a: Vec<u8>,
+    pub partial_a: PartialSignature,
+    pub msg_b: Vec<u8>,
+    pub partial_b: PartialSignature,
+}
+
+impl EquivocationProof {
+    /// Checks that messages differ, both partial signatures are made by the same party, and
+    /// both of them are valid under its verification key
+    ///
+    /// `vk_vec` is a list of verification keys of the group, `vk_vec[i-1]` belongs to the party
+    /// with keygen index `i`. Verification key is looked up by index of the partial signatures.
+    pub fn verify(&self, vk_vec: &[GE2]) -> bool {
+        let vk_i = match u16::try_from(vk_vec.len())
+            .ok()
+            .and_then(|n| self.partial_a.index.position(n))
+        {
+            Some(position) => &vk_vec[position],
+            None => return false,
+        };
+        self.msg_a != self.msg_b
+            && self.partial_a.index == self.partial_b.index
+            && self.partial_a.verify(&hash_to_g1(&self.msg_a), vk_i)
+            && self.partial_b.verify(&hash_to_g1(&self.msg_b), vk_i)
+    }
+}
+
+/// Detects whether a party of the group holding verification keys `vk_vec` signed two
+/// conflicting messages
+///
+/// Returns [EquivocationProof] if `msg_a != msg_b`, both partial signatures claim the same
+/// index, and both of them pass verification under the key of that index (see
+/// [EquivocationProof::verify]). Otherwise returns `None`.
+pub fn detect_equivocation(
+    partial_a: &PartialSignature,
+    msg_a: &[u8],
+    partial_b: &PartialSignature,
+    msg_b: &[u8],
+    vk_vec: &[GE2],
+) -> Option<EquivocationProof> {
+    let proof = EquivocationProof {
+        msg_a: msg_a.to_vec(),
+        partial_a: partial_a.clone(),
+        msg_b: msg_b.to_vec(),
+        partial_b: partial_b.clone(),
+    };
+    if proof.verify(vk_vec) {
+        Some(proof)
+    } else {
+        None
+    }
+}
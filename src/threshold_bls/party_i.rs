@@ -1,4 +1,7 @@
-use crate::Error;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::{Error, ParseError};
 
 use curv::arithmetic::traits::*;
 
@@ -6,20 +9,36 @@ use curv::elliptic::curves::traits::*;
 
 use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
 use curv::cryptographic_primitives::commitments::traits::Commitment;
+use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
+use curv::cryptographic_primitives::hashing::traits::Hash;
 use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
 use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
 use curv::BigInt;
 
-use crate::basic_bls::BLSSignature;
+use crate::basic_bls::{g1_from_bytes, g2_from_bytes, hash_to_g1, BLSSignature, KeyPairG2};
+use crate::threshold_bls::secret_scalar;
 use crate::threshold_bls::utilities::{ECDDHProof, ECDDHStatement, ECDDHWitness};
+use crate::threshold_bls::{group_public_key, KeygenIndex};
 use curv::cryptographic_primitives::secret_sharing::feldman_vss::ShamirSecretSharing;
 use curv::elliptic::curves::bls12_381::g1::FE as FE1;
 use curv::elliptic::curves::bls12_381::g1::GE as GE1;
 use curv::elliptic::curves::bls12_381::g2::FE as FE2;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use ff_zeroize::Field;
+use pairing_plus::bls12_381::{Bls12, Fq12, G1Affine, G2Affine};
+use pairing_plus::serdes::SerDes;
+use pairing_plus::{CurveAffine, Engine};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
 
-const SECURITY: usize = 256;
+/// Default bit length of the blinding factor used in keygen hash commitments
+///
+/// Commitments are computed with SHA-256, so a 256 bits blinding factor matches the hash output
+/// size and doesn't become the weakest link of the hiding property.
+pub const SECURITY: usize = 256;
+/// Minimal accepted bit length of the commitment blinding factor
+pub const MIN_SECURITY: usize = 128;
 
 /// The protocol follows threshold GLOW signature from  [https://eprint.iacr.org/2020/096.pdf] section VIII.
 /// In our protocol we assume dishonest majority. We adapt the DKG accordingly.
@@ -41,37 +60,443 @@ pub struct Keys {
     pub party_index: usize,
 }
 
+impl Zeroize for Keys {
+    fn zeroize(&mut self) {
+        self.u_i.zeroize();
+    }
+}
+
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct KeyGenComm {
     pub com: BigInt,
 }
 
+/// Size of SHA-256 hash commitment in bytes
+pub const COMMITMENT_BYTES_LEN: usize = 32;
+
+impl KeyGenComm {
+    /// Checks that commitment fits into [COMMITMENT_BYTES_LEN] bytes
+    ///
+    /// Commitment received from other party must be checked before it's stored, so the party
+    /// can't make us keep and compare arbitrarily large integers.
+    pub fn is_well_sized(&self) -> bool {
+        self.com.bit_length() <= 8 * COMMITMENT_BYTES_LEN
+    }
+}
+
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct KeyGenDecom {
     pub blind_factor: BigInt,
     pub y_i: GE2,
 }
 
+/// Secret share of a party along with public parameters of the key
+///
+/// Secret share `sk_i` is serialized in constant time (see
+/// [secret_scalar](crate::threshold_bls::secret_scalar)).
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SharedKeys {
     pub index: usize,
     pub params: ShamirSecretSharing,
     pub vk: GE2,
+    #[serde(with = "crate::threshold_bls::secret_scalar")]
     pub sk_i: FE2,
 }
 
+impl Zeroize for SharedKeys {
+    fn zeroize(&mut self) {
+        self.sk_i.zeroize();
+    }
+}
+
+/// Shares held by a single party in weighted threshold setting
+///
+/// Party of weight `w` holds `w` distinct shares (see [weighted_share_indexes]) and contributes
+/// a partial signature for each of them. Threshold is counted in shares, i.e. any set of
+/// parties with total weight at least `threshold+1` can sign.
+///
+/// Weighted shares are supported by the party-level API only. The
+/// [keygen](super::state_machine::keygen) and [sign](super::state_machine::sign) state machines
+/// still assume one share per party.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeightedSharedKeys {
+    pub shares: Vec<SharedKeys>,
+}
+
+/// Holder of a secret share which produces partial signatures without revealing the share
+///
+/// Lets the share live behind a signing oracle, e.g. in HSM which computes `sk_i * H_x`
+/// along with the ECDDH proof and never exports `sk_i`. [SharedKeys] is a software
+/// implementation keeping the share in memory.
+pub trait PartialSigner {
+    /// Keygen index of the party holding the share (see [SharedKeys::keygen_index])
+    fn index(&self) -> KeygenIndex;
+    /// Computes `sigma_i = sk_i * H_x` and proves that `sigma_i` and `vk_i = sk_i * G2` share
+    /// the same discrete log
+    fn sign_point(&self, H_x: &GE1) -> (GE1, ECDDHProof);
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct PartialSignature {
-    pub index: usize,
+    /// Keygen index of the signer, i.e. evaluation point of its share
+    pub index: KeygenIndex,
     pub sigma_i: GE1,
     pub ddh_proof: ECDDHProof,
 }
 
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+impl PartialSignature {
+    /// Signs `message` with secret share `sk_i` of party with given `index`, and proves that
+    /// partial signature is consistent with party's public key
+    ///
+    /// Appropriate when a party holds its share outside of [SharedKeys] (e.g. after
+    /// [export_share](SharedKeys::export_share)). Partial signatures produced by another
+    /// implementation should be constructed via [from_parts](Self::from_parts) instead.
+    pub fn new(index: KeygenIndex, sk_i: &FE2, message: &[u8]) -> (Self, GE1) {
+        let H_x = hash_to_g1(message);
+        let (sigma_i, ddh_proof) = prove_partial(sk_i, &H_x);
+        (
+            PartialSignature {
+                index,
+                sigma_i,
+                ddh_proof,
+            },
+            H_x,
+        )
+    }
+
+    /// Signs `message` with a share held by `signer`
+    ///
+    /// Unlike [new](Self::new), the proof isn't checked as signer may be external: such
+    /// partial signature must be [verified](Self::verify) before it's trusted, which
+    /// [combine](SharedKeys::combine) does for every partial signature.
+    pub fn sign<S: PartialSigner + ?Sized>(signer: &S, message: &[u8]) -> (Self, GE1) {
+        let H_x = hash_to_g1(message);
+        (Self::sign_point(signer, &H_x), H_x)
+    }
+
+    /// Same as [sign](Self::sign), but message is already a point on G1, so it's signed as is
+    pub fn sign_point<S: PartialSigner + ?Sized>(signer: &S, H_x: &GE1) -> Self {
+        let (sigma_i, ddh_proof) = signer.sign_point(H_x);
+        PartialSignature {
+            index: signer.index(),
+            sigma_i,
+            ddh_proof,
+        }
+    }
+
+    /// Assembles partial signature produced and proven by external signer
+    ///
+    /// No checks are performed: such partial signature must be checked via
+    /// [verify](Self::verify) before it's trusted. [combine](SharedKeys::combine) does it
+    /// for every partial signature it's given.
+    pub fn from_parts(index: KeygenIndex, sigma_i: GE1, ddh_proof: ECDDHProof) -> Self {
+        PartialSignature {
+            index,
+            sigma_i,
+            ddh_proof,
+        }
+    }
+
+    /// Verifies ECDDH proof of partial signature over message `H_x` given public key `vk_i` of
+    /// the signer
+    pub fn verify(&self, H_x: &GE1, vk_i: &GE2) -> bool {
+        let delta = ECDDHStatement {
+            g1: H_x.clone(),
+            h1: self.sigma_i.clone(),
+            g2: GE2::generator(),
+            h2: vk_i.clone(),
+        };
+        self.ddh_proof.verify(&delta)
+    }
+
+    /// Size of partial signature serialized via [to_bytes](Self::to_bytes)
+    pub const BYTES_LEN: usize = 2 + 48 + ECDDHProof::BYTES_LEN;
+
+    /// Serializes partial signature as keygen `index` (2 bytes big-endian), compressed `sigma_i`
+    /// (48 bytes), and [ECDDH proof](ECDDHProof::to_bytes)
+    ///
+    /// Fails only if the proof can't be serialized, which never happens for partial signatures
+    /// produced by this party.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ParseError> {
+        let mut bytes = Vec::with_capacity(Self::BYTES_LEN);
+        bytes.extend_from_slice(&self.index.0.to_be_bytes());
+        G1Affine::serialize(&self.sigma_i.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        bytes.extend_from_slice(&self.ddh_proof.to_bytes()?);
+        Ok(bytes)
+    }
+
+    /// Parses partial signature serialized via [to_bytes](Self::to_bytes)
+    ///
+    /// Only encoding is checked, partial signature still must be [verified](Self::verify)
+    /// before it's trusted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != Self::BYTES_LEN {
+            return Err(ParseError::InvalidLength);
+        }
+        let index = u16::from_be_bytes([bytes[0], bytes[1]]);
+        if index == 0 {
+            return Err(ParseError::InvalidEncoding);
+        }
+        let sigma_i = g1_from_bytes(&bytes[2..50])?;
+        let ddh_proof = ECDDHProof::from_bytes(&bytes[50..])?;
+        Ok(PartialSignature {
+            index: KeygenIndex(index),
+            sigma_i,
+            ddh_proof,
+        })
+    }
+}
+
+/// Set of verified partial signatures collected so far
+///
+/// Lets a coordinator checkpoint signing progress before the quorum is reached. Partial
+/// signatures are kept along with their proofs, so a challenger can re-verify the checkpoint
+/// via [verify_subset_against](Self::verify_subset_against). [merkle_root](Self::merkle_root)
+/// commits to the collected set, and [merkle_proof](Self::merkle_proof) proves that partial
+/// signature of a single party is included in it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PartialAggregate {
+    H_x: GE1,
+    partials: Vec<PartialSignature>,
+}
+
+impl PartialAggregate {
+    /// Constructs empty aggregate of partial signatures over message `H_x`
+    pub fn new(H_x: GE1) -> Self {
+        Self {
+            H_x,
+            partials: vec![],
+        }
+    }
+
+    /// Verifies partial signature and adds it to the aggregate
+    ///
+    /// Returns error if partial signature is invalid, or partial signature of the same party
+    /// was already added.
+    pub fn add(&mut self, partial: PartialSignature, vk_i: &GE2) -> Result<(), Error> {
+        if !partial.verify(&self.H_x, vk_i) {
+            return Err(Error::PartialSignatureVerificationError);
+        }
+        match self
+            .partials
+            .binary_search_by_key(&partial.index, |p| p.index)
+        {
+            Ok(_) => Err(Error::PartialSignatureDuplicate),
+            Err(pos) => {
+                self.partials.insert(pos, partial);
+                Ok(())
+            }
+        }
+    }
+
+    /// Message the partial signatures are made over
+    pub fn message(&self) -> &GE1 {
+        &self.H_x
+    }
+
+    /// Collected partial signatures sorted by party index
+    pub fn partials(&self) -> &[PartialSignature] {
+        &self.partials
+    }
+
+    /// Merkle root over `(index, sigma_i)` of collected partial signatures sorted by index
+    ///
+    /// Leaves and inner nodes are hashed with distinct prefixes (see [MerkleProof]), so a node
+    /// can't be passed off as a leaf. Root of empty aggregate is all zeroes.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        match self.merkle_layers().last() {
+            Some(root) => root[0],
+            None => [0u8; 32],
+        }
+    }
+
+    /// Proves that partial signature of party with given keygen `index` is included in
+    /// [merkle_root](Self::merkle_root)
+    ///
+    /// Returns `None` if aggregate has no partial signature of the party.
+    pub fn merkle_proof(&self, index: KeygenIndex) -> Option<MerkleProof> {
+        let position = self
+            .partials
+            .binary_search_by_key(&index, |p| p.index)
+            .ok()?;
+        let siblings = self
+            .merkle_layers()
+            .iter()
+            .enumerate()
+            .filter_map(|(level, layer)| layer.get((position >> level) ^ 1).copied())
+            .collect();
+        Some(MerkleProof {
+            position,
+            leaves: self.partials.len(),
+            siblings,
+        })
+    }
+
+    /// Layers of merkle tree from leaves to root, empty if there are no partial signatures
+    fn merkle_layers(&self) -> Vec<Vec<[u8; 32]>> {
+        let leaves: Vec<_> = self.partials.iter().map(merkle_leaf).collect();
+        if leaves.is_empty() {
+            return vec![];
+        }
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => merkle_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks(2) yields one or two elements"),
+                })
+                .collect();
+            layers.push(layer);
+        }
+        layers
+    }
+
+    /// Re-verifies every collected partial signature
+    ///
+    /// `vk_vec` is a list of public keys of all parties holding a key, `vk_vec[i-1]` belongs to
+    /// party with [keygen index](PartialSignature::index) `i`. Fails if aggregate was collected
+    /// for a message other than `H_x`.
+    pub fn verify_subset_against(&self, H_x: &GE1, vk_vec: &[GE2]) -> Result<(), Error> {
+        if &self.H_x != H_x {
+            return Err(Error::PartialSignatureVerificationError);
+        }
+        let n = u16::try_from(vk_vec.len()).map_err(|_| Error::SigningMisMatchedVectors)?;
+        for partial in &self.partials {
+            let vk_i = partial
+                .index
+                .position(n)
+                .map(|position| &vk_vec[position])
+                .ok_or(Error::SigningMisMatchedVectors)?;
+            if !partial.verify(H_x, vk_i) {
+                return Err(Error::PartialSignatureVerificationError);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Proof that partial signature is included in [PartialAggregate::merkle_root]
+///
+/// Leaf is `SHA256(0x00 || index || sigma_i)` where index is 2 bytes big-endian and `sigma_i`
+/// is compressed, inner node is `SHA256(0x01 || left || right)`. Node without a pair is moved
+/// to the next level as is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Position of the leaf among partial signatures sorted by index
+    pub position: usize,
+    /// Number of leaves in the tree
+    pub leaves: usize,
+    /// Hashes of sibling nodes from the leaf up to the root
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Checks that `partial` is included in the tree with given `root`
+    pub fn verify(&self, root: &[u8; 32], partial: &PartialSignature) -> bool {
+        if self.position >= self.leaves {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let (mut node, mut position, mut width) =
+            (merkle_leaf(partial), self.position, self.leaves);
+        while width > 1 {
+            if position ^ 1 < width {
+                let sibling = match siblings.next() {
+                    Some(sibling) => sibling,
+                    None => return false,
+                };
+                node = if position % 2 == 0 {
+                    merkle_node(&node, sibling)
+                } else {
+                    merkle_node(sibling, &node)
+                };
+            }
+            position /= 2;
+            width = (width + 1) / 2;
+        }
+        siblings.next().is_none() && node == *root
+    }
+}
+
+fn merkle_leaf(partial: &PartialSignature) -> [u8; 32] {
+    let mut sigma_i = vec![];
+    G1Affine::serialize(&partial.sigma_i.get_element(), &mut sigma_i, true)
+        .expect("serialize to vec should always succeed");
+    let hash = Sha256::new()
+        .chain(&[0x00u8])
+        .chain(&partial.index.0.to_be_bytes())
+        .chain(&sigma_i)
+        .result();
+    let mut leaf = [0u8; 32];
+    leaf.copy_from_slice(&hash);
+    leaf
+}
+
+fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let hash = Sha256::new()
+        .chain(&[0x01u8])
+        .chain(left)
+        .chain(right)
+        .result();
+    let mut node = [0u8; 32];
+    node.copy_from_slice(&hash);
+    node
+}
+
+/// Error returned by [SharedKeys::combine_verbose]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CombineError {
+    /// Lengths of input vectors are inconsistent with each other or with threshold parameters
+    MisMatchedVectors,
+    /// Partial signatures didn't pass verification. Contains keygen index of the party behind
+    /// every invalid partial signature.
+    InvalidPartials(Vec<KeygenIndex>),
+    /// Partial signature of `signer` claims index of another party
+    IndexMismatch { signer: KeygenIndex },
+}
+
+impl From<CombineError> for Error {
+    fn from(err: CombineError) -> Self {
+        match err {
+            CombineError::MisMatchedVectors | CombineError::IndexMismatch { .. } => {
+                Error::SigningMisMatchedVectors
+            }
+            CombineError::InvalidPartials(_) => Error::PartialSignatureVerificationError,
+        }
+    }
+}
+
+/// Serializable form of [BLSSignature]
+///
+/// Holds the same G1 point as [BLSSignature] and converts to and from it losslessly. Use it
+/// where a signature needs to be sent or stored via serde, and [BLSSignature] to verify it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Signature {
     pub sigma: GE1,
 }
 
+impl Signature {
+    /// Serializes signature the same way as [BLSSignature::to_bytes]
+    pub fn to_bytes(&self, compressed: bool) -> Vec<u8> {
+        BLSSignature::from(self.clone()).to_bytes(compressed)
+    }
+}
+
+impl From<Signature> for BLSSignature {
+    fn from(sig: Signature) -> Self {
+        BLSSignature { sigma: sig.sigma }
+    }
+}
+
+impl From<BLSSignature> for Signature {
+    fn from(sig: BLSSignature) -> Self {
+        Signature { sigma: sig.sigma }
+    }
+}
+
 impl Keys {
     pub fn phase1_create(index: usize) -> Keys {
         let u: FE2 = ECScalar::new_random();
@@ -85,7 +510,20 @@ impl Keys {
     }
 
     pub fn phase1_broadcast(&self) -> (KeyGenComm, KeyGenDecom) {
-        let blind_factor = BigInt::sample(SECURITY);
+        self.phase1_broadcast_with_security(SECURITY)
+            .expect("default security parameter is always valid")
+    }
+
+    /// Same as [phase1_broadcast](Self::phase1_broadcast), but samples blinding factor of
+    /// `security_bits` length. Returns error if `security_bits` is less than [MIN_SECURITY].
+    pub fn phase1_broadcast_with_security(
+        &self,
+        security_bits: usize,
+    ) -> Result<(KeyGenComm, KeyGenDecom), Error> {
+        if security_bits < MIN_SECURITY {
+            return Err(Error::KeyGenWeakSecurityParameter);
+        }
+        let blind_factor = BigInt::sample(security_bits);
         let com = HashCommitment::create_commitment_with_user_defined_randomness(
             &(self.y_i.bytes_compressed_to_big_int() + BigInt::from(self.party_index as u32)), // we add context to the hash function
             &blind_factor,
@@ -95,7 +533,7 @@ impl Keys {
             blind_factor,
             y_i: self.y_i.clone(),
         };
-        (bcm1, decm1)
+        Ok((bcm1, decm1))
     }
 
     pub fn phase1_verify_com_phase2_distribute(
@@ -143,13 +581,8 @@ impl Keys {
         }
 
         let correct_ss_verify = (0..y_vec.len())
-            .map(|i| {
-                vss_scheme_vec[i]
-                    .validate_share(&secret_shares_vec[i], *index)
-                    .is_ok()
-                    && vss_scheme_vec[i].commitments[0] == y_vec[i]
-            })
-            .all(|x| x == true);
+            .all(|i| vss_scheme_vec[i].commitments[0] == y_vec[i])
+            && validate_shares_batched(secret_shares_vec, vss_scheme_vec, *index);
 
         match correct_ss_verify {
             true => {
@@ -171,6 +604,103 @@ impl Keys {
         }
     }
 
+    /// Weighted version of [phase1_verify_com_phase2_distribute](Self::phase1_verify_com_phase2_distribute)
+    ///
+    /// `params.share_count` must be equal to the sum of `weights`, while `decom_vec` and
+    /// `bc1_vec` contain one item per party. Returns VSS scheme and shares grouped by
+    /// receiving party according to [weighted_share_indexes].
+    pub fn phase1_verify_com_phase2_distribute_weighted(
+        &self,
+        params: &ShamirSecretSharing,
+        weights: &[u16],
+        decom_vec: &[KeyGenDecom],
+        bc1_vec: &[KeyGenComm],
+    ) -> Result<(VerifiableSS<GE2>, Vec<Vec<FE2>>, usize), Error> {
+        if weights.iter().map(|&w| usize::from(w)).sum::<usize>() != params.share_count
+            || decom_vec.len() != weights.len()
+            || bc1_vec.len() != weights.len()
+        {
+            return Err(Error::KeyGenMisMatchedVectors);
+        }
+        let correct_key_correct_decom_all = (0..bc1_vec.len()).all(|i| {
+            HashCommitment::create_commitment_with_user_defined_randomness(
+                &(decom_vec[i].y_i.bytes_compressed_to_big_int() + BigInt::from(i as u32)),
+                &decom_vec[i].blind_factor,
+            ) == bc1_vec[i].com
+        });
+        if !correct_key_correct_decom_all {
+            return Err(Error::KeyGenBadCommitment);
+        }
+
+        let (vss_scheme, secret_shares) =
+            VerifiableSS::share(params.threshold, params.share_count, &self.u_i);
+        let party_shares = weighted_share_indexes(weights)
+            .into_iter()
+            .map(|indexes| indexes.into_iter().map(|k| secret_shares[k]).collect())
+            .collect();
+        Ok((vss_scheme, party_shares, self.party_index))
+    }
+
+    /// Weighted version of [phase2_verify_vss_construct_keypair_prove_dlog](Self::phase2_verify_vss_construct_keypair_prove_dlog)
+    ///
+    /// `y_vec`, `secret_shares_vec` and `vss_scheme_vec` contain one item per party, where
+    /// `secret_shares_vec[j]` are shares dealt by party `j` to this party. Constructs a key and
+    /// DLog proof for every share held by this party.
+    pub fn phase2_verify_vss_construct_keypairs_prove_dlog_weighted(
+        &self,
+        params: &ShamirSecretSharing,
+        weights: &[u16],
+        y_vec: &[GE2],
+        secret_shares_vec: &[Vec<FE2>],
+        vss_scheme_vec: &[VerifiableSS<GE2>],
+    ) -> Result<(WeightedSharedKeys, Vec<DLogProof<GE2>>), Error> {
+        let indexes = weighted_share_indexes(weights)
+            .into_iter()
+            .nth(self.party_index)
+            .ok_or(Error::KeyGenMisMatchedVectors)?;
+        if weights.iter().map(|&w| usize::from(w)).sum::<usize>() != params.share_count
+            || y_vec.len() != weights.len()
+            || secret_shares_vec.len() != weights.len()
+            || vss_scheme_vec.len() != weights.len()
+            || secret_shares_vec.iter().any(|s| s.len() != indexes.len())
+        {
+            return Err(Error::KeyGenMisMatchedVectors);
+        }
+
+        let correct_ss_verify = (0..y_vec.len()).all(|j| {
+            vss_scheme_vec[j].commitments[0] == y_vec[j]
+                && indexes.iter().enumerate().all(|(k, &index)| {
+                    vss_scheme_vec[j]
+                        .validate_share(&secret_shares_vec[j][k], index + 1)
+                        .is_ok()
+                })
+        });
+        if !correct_ss_verify {
+            return Err(Error::KeyGenInvalidShare);
+        }
+
+        let (head, tail) = y_vec.split_at(1);
+        let y = tail.iter().fold(head[0], |acc, x| acc + x);
+        let (shares, dlog_proofs) = indexes
+            .iter()
+            .enumerate()
+            .map(|(k, &index)| {
+                let x_i = secret_shares_vec
+                    .iter()
+                    .fold(FE2::zero(), |acc, shares| acc + shares[k]);
+                let dlog_proof = DLogProof::prove(&x_i);
+                let shared_keys = SharedKeys {
+                    index,
+                    params: params.clone(),
+                    vk: y,
+                    sk_i: x_i,
+                };
+                (shared_keys, dlog_proof)
+            })
+            .unzip();
+        Ok((WeightedSharedKeys { shares }, dlog_proofs))
+    }
+
     pub fn verify_dlog_proofs(
         params: &ShamirSecretSharing,
         dlog_proofs_vec: &[DLogProof<GE2>],
@@ -188,6 +718,298 @@ impl Keys {
             Err(Error::KeyGenDlogProofError)
         }
     }
+
+    /// Same as [verify_dlog_proofs](Self::verify_dlog_proofs), but verifies all proofs at once
+    ///
+    /// Checks a random linear combination of verification equations `G·z_i + e_i·pk_i = R_i`,
+    /// which takes a single multiplication by generator instead of one per proof. Use
+    /// [find_invalid_dlog_proofs] to figure out which proofs are invalid if batch didn't pass
+    /// verification.
+    pub fn verify_dlog_proofs_batched(
+        params: &ShamirSecretSharing,
+        dlog_proofs_vec: &[DLogProof<GE2>],
+    ) -> Result<(), Error> {
+        if dlog_proofs_vec.len() != params.share_count {
+            return Err(Error::KeyGenMisMatchedVectors);
+        }
+        if dlog_proofs_vec.is_empty() {
+            // Nothing to verify, same as in verify_dlog_proofs
+            return Ok(());
+        }
+        let generator = GE2::generator();
+        let mut responses_sum = FE2::zero();
+        let mut lhs = vec![];
+        let mut rhs = vec![];
+        for proof in dlog_proofs_vec {
+            // Challenge is computed the same way as in DLogProof::verify
+            let challenge = HSha256::create_hash(&[
+                &proof.pk_t_rand_commitment.bytes_compressed_to_big_int(),
+                &generator.bytes_compressed_to_big_int(),
+                &proof.pk.bytes_compressed_to_big_int(),
+            ]);
+            let e: FE2 = ECScalar::from(&challenge);
+            let r: FE2 = ECScalar::from(&BigInt::sample(BATCH_SECURITY));
+            responses_sum =
+                responses_sum.add(&r.mul(&proof.challenge_response.get_element()).get_element());
+            lhs.push(proof.pk * &r.mul(&e.get_element()));
+            rhs.push(proof.pk_t_rand_commitment * &r);
+        }
+        lhs.push(generator * &responses_sum);
+
+        let (lhs_head, lhs_tail) = lhs.split_at(1);
+        let (rhs_head, rhs_tail) = rhs.split_at(1);
+        let lhs = lhs_tail.iter().fold(lhs_head[0], |acc, x| acc + x);
+        let rhs = rhs_tail.iter().fold(rhs_head[0], |acc, x| acc + x);
+        if lhs == rhs {
+            Ok(())
+        } else {
+            Err(Error::KeyGenDlogProofError)
+        }
+    }
+}
+
+/// Verifies DLog proofs one by one
+///
+/// Returns positions of invalid proofs. Identifies misbehaving parties after
+/// [Keys::verify_dlog_proofs_batched] failed with [Error::KeyGenDlogProofError].
+pub fn find_invalid_dlog_proofs(dlog_proofs_vec: &[DLogProof<GE2>]) -> Vec<usize> {
+    dlog_proofs_vec
+        .iter()
+        .enumerate()
+        .filter(|(_, proof)| DLogProof::verify(proof).is_err())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Encrypts VSS share sent by party `sender` to party `receiver`
+///
+/// Share is masked with a key derived from Diffie-Hellman of sender's ephemeral secret key and
+/// receiver's ephemeral public key, so it can't be recovered by anyone observing the transport.
+/// Ephemeral keys must be freshly generated for every keygen.
+pub fn encrypt_share(
+    share: &FE2,
+    ephemeral_sk: &FE2,
+    receiver_ephemeral_pk: &GE2,
+    sender: usize,
+    receiver: usize,
+) -> FE2 {
+    let mask = share_mask(ephemeral_sk, receiver_ephemeral_pk, sender, receiver);
+    share.add(&mask.get_element())
+}
+
+/// Decrypts VSS share encrypted via [encrypt_share]
+pub fn decrypt_share(
+    encrypted_share: &FE2,
+    ephemeral_sk: &FE2,
+    sender_ephemeral_pk: &GE2,
+    sender: usize,
+    receiver: usize,
+) -> FE2 {
+    let mask = share_mask(ephemeral_sk, sender_ephemeral_pk, sender, receiver);
+    encrypted_share.sub(&mask.get_element())
+}
+
+fn share_mask(ephemeral_sk: &FE2, other_ephemeral_pk: &GE2, sender: usize, receiver: usize) -> FE2 {
+    let shared_secret = other_ephemeral_pk * ephemeral_sk;
+    let mask = HSha256::create_hash(&[
+        &shared_secret.bytes_compressed_to_big_int(),
+        &BigInt::from(sender as u32),
+        &BigInt::from(receiver as u32),
+    ]);
+    ECScalar::from(&mask)
+}
+
+/// Assigns share indexes to parties of given weights
+///
+/// Party `i` receives `weights[i]` consecutive share indexes, starting right after the indexes
+/// of party `i-1`. Share indexes are 0-based, as [SharedKeys::index].
+pub fn weighted_share_indexes(weights: &[u16]) -> Vec<Vec<usize>> {
+    let mut next = 0;
+    weights
+        .iter()
+        .map(|&w| {
+            let indexes = (next..next + usize::from(w)).collect();
+            next += usize::from(w);
+            indexes
+        })
+        .collect()
+}
+
+impl WeightedSharedKeys {
+    /// Number of shares held by the party
+    pub fn weight(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Produces partial signature for every share held by the party
+    pub fn partial_sign(&self, x: &[u8]) -> (Vec<PartialSignature>, GE1) {
+        let H_x = hash_to_g1(x);
+        let partial_sigs = self
+            .shares
+            .iter()
+            .map(|shared_keys| PartialSignature::sign_point(shared_keys, &H_x))
+            .collect();
+        (partial_sigs, H_x)
+    }
+}
+
+impl PartialSigner for SharedKeys {
+    fn index(&self) -> KeygenIndex {
+        self.keygen_index()
+    }
+    fn sign_point(&self, H_x: &GE1) -> (GE1, ECDDHProof) {
+        prove_partial(&self.sk_i, H_x)
+    }
+}
+
+/// Bit length of random coefficients used in [batched](validate_shares_batched) validation of
+/// VSS shares and [batched](Keys::verify_dlog_proofs_batched) verification of DLog proofs
+///
+/// Batch containing an invalid item passes validation with probability at most
+/// `2^-BATCH_SECURITY`.
+const BATCH_SECURITY: usize = 128;
+
+/// Validates VSS shares received by party `index` at once
+///
+/// Checks that `G·Σ r_i·s_i = C(index)` for random `r_i`, where `C` is the polynomial
+/// commitment with coefficients `C_k = Σ r_i·C_{i,k}` and `C_{i,k}` are VSS commitments of the
+/// `i`-th dealer. Commitments are combined by multiplications by short `r_i`, so `C` is
+/// evaluated once instead of evaluating commitment of every dealer. Use
+/// [find_invalid_shares] to figure out which shares are invalid if batch didn't pass
+/// validation.
+fn validate_shares_batched(
+    secret_shares_vec: &[FE2],
+    vss_scheme_vec: &[VerifiableSS<GE2>],
+    index: usize,
+) -> bool {
+    let coefficients = match vss_scheme_vec.first() {
+        Some(vss) => vss.commitments.len(),
+        None => return false,
+    };
+    if vss_scheme_vec
+        .iter()
+        .any(|vss| vss.commitments.len() != coefficients)
+    {
+        return false;
+    }
+
+    let mut shares_sum = FE2::zero();
+    let mut combined: Vec<Option<GE2>> = vec![None; coefficients];
+    for (share, vss) in secret_shares_vec.iter().zip(vss_scheme_vec) {
+        let r: FE2 = ECScalar::from(&BigInt::sample(BATCH_SECURITY));
+        shares_sum = shares_sum.add(&r.mul(&share.get_element()).get_element());
+        for (sum, commitment) in combined.iter_mut().zip(&vss.commitments) {
+            let point = *commitment * &r;
+            *sum = Some(match sum.take() {
+                Some(sum) => sum + point,
+                None => point,
+            });
+        }
+    }
+
+    // Evaluates combined commitment at `index` via Horner's method
+    let combined: Option<Vec<GE2>> = combined.into_iter().collect();
+    let (highest, rest) = match combined.as_ref().and_then(|c| c.split_last()) {
+        Some(split) => split,
+        None => return false,
+    };
+    let x: FE2 = ECScalar::from(&BigInt::from(index as u32));
+    let evaluation = rest
+        .iter()
+        .rev()
+        .fold(*highest, |acc, coefficient| acc * &x + coefficient);
+    GE2::generator() * &shares_sum == evaluation
+}
+
+/// Checks that `e(Σ r_i·sigma_i, -g2)·Π e(H_x_i, r_i·vk_i) = 1` for random `r_i`
+///
+/// Empty batch trivially passes.
+fn partial_sigs_pass_batch(items: &[(GE1, PartialSignature, GE2)]) -> bool {
+    let mut sigma_sum: Option<GE1> = None;
+    let mut prepared = Vec::with_capacity(items.len() + 1);
+    for (H_x, partial_sig, vk_i) in items {
+        let r = BigInt::sample(BATCH_SECURITY);
+        let r1: FE1 = ECScalar::from(&r);
+        let r2: FE2 = ECScalar::from(&r);
+        let sigma = partial_sig.sigma_i * &r1;
+        sigma_sum = Some(match sigma_sum {
+            Some(sum) => sum + sigma,
+            None => sigma,
+        });
+        prepared.push((
+            H_x.get_element().prepare(),
+            (*vk_i * &r2).get_element().prepare(),
+        ));
+    }
+    let sigma_sum = match sigma_sum {
+        Some(sum) => sum,
+        None => return true,
+    };
+    let mut neg_g2 = G2Affine::one();
+    neg_g2.negate();
+    prepared.push((sigma_sum.get_element().prepare(), neg_g2.prepare()));
+
+    let pairs: Vec<_> = prepared.iter().map(|(a, b)| (a, b)).collect();
+    Bls12::final_exponentiation(&Bls12::miller_loop(pairs.iter())) == Some(Fq12::one())
+}
+
+/// Validates VSS shares received by party `index` one by one
+///
+/// Returns positions of shares that are inconsistent with their VSS commitments. Slower than
+/// batched validation performed at keygen, but identifies misbehaving dealers after
+/// [Keys::phase2_verify_vss_construct_keypair_prove_dlog] failed with
+/// [Error::KeyGenInvalidShare].
+pub fn find_invalid_shares(
+    secret_shares_vec: &[FE2],
+    vss_scheme_vec: &[VerifiableSS<GE2>],
+    index: usize,
+) -> Vec<usize> {
+    secret_shares_vec
+        .iter()
+        .zip(vss_scheme_vec)
+        .enumerate()
+        .filter(|(_, (share, vss))| vss.validate_share(share, index).is_err())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Checks that the first `t+1` partial signatures are made by signers `s[..=t]` in the same
+/// order, and that these signers are distinct
+///
+/// Signatures are interpolated at points `s[..=t]`, so repeated point would make Lagrange
+/// coefficients undefined, and misordered `s` would interpolate signatures at wrong points.
+fn partials_match_indexes(
+    partial_sigs_vec: &[PartialSignature],
+    s: &[KeygenIndex],
+    t: usize,
+) -> bool {
+    let s = &s[..=t];
+    partial_sigs_vec[..=t]
+        .iter()
+        .zip(s)
+        .all(|(sig, &index)| sig.index == index)
+        && s.iter()
+            .enumerate()
+            .all(|(k, &index)| index.0 != 0 && !s[..k].contains(&index))
+}
+
+/// Computes `sk_i * H_x` along with ECDDH proof of consistency with `sk_i * G2`
+fn prove_partial(sk_i: &FE2, H_x: &GE1) -> (GE1, ECDDHProof) {
+    let sk_bn = ECScalar::to_big_int(sk_i);
+    let sk_i_fe1: FE1 = ECScalar::from(&sk_bn);
+    let sigma_i = H_x * &sk_i_fe1;
+
+    let w = ECDDHWitness { x: sk_bn };
+
+    let delta = ECDDHStatement {
+        g1: H_x.clone(),
+        h1: sigma_i.clone(),
+        g2: GE2::generator(),
+        h2: GE2::generator() * sk_i,
+    };
+    let ddh_proof = ECDDHProof::prove(&w, &delta);
+    assert!(ddh_proof.verify(&delta));
+    (sigma_i, ddh_proof)
 }
 
 impl SharedKeys {
@@ -195,84 +1017,287 @@ impl SharedKeys {
         GE2::generator() * &self.sk_i
     }
 
-    pub fn partial_sign(&self, x: &[u8]) -> (PartialSignature, GE1) {
-        let H_x = GE1::hash_to_curve(x);
-        let sk_bn = ECScalar::to_big_int(&self.sk_i);
-        let sk_i_fe1: FE1 = ECScalar::from(&sk_bn);
-        let sigma_i = &H_x * &sk_i_fe1;
+    /// Keygen index of the party holding the share, i.e. `index+1`
+    ///
+    /// Panics if index doesn't fit into `u16`, which never happens for shares produced by
+    /// keygen.
+    pub fn keygen_index(&self) -> KeygenIndex {
+        KeygenIndex::from_position(self.index).expect("index doesn't fit into u16")
+    }
 
-        let w = ECDDHWitness { x: sk_bn };
+    /// Exports party's share as `(index, sk_i, params, vk)`
+    ///
+    /// Lets a share be moved to external custody (e.g. HSM) without serializing the whole key.
+    /// Returned `sk_i` is the secret share, it must be handled accordingly.
+    pub fn export_share(&self) -> (usize, FE2, ShamirSecretSharing, GE2) {
+        (self.index, self.sk_i, self.params.clone(), self.vk)
+    }
 
-        let delta = ECDDHStatement {
-            g1: H_x.clone(),
-            h1: sigma_i.clone(),
-            g2: GE2::generator(),
-            h2: self.get_shared_pubkey(),
-        };
-        let ddh_proof = ECDDHProof::prove(&w, &delta);
-        assert!(ddh_proof.verify(&delta));
+    /// Imports share previously obtained via [export_share](Self::export_share)
+    ///
+    /// `vk_vec` is a list of public keys of all parties holding a key (as received at keygen).
+    /// Share is checked to be consistent with its public key `vk_vec[index]`, and every key of
+    /// `vk_vec` is checked to agree with group public key `vk` (see [group_public_key]).
+    pub fn import_share(
+        index: usize,
+        sk_i: FE2,
+        params: ShamirSecretSharing,
+        vk: GE2,
+        vk_vec: &[GE2],
+    ) -> Result<Self, Error> {
+        if vk_vec.len() != params.share_count
+            || index >= params.share_count
+            || params.threshold >= params.share_count
+        {
+            return Err(Error::ImportedShareMismatch);
+        }
+        if GE2::generator() * &sk_i != vk_vec[index] {
+            return Err(Error::ImportedShareMismatch);
+        }
+        if group_public_key(vk_vec, &params) != Ok(vk) {
+            return Err(Error::ImportedShareMismatch);
+        }
+        Ok(SharedKeys {
+            index,
+            params,
+            vk,
+            sk_i,
+        })
+    }
 
-        (
-            PartialSignature {
-                index: self.index,
-                sigma_i,
-                ddh_proof,
-            },
-            H_x,
-        )
+    /// Size of shared keys serialized via [to_bytes_compact](Self::to_bytes_compact)
+    pub const COMPACT_BYTES_LEN: usize = 2 + 96 + secret_scalar::BYTES_LEN;
+
+    /// Serializes shared keys without `params` as `index` (2 bytes big-endian), compressed `vk`
+    /// (96 bytes), and `sk_i` (32 bytes big-endian)
+    ///
+    /// Threshold parameters are the same for all parties of the group, so they're meant to be
+    /// transmitted once and supplied to [from_bytes_compact](Self::from_bytes_compact). Output
+    /// contains the secret share, it must be handled accordingly.
+    ///
+    /// Panics if `index` doesn't fit into `u16`, which never happens for shares produced by
+    /// keygen.
+    pub fn to_bytes_compact(&self) -> Vec<u8> {
+        let index = u16::try_from(self.index).expect("index doesn't fit into u16");
+        let mut bytes = Vec::with_capacity(Self::COMPACT_BYTES_LEN);
+        bytes.extend_from_slice(&index.to_be_bytes());
+        G2Affine::serialize(&self.vk.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        bytes.extend_from_slice(&secret_scalar::to_bytes(&self.sk_i));
+        bytes
+    }
+
+    /// Parses shared keys serialized via [to_bytes_compact](Self::to_bytes_compact) and
+    /// reassembles them with threshold `params`
+    ///
+    /// Returns [Error::ImportedShareMismatch] if `params` are invalid or `index` is out of their
+    /// range.
+    pub fn from_bytes_compact(bytes: &[u8], params: &ShamirSecretSharing) -> Result<Self, Error> {
+        if bytes.len() != Self::COMPACT_BYTES_LEN {
+            return Err(ParseError::InvalidLength.into());
+        }
+        let index = usize::from(u16::from_be_bytes([bytes[0], bytes[1]]));
+        if params.threshold >= params.share_count || index >= params.share_count {
+            return Err(Error::ImportedShareMismatch);
+        }
+        let vk = g2_from_bytes(&bytes[2..98])?;
+        let sk_i = <&[u8; secret_scalar::BYTES_LEN]>::try_from(&bytes[98..])
+            .map_err(|_| ParseError::InvalidLength)?;
+        Ok(SharedKeys {
+            index,
+            params: params.clone(),
+            vk,
+            sk_i: secret_scalar::from_bytes(sk_i)?,
+        })
+    }
+
+    /// Returns party's share as a basic BLS key pair `(sk_i, sk_i·G)`
+    ///
+    /// __For diagnostics only.__ The key pair exposes the secret share. It lets a developer
+    /// check a single party's behaviour with [BLSSignature::sign]: signature produced with
+    /// this key pair equals `sigma_i` of the party's partial signature, and verifies under the
+    /// party's verification key.
+    pub fn as_basic_keypair(&self) -> KeyPairG2 {
+        KeyPairG2::from_secret_key(self.sk_i)
+    }
+
+    pub fn partial_sign(&self, x: &[u8]) -> (PartialSignature, GE1) {
+        PartialSignature::sign(self, x)
     }
 
+    /// Combines partial signatures into BLS signature under group public key
+    ///
+    /// Inputs are matched positionally: `vk_vec[i]` must be verification key of the party that
+    /// produced `partial_sigs_vec[i]`, and `s` lists [indexes](PartialSignature::index) of
+    /// partial signatures in the same order. If `s` is ordered differently than partial
+    /// signatures or has repeated indexes, fails with [Error::SigningMisMatchedVectors]. If
+    /// `vk_vec` is ordered differently, verification fails with
+    /// [Error::PartialSignatureVerificationError]. Use [combine_map](Self::combine_map) to
+    /// match partial signatures with keys by index.
+    ///
+    /// At least `threshold+1` partial signatures are required. Fewer fail with
+    /// [Error::SigningMisMatchedVectors].
     pub fn combine(
         &self,
         vk_vec: &[GE2],
         partial_sigs_vec: &[PartialSignature],
         H_x: GE1,
-        s: &[usize],
+        s: &[KeygenIndex],
     ) -> Result<BLSSignature, Error> {
-        if vk_vec.len() != partial_sigs_vec.len()
-            || vk_vec.len() < self.params.threshold
-            || s.len() < self.params.threshold
-            || s.len() > self.params.share_count
-        {
-            return Err(Error::SigningMisMatchedVectors);
-        }
-        //verify ec_ddh proofs and signatures
+        self.combine_verbose(vk_vec, partial_sigs_vec, H_x, s)
+            .map_err(Error::from)
+    }
 
-        let partial_sigs_verify = (0..vk_vec.len())
-            .map(|i| {
-                let delta = ECDDHStatement {
-                    g1: H_x.clone(),
-                    h1: partial_sigs_vec[i].sigma_i.clone(),
-                    g2: GE2::generator(),
-                    h2: vk_vec[i],
-                };
+    /// Same as [combine](Self::combine), but on failed verification reports which partial
+    /// signatures were invalid
+    ///
+    /// Every partial signature is verified (verification doesn't stop at the first invalid one),
+    /// so a caller can exclude all misbehaving signers at once and retry.
+    pub fn combine_verbose(
+        &self,
+        vk_vec: &[GE2],
+        partial_sigs_vec: &[PartialSignature],
+        H_x: GE1,
+        s: &[KeygenIndex],
+    ) -> Result<BLSSignature, CombineError> {
+        let zero: FE1 = ECScalar::zero();
+        self.interpolate_at(&zero, vk_vec, partial_sigs_vec, H_x, s)
+    }
 
-                partial_sigs_vec[i].ddh_proof.verify(&delta)
-            })
-            .all(|x| x);
-        if partial_sigs_verify == false {
-            return Err(Error::PartialSignatureVerificationError);
+    /// Same as [combine_verbose](Self::combine_verbose), but matches partial signatures with
+    /// verification keys by index instead of position
+    ///
+    /// Every item of `partials` is `(signer, partial_sig)`, where verification key of `signer`
+    /// is `vk_map[signer]`. Lagrange coefficients are computed at `signer` indexes, so partial
+    /// signature must be labeled with the index of its signer: a partial signature checked
+    /// against one verification key but interpolated at another point would combine into an
+    /// invalid signature. Returns [MisMatchedVectors](CombineError::MisMatchedVectors) if
+    /// `vk_map` misses any signer, and [IndexMismatch](CombineError::IndexMismatch) if
+    /// partial signature claims index other than its signer's.
+    pub fn combine_map(
+        &self,
+        partials: &[(KeygenIndex, PartialSignature)],
+        vk_map: &HashMap<KeygenIndex, GE2>,
+        H_x: GE1,
+    ) -> Result<BLSSignature, CombineError> {
+        if let Some((signer, _)) = partials.iter().find(|(signer, sig)| sig.index != *signer) {
+            return Err(CombineError::IndexMismatch { signer: *signer });
         }
+        let vk_vec = partials
+            .iter()
+            .map(|(signer, _)| vk_map.get(signer).copied())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(CombineError::MisMatchedVectors)?;
+        let partial_sigs_vec: Vec<_> = partials.iter().map(|(_, sig)| sig.clone()).collect();
+        let s: Vec<_> = partials.iter().map(|(signer, _)| *signer).collect();
+        self.combine_verbose(&vk_vec, &partial_sigs_vec, H_x, &s)
+    }
 
+    /// Same as [combine](Self::combine), but interpolates signature at `point` instead of `x=0`
+    ///
+    /// Only needed for interop with key sets whose secret is the sharing polynomial evaluated
+    /// at a non-zero point. Shares are still assumed to be evaluated at keygen indexes of
+    /// parties. Signature obtained at any `point` other than zero doesn't verify under group
+    /// public key `vk`.
+    pub fn combine_at(
+        &self,
+        point: &FE1,
+        vk_vec: &[GE2],
+        partial_sigs_vec: &[PartialSignature],
+        H_x: GE1,
+        s: &[KeygenIndex],
+    ) -> Result<BLSSignature, Error> {
+        self.interpolate_at(point, vk_vec, partial_sigs_vec, H_x, s)
+            .map_err(Error::from)
+    }
+
+    /// Verifies partial signatures and interpolates them at `point`
+    fn interpolate_at(
+        &self,
+        point: &FE1,
+        vk_vec: &[GE2],
+        partial_sigs_vec: &[PartialSignature],
+        H_x: GE1,
+        s: &[KeygenIndex],
+    ) -> Result<BLSSignature, CombineError> {
+        self.verify_partials(vk_vec, partial_sigs_vec, &H_x, s)?;
+
+        let s = &s[0..self.params.threshold + 1];
         let (head, tail) = partial_sigs_vec.split_at(1);
         let sigma = tail[0..self.params.threshold].iter().fold(
-            &head[0].sigma_i
-                * &VerifiableSS::<GE1>::map_share_to_new_params(
-                    &self.params,
-                    head[0].index,
-                    &s[0..self.params.threshold + 1],
-                ),
-            |acc, x| {
-                acc + &x.sigma_i
-                    * &VerifiableSS::<GE1>::map_share_to_new_params(
-                        &self.params,
-                        x.index,
-                        &s[0..self.params.threshold + 1],
-                    )
-            },
+            &head[0].sigma_i * &lagrange_coefficient_at(point, head[0].index, s),
+            |acc, x| acc + &x.sigma_i * &lagrange_coefficient_at(point, x.index, s),
         );
+        Ok(BLSSignature { sigma })
+    }
+
+    /// Checks lengths of input vectors of [combine_verbose](Self::combine_verbose), and
+    /// verifies ec_ddh proofs and signatures
+    fn verify_partials(
+        &self,
+        vk_vec: &[GE2],
+        partial_sigs_vec: &[PartialSignature],
+        H_x: &GE1,
+        s: &[KeygenIndex],
+    ) -> Result<(), CombineError> {
+        if vk_vec.len() != partial_sigs_vec.len()
+            || vk_vec.len() < self.params.threshold + 1
+            || s.len() < self.params.threshold + 1
+            || s.len() > self.params.share_count
+            || !partials_match_indexes(partial_sigs_vec, s, self.params.threshold)
+        {
+            return Err(CombineError::MisMatchedVectors);
+        }
+
+        let invalid_partials: Vec<KeygenIndex> = partial_sigs_vec
+            .iter()
+            .zip(vk_vec)
+            .filter(|(partial_sig, vk)| !partial_sig.verify(H_x, vk))
+            .map(|(partial_sig, _)| partial_sig.index)
+            .collect();
+        if !invalid_partials.is_empty() {
+            return Err(CombineError::InvalidPartials(invalid_partials));
+        }
+        Ok(())
+    }
 
-        return Ok(BLSSignature { sigma });
+    /// Verifies partial signature over raw `message`
+    ///
+    /// Same as [PartialSignature::verify], but hashes the message to G1 via [hash_to_g1], so
+    /// validators that only have the raw message don't need to reimplement hashing.
+    pub fn verify_partial_sig_from_message(
+        message: &[u8],
+        partial: &PartialSignature,
+        vk_i: &GE2,
+    ) -> bool {
+        partial.verify(&hash_to_g1(message), vk_i)
+    }
+
+    /// Verifies partial signatures made over distinct messages at once
+    ///
+    /// Every item is `(H_x, partial_sig, vk_i)`: message, partial signature over it, and public
+    /// key of the signer. Checks a random linear combination of equations
+    /// `e(H_x_i, vk_i) = e(sigma_i, g2)`, which takes a single final exponentiation instead of
+    /// verifying DDH proof of every partial signature. If the batch doesn't pass, partial
+    /// signatures are verified one by one, and positions in `items` of every invalid one are
+    /// returned.
+    pub fn verify_partial_sigs_batch(
+        items: &[(GE1, PartialSignature, GE2)],
+    ) -> Result<(), Vec<usize>> {
+        if partial_sigs_pass_batch(items) {
+            return Ok(());
+        }
+        let invalid: Vec<usize> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, (H_x, partial_sig, vk_i))| !partial_sig.verify(H_x, vk_i))
+            .map(|(i, _)| i)
+            .collect();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
     }
 
     // check e(H(m), vk) == e(sigma, g2)
@@ -280,3 +1305,25 @@ impl SharedKeys {
         sig.verify(x, &self.vk)
     }
 }
+
+/// Computes Lagrange coefficient of share `index` for interpolating at `point`
+///
+/// Shares of parties `s` are evaluations at their keygen indexes, same as in
+/// [map_share_to_new_params](VerifiableSS::map_share_to_new_params) which is the special case
+/// of `point = 0`.
+pub(crate) fn lagrange_coefficient_at(point: &FE1, index: KeygenIndex, s: &[KeygenIndex]) -> FE1 {
+    let q = FE1::q();
+    let x = point.to_big_int();
+    let x_i = BigInt::from(u32::from(index.0));
+    let (num, denom) = s
+        .iter()
+        .filter(|&&j| j != index)
+        .map(|&j| BigInt::from(u32::from(j.0)))
+        .fold((BigInt::one(), BigInt::one()), |(num, denom), x_j| {
+            (
+                BigInt::mod_mul(&num, &BigInt::mod_sub(&x, &x_j, &q), &q),
+                BigInt::mod_mul(&denom, &BigInt::mod_sub(&x_i, &x_j, &q), &q),
+            )
+        });
+    ECScalar::from(&BigInt::mod_mul(&num, &BigInt::mod_inv(&denom, &q), &q))
+}
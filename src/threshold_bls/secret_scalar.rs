@@ -0,0 +1,178 @@
+//! Constant-time encoding of secret scalars
+//!
+//! Default serialization of curv scalars goes through [BigInt](curv::BigInt) whose conversions
+//! branch on the value (e.g. leading zeroes are stripped), so the time it takes to serialize a
+//! secret share may leak information about it. This module encodes scalars as fixed-size
+//! 32 bytes big-endian directly from field element limbs, without any secret-dependent branches
+//! or table lookups. Only validity of the input (whether it's a canonical field element) may
+//! affect timing of decoding.
+//!
+//! Serde functions encode the scalar as 64 hex digits, which is accepted by curv scalar
+//! deserializer, and accept any hex string curv serializer produces, so keys serialized by
+//! previous versions of the library remain readable. Use it as
+//! `#[serde(with = "bls::threshold_bls::secret_scalar")]`.
+//!
+//! [SharedKeys](super::party_i::SharedKeys) (and, therefore,
+//! [LocalKey](super::state_machine::keygen::LocalKey)) serializes its secret share this way.
+
+use curv::elliptic::curves::bls12_381::g2::FE as FE2;
+use curv::elliptic::curves::traits::ECScalar;
+use ff_zeroize::{PrimeField, PrimeFieldRepr};
+use pairing_plus::bls12_381::{Fr, FrRepr};
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+use zeroize::Zeroize;
+
+use crate::ParseError;
+
+/// Size of encoded scalar
+pub const BYTES_LEN: usize = 32;
+
+/// Encodes scalar as 32 bytes big-endian
+pub fn to_bytes(scalar: &FE2) -> [u8; BYTES_LEN] {
+    let mut repr = scalar.get_element().into_repr();
+    let mut bytes = [0u8; BYTES_LEN];
+    repr.write_be(&mut bytes[..])
+        .expect("FrRepr is exactly 32 bytes long");
+    repr.as_mut().zeroize();
+    bytes
+}
+
+/// Decodes scalar encoded via [to_bytes]
+///
+/// Returns [ParseError::InvalidEncoding] if bytes represent a number that isn't less than the
+/// group order.
+pub fn from_bytes(bytes: &[u8; BYTES_LEN]) -> Result<FE2, ParseError> {
+    let mut repr = FrRepr::default();
+    repr.read_be(&bytes[..])
+        .expect("FrRepr is exactly 32 bytes long");
+    let element = Fr::from_repr(repr).map_err(|_| ParseError::InvalidEncoding);
+    repr.as_mut().zeroize();
+    let mut scalar: FE2 = ECScalar::zero();
+    scalar.set_element(element?);
+    Ok(scalar)
+}
+
+pub fn serialize<S: Serializer>(scalar: &FE2, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut bytes = to_bytes(scalar);
+    let mut hex = [0u8; 2 * BYTES_LEN];
+    for (byte, digits) in bytes.iter().zip(hex.chunks_mut(2)) {
+        digits[0] = hex_digit(byte >> 4);
+        digits[1] = hex_digit(byte & 0x0f);
+    }
+    bytes.zeroize();
+    let result = serializer
+        .serialize_str(std::str::from_utf8(&hex).expect("hex digits are valid utf8 string"));
+    hex.zeroize();
+    result
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FE2, D::Error> {
+    struct HexScalar;
+    impl<'de> Visitor<'de> for HexScalar {
+        type Value = FE2;
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "scalar encoded as at most {} hex digits", 2 * BYTES_LEN)
+        }
+        fn visit_str<E: de::Error>(self, hex: &str) -> Result<FE2, E> {
+            let hex = hex.as_bytes();
+            if hex.is_empty() || hex.len() > 2 * BYTES_LEN {
+                return Err(E::invalid_length(hex.len(), &self));
+            }
+            // Curv strips leading zeroes, so shorter (and odd-length) strings are padded
+            let mut bytes = [0u8; BYTES_LEN];
+            let mut invalid = 0u8;
+            for (k, &digit) in hex.iter().rev().enumerate() {
+                let (value, digit_invalid) = hex_value(digit);
+                bytes[BYTES_LEN - 1 - k / 2] |= value << (4 * (k % 2));
+                invalid |= digit_invalid;
+            }
+            let scalar = if invalid == 0 {
+                from_bytes(&bytes).map_err(|_| E::custom("scalar is not less than group order"))
+            } else {
+                Err(E::custom("invalid hex digit"))
+            };
+            bytes.zeroize();
+            scalar
+        }
+    }
+    deserializer.deserialize_str(HexScalar)
+}
+
+/// Maps `n` in range `[0; 16)` to lower-case hex digit without branches
+fn hex_digit(n: u8) -> u8 {
+    let n = i16::from(n);
+    // (9 - n) >> 8 is -1 if n > 9, and 0 otherwise
+    (n + 0x30 + (((9 - n) >> 8) & 0x27)) as u8
+}
+
+/// Parses hex digit (either case) without branches
+///
+/// Returns digit value, and `0xff` as second element if `digit` isn't a hex digit (`0`
+/// otherwise).
+fn hex_value(digit: u8) -> (u8, u8) {
+    let c = i16::from(digit);
+    let num = c ^ 0x30;
+    // -1 if `c` is in range '0'..='9', 0 otherwise
+    let is_num = (num - 10) >> 8;
+    let alpha = (c & !0x20) - 55;
+    // -1 if `c` is in range 'A'..='F' or 'a'..='f', 0 otherwise
+    let is_alpha = ((alpha - 10) ^ (alpha - 16)) >> 8;
+    let value = (is_num & num) | (is_alpha & alpha);
+    (value as u8 & 0x0f, !(is_num | is_alpha) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] FE2);
+
+    #[test]
+    fn bytes_roundtrip() {
+        let scalar: FE2 = ECScalar::new_random();
+        let bytes = to_bytes(&scalar);
+        assert_eq!(
+            curv::BigInt::from(&bytes[..]),
+            ECScalar::to_big_int(&scalar)
+        );
+        assert_eq!(from_bytes(&bytes), Ok(scalar));
+
+        assert_eq!(
+            from_bytes(&[0xff; BYTES_LEN]),
+            Err(ParseError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn serialization_is_compatible_with_curv() {
+        let small: FE2 = ECScalar::from(&curv::BigInt::from(0xabcu32));
+        for scalar in [ECScalar::new_random(), small].iter() {
+            let json = serde_json::to_string(&Wrapper(*scalar)).unwrap();
+            assert_eq!(json.len(), 2 * BYTES_LEN + 2);
+            // New encoding is understood by curv
+            assert_eq!(serde_json::from_str::<FE2>(&json).unwrap(), *scalar);
+            // Curv encoding is understood by us
+            let legacy = serde_json::to_string(scalar).unwrap();
+            assert_eq!(serde_json::from_str::<Wrapper>(&legacy).unwrap().0, *scalar);
+        }
+    }
+
+    #[test]
+    fn hex_digits_are_parsed() {
+        for n in 0..16u8 {
+            let digit = hex_digit(n);
+            assert_eq!(digit, b"0123456789abcdef"[usize::from(n)]);
+            assert_eq!(hex_value(digit), (n, 0));
+            assert_eq!(hex_value(digit.to_ascii_uppercase()), (n, 0));
+        }
+        for &c in b"gG/:@`-x \0".iter() {
+            assert_eq!(hex_value(c).1, 0xff);
+        }
+        assert!(serde_json::from_str::<Wrapper>("\"12g4\"").is_err());
+        assert!(serde_json::from_str::<Wrapper>("\"\"").is_err());
+    }
+}
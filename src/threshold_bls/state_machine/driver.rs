@@ -0,0 +1,164 @@
+//! Message-by-message protocol execution
+//!
+//! [ProtocolDriver] is an alternative to [AsyncProtocol](round_based::AsyncProtocol) for those
+//! who want to await every incoming message individually rather than hand over transport
+//! stream and sink to `run()`, e.g. to schedule, retry or log in between messages.
+
+use round_based::{IsCritical, Msg, StateMachine};
+use thiserror::Error;
+
+/// Drives a [StateMachine] one incoming message at a time
+///
+/// Call [start](Self::start) once to obtain messages of the first round, then feed every
+/// received message into [step](Self::step) and send out messages it returns, until
+/// [is_finished](Self::is_finished). Finally, take protocol output via
+/// [finish](Self::finish).
+///
+/// Proceeding some rounds is expensive, so, like [AsyncProtocol](round_based::AsyncProtocol),
+/// driver does it via `tokio::task::spawn_blocking` to not block the executor. Methods must be
+/// called within tokio runtime. If future returned by [start](Self::start) or
+/// [step](Self::step) is dropped before completion, state machine is lost and driver must not
+/// be used anymore.
+#[derive(Debug)]
+pub struct ProtocolDriver<SM> {
+    /// `None` while state machine is proceeded on blocking thread
+    sm: Option<SM>,
+}
+
+impl<SM> ProtocolDriver<SM>
+where
+    SM: StateMachine + Send + 'static,
+    SM::Err: IsCritical + Send,
+    SM::MessageBody: Send,
+{
+    pub fn new(sm: SM) -> Self {
+        Self { sm: Some(sm) }
+    }
+
+    /// Proceeds the protocol as far as possible without incoming messages and returns messages
+    /// to be sent
+    pub async fn start(&mut self) -> Result<Vec<Msg<SM::MessageBody>>, SM::Err> {
+        self.blocking(proceed).await
+    }
+
+    /// Handles incoming message and returns messages to be sent in response
+    ///
+    /// Messages sent by this party itself and P2P messages addressed to other parties are
+    /// ignored. Errors of handling the message are returned as is: after non-critical error
+    /// (see [IsCritical]) protocol can be continued, after critical one it must be aborted.
+    pub async fn step(
+        &mut self,
+        msg: Msg<SM::MessageBody>,
+    ) -> Result<Vec<Msg<SM::MessageBody>>, SM::Err> {
+        let i = self.state_machine().party_ind();
+        if msg.sender == i || msg.receiver.map_or(false, |receiver| receiver != i) {
+            return Ok(vec![]);
+        }
+        self.blocking(move |sm| {
+            sm.handle_incoming(msg)?;
+            proceed(sm)
+        })
+        .await
+    }
+
+    /// Whether protocol is completed and [finish](Self::finish) returns output
+    pub fn is_finished(&self) -> bool {
+        self.state_machine().is_finished()
+    }
+
+    /// Returns wrapped state machine, e.g. to inspect its state
+    pub fn state_machine(&self) -> &SM {
+        self.sm.as_ref().expect(STATE_MACHINE_LOST)
+    }
+
+    /// Takes protocol output
+    ///
+    /// Returns [FinishError::NotFinished] if protocol is not completed yet.
+    pub async fn finish(mut self) -> Result<SM::Output, FinishError<SM::Err>> {
+        let mut sm = self.sm.take().expect(STATE_MACHINE_LOST);
+        match sm.pick_output() {
+            Some(output) => output.map_err(FinishError::Protocol),
+            None => Err(FinishError::NotFinished),
+        }
+    }
+
+    async fn blocking<F>(&mut self, f: F) -> Result<Vec<Msg<SM::MessageBody>>, SM::Err>
+    where
+        F: FnOnce(&mut SM) -> Result<Vec<Msg<SM::MessageBody>>, SM::Err> + Send + 'static,
+    {
+        let mut sm = self.sm.take().expect(STATE_MACHINE_LOST);
+        let (sm, result) = match tokio::task::spawn_blocking(move || {
+            let result = f(&mut sm);
+            (sm, result)
+        })
+        .await
+        {
+            Ok(output) => output,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+        self.sm = Some(sm);
+        result
+    }
+}
+
+const STATE_MACHINE_LOST: &str = "state machine is lost: previous step was cancelled";
+
+fn proceed<SM: StateMachine>(sm: &mut SM) -> Result<Vec<Msg<SM::MessageBody>>, SM::Err> {
+    while sm.wants_to_proceed() && !sm.is_finished() {
+        sm.proceed()?;
+    }
+    Ok(sm.message_queue().drain(..).collect())
+}
+
+/// Error of [ProtocolDriver::finish]
+#[derive(Debug, Error)]
+pub enum FinishError<E> {
+    /// Protocol is not completed yet
+    #[error("protocol is not finished")]
+    NotFinished,
+    /// State machine failed to produce output
+    #[error("protocol failed: {0}")]
+    Protocol(#[source] E),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::threshold_bls::state_machine::keygen::Keygen;
+
+    #[tokio::test]
+    async fn drives_keygen_message_by_message() {
+        let (t, n) = (1, 3);
+        let mut parties: Vec<_> = (1..=n)
+            .map(|i| ProtocolDriver::new(Keygen::new(i, t, n).unwrap()))
+            .collect();
+
+        let mut in_flight = vec![];
+        for party in &mut parties {
+            in_flight.extend(party.start().await.unwrap());
+        }
+        while let Some(msg) = in_flight.pop() {
+            for party in &mut parties {
+                in_flight.extend(party.step(msg.clone()).await.unwrap());
+            }
+        }
+        assert!(parties.iter().all(|party| party.is_finished()));
+
+        let mut keys = vec![];
+        for party in parties {
+            keys.push(party.finish().await.unwrap());
+        }
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+    }
+
+    #[tokio::test]
+    async fn finish_before_completion_fails() {
+        let mut party = ProtocolDriver::new(Keygen::new(1, 1, 3).unwrap());
+        party.start().await.unwrap();
+        assert!(!party.is_finished());
+        assert!(matches!(
+            party.finish().await,
+            Err(FinishError::NotFinished)
+        ));
+    }
+}
@@ -2,41 +2,53 @@
 
 use std::fmt;
 use std::mem::replace;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
-use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
+use curv::cryptographic_primitives::secret_sharing::feldman_vss::{
+    ShamirSecretSharing, VerifiableSS,
+};
 use curv::elliptic::curves::bls12_381::g2::FE as FE2;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::ECPoint;
 use round_based::containers::{
     push::{Push, PushExt},
     *,
 };
 use round_based::{IsCritical, Msg, StateMachine};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
+use zeroize::Zeroize;
 
 use crate::threshold_bls::party_i;
+use crate::threshold_bls::state_machine::{
+    check_msg_kind, deserialize_versioned, Event, KeygenIndex, OutgoingMsg, UnexpectedMessageKind,
+};
 
 mod rounds;
-pub use rounds::{LocalKey, ProceedError};
+pub use rounds::{GroupPublicParams, LocalKey, ProceedError};
 use rounds::{Round0, Round1, Round2, Round3, Round4};
 
 /// Keygen protocol state machine
 ///
 /// Successfully completed keygen protocol produces [LocalKey] that can be used in further
 /// [signing](super::sign::Sign) protocol.
+///
+/// Both `Keygen` and [LocalKey] are `Send + Sync`, so state machine can be moved into a spawned
+/// task or shared between threads.
 pub struct Keygen {
     round: R,
 
-    msgs1: Option<Store<BroadcastMsgs<party_i::KeyGenComm>>>,
+    msgs1: Option<Store<BroadcastMsgs<(party_i::KeyGenComm, Option<GE2>)>>>,
     msgs2: Option<Store<BroadcastMsgs<party_i::KeyGenDecom>>>,
     msgs3: Option<Store<P2PMsgs<(VerifiableSS<GE2>, FE2)>>>,
     msgs4: Option<Store<BroadcastMsgs<DLogProof<GE2>>>>,
 
     msgs_queue: Vec<Msg<ProtocolMessage>>,
+    events: Option<Vec<Event<ProtocolMessage>>>,
 
     party_i: u16,
+    party_t: u16,
     party_n: u16,
 }
 
@@ -52,6 +64,32 @@ impl Keygen {
     /// * `t` is not in range `[1; n-1]`, returns [Error::InvalidThreshold]
     /// * `i` is not in range `[1; n]`, returns [Error::InvalidPartyIndex]
     pub fn new(i: u16, t: u16, n: u16) -> Result<Self> {
+        KeygenBuilder::new(i, t, n).build()
+    }
+
+    /// Constructs a party of keygen protocol that encrypts VSS shares to recipients
+    ///
+    /// Shortcut for `KeygenBuilder::new(i, t, n).encrypt_shares(true).build()`. Parties
+    /// broadcast ephemeral public keys along with round 1 commitments, and round 3 shares are
+    /// sealed to recipient's ephemeral key (see [encrypt_share](party_i::encrypt_share)), so
+    /// protocol doesn't rely on transport to keep P2P messages secret. Every party must be
+    /// constructed this way, otherwise keygen fails with
+    /// [ProceedError::Round1EncryptionMismatch].
+    ///
+    /// Takes the same arguments and returns the same errors as [Keygen::new].
+    pub fn with_encryption(i: u16, t: u16, n: u16) -> Result<Self> {
+        KeygenBuilder::new(i, t, n).encrypt_shares(true).build()
+    }
+
+    fn with_builder(builder: KeygenBuilder) -> Result<Self> {
+        let KeygenBuilder {
+            i,
+            t,
+            n,
+            security_bits,
+            skip_dlog_proofs,
+            encrypt_shares,
+        } = builder;
         if n < 2 {
             return Err(Error::TooFewParties);
         }
@@ -61,8 +99,18 @@ impl Keygen {
         if i == 0 || i > n {
             return Err(Error::InvalidPartyIndex);
         }
+        if security_bits < party_i::MIN_SECURITY {
+            return Err(Error::WeakSecurityParameter);
+        }
         let mut state = Self {
-            round: R::Round0(Round0 { party_i: i, t, n }),
+            round: R::Round0(Round0 {
+                party_i: i,
+                t,
+                n,
+                security_bits,
+                skip_dlog_proofs,
+                encrypt_shares,
+            }),
 
             msgs1: Some(Round1::expects_messages(i, n)),
             msgs2: Some(Round2::expects_messages(i, n)),
@@ -70,8 +118,10 @@ impl Keygen {
             msgs4: Some(Round4::expects_messages(i, n)),
 
             msgs_queue: vec![],
+            events: None,
 
             party_i: i,
+            party_t: t,
             party_n: n,
         };
 
@@ -79,16 +129,166 @@ impl Keygen {
         Ok(state)
     }
 
+    /// Enables recording of events: every incoming message and every proceed call
+    ///
+    /// Recorded [events](Self::events) can be [replayed](Self::replay) to deterministically
+    /// reproduce the messages sequence this party has seen, e.g. to debug a stuck ceremony.
+    /// Note that replayed party samples fresh randomness, so it doesn't reproduce own messages
+    /// and the resulting key. Messages of other parties that depend on its original shares don't
+    /// match it anymore: replay past round 3 fails to verify round 4 proofs, and replay of
+    /// keygen with encrypted shares fails to decrypt round 3 shares.
+    ///
+    /// Event log contains secret shares received from other parties at round 3, so it must be
+    /// kept as secret as [LocalKey] itself. [abort](Self::abort) zeroizes them.
+    pub fn record(&mut self) {
+        if self.events.is_none() {
+            self.events = Some(vec![])
+        }
+    }
+
+    /// Returns events recorded since [record](Self::record) was called
+    ///
+    /// Empty if recording is not enabled.
+    pub fn events(&self) -> &[Event<ProtocolMessage>] {
+        self.events.as_deref().unwrap_or(&[])
+    }
+
+    /// Re-drives freshly constructed state machine through recorded `events`
+    ///
+    /// State machine must be constructed with the same arguments as the one which recorded
+    /// events. Incoming messages are passed to [handle_incoming](StateMachine::handle_incoming)
+    /// and every recorded proceed results in [proceed](StateMachine::proceed) call. Like protocol
+    /// executor, replay skips non-critical errors and stops at the first critical one, which is
+    /// returned. Replayed state machine records events as well.
+    pub fn replay(mut self, events: &[Event<ProtocolMessage>]) -> Result<Self> {
+        self.record();
+        for event in events {
+            let result = match event {
+                Event::Incoming(msg) => self.handle_incoming(msg.clone()),
+                Event::Proceed { .. } => self.proceed(),
+            };
+            match result {
+                Err(err) if err.is_critical() => return Err(err),
+                _ => (),
+            }
+        }
+        Ok(self)
+    }
+
+    fn record_proceed(&mut self, from_round: u16) {
+        let to_round = self.current_round();
+        if let Some(events) = self.events.as_mut() {
+            events.push(Event::Proceed {
+                from_round,
+                to_round,
+            });
+        }
+    }
+
+    /// Takes all outgoing messages from the queue along with their delivery metadata
+    ///
+    /// Alternative to [message_queue](StateMachine::message_queue) for transports that want to
+    /// handle broadcast and P2P messages differently.
+    pub fn drain_messages(&mut self) -> Vec<OutgoingMsg<ProtocolMessage>> {
+        self.msgs_queue.drain(..).map(OutgoingMsg::from).collect()
+    }
+
+    /// Aborts the protocol wiping secrets of the current round
+    ///
+    /// Supposed to be called when keygen can't be completed (e.g. a cheater was detected), so
+    /// long-lived processes running many ceremonies don't leave secret scalars of abandoned
+    /// ones in freed memory. Zeroizes party's secret polynomial, shares it dealt (including
+    /// ones queued for sending), ephemeral encryption key, resulting secret share, and shares
+    /// kept in the [event log](Self::record).
+    ///
+    /// Shares received from other parties are wiped once they're combined at round 3. Shares
+    /// that are still buffered in message store can't be reached and are just dropped.
+    pub fn abort(mut self) {
+        self.wipe_secrets()
+    }
+
+    fn wipe_secrets(&mut self) {
+        match &mut self.round {
+            R::Round0(_) | R::Gone => (),
+            R::Round1(round) => round.zeroize(),
+            R::Round2(round) => round.zeroize(),
+            R::Round3(round) => round.zeroize(),
+            R::Round4(round) => round.zeroize(),
+            R::Final(local_key) => local_key.zeroize(),
+        }
+        for msg in &mut self.msgs_queue {
+            if let ProtocolMessage(_, M::Round3((_, share))) = &mut msg.body {
+                share.zeroize();
+            }
+        }
+        for event in self.events.iter_mut().flatten() {
+            if let Event::Incoming(Msg {
+                body: ProtocolMessage(_, M::Round3((_, share))),
+                ..
+            }) = event
+            {
+                share.zeroize();
+            }
+        }
+    }
+
+    /// Whether protocol is completed and output can be taken via
+    /// [pick_output](StateMachine::pick_output)
+    ///
+    /// Output can be taken only once: after that, `output_ready` returns `false` again.
+    pub fn output_ready(&self) -> bool {
+        matches!(self.round, R::Final(_))
+    }
+
+    /// Number of messages this party needs to receive in given `round` to proceed
+    ///
+    /// Returns `None` if party doesn't receive any messages in the round (or round doesn't
+    /// exist). In every round of keygen, party receives exactly one message from each other
+    /// party: rounds 1, 2 and 4 are broadcast rounds, and round 3 is P2P round.
+    ///
+    /// Lets transport collect exactly that many messages before calling
+    /// [proceed](StateMachine::proceed).
+    pub fn expected_messages(&self, round: u16) -> Option<usize> {
+        match round {
+            1..=4 => Some(usize::from(self.party_n) - 1),
+            _ => None,
+        }
+    }
+
     fn gmap_queue<'a, T, F>(&'a mut self, mut f: F) -> impl Push<Msg<T>> + 'a
     where
         F: FnMut(T) -> M + 'a,
     {
-        (&mut self.msgs_queue).gmap(move |m: Msg<T>| m.map_body(|m| ProtocolMessage(f(m))))
+        (&mut self.msgs_queue)
+            .gmap(move |m: Msg<T>| m.map_body(|m| ProtocolMessage(PROTOCOL_VERSION, f(m))))
+    }
+
+    /// Proceeds protocol spending at most `budget` of time on expensive computations
+    ///
+    /// Runs every round that can be proceeded: cheap rounds are always computed, while an
+    /// expensive round is started only if time spent so far is within the `budget`. Note that
+    /// an expensive round started within the budget is always completed, so the call may take
+    /// longer than `budget`. [proceed](StateMachine::proceed) is equivalent to unlimited budget.
+    ///
+    /// Lets cooperative schedulers bound CPU time spent per poll, e.g. on single-threaded
+    /// runtimes. Call it again later to continue proceeding.
+    pub fn proceed_within(&mut self, budget: Duration) -> Result<()> {
+        let started = Instant::now();
+        let from_round = self.current_round();
+        let result = self.proceed_round_while(&|| started.elapsed() < budget);
+        self.record_proceed(from_round);
+        result
     }
 
     /// Proceeds round state if it received enough messages and if it's cheap to compute or
     /// `may_block == true`
     fn proceed_round(&mut self, may_block: bool) -> Result<()> {
+        self.proceed_round_while(&|| may_block)
+    }
+
+    /// Proceeds round state if it received enough messages and if it's cheap to compute or
+    /// `may_block()` returns `true`
+    fn proceed_round_while(&mut self, may_block: &dyn Fn() -> bool) -> Result<()> {
         let store1_wants_more = self.msgs1.as_ref().map(|s| s.wants_more()).unwrap_or(false);
         let store2_wants_more = self.msgs2.as_ref().map(|s| s.wants_more()).unwrap_or(false);
         let store3_wants_more = self.msgs3.as_ref().map(|s| s.wants_more()).unwrap_or(false);
@@ -96,9 +296,15 @@ impl Keygen {
 
         let next_state: R;
         let try_again: bool = match replace(&mut self.round, R::Gone) {
-            R::Round0(round) if !round.is_expensive() || may_block => {
+            R::Round0(round) if !round.is_expensive() || may_block() => {
+                let params = Params {
+                    t: self.party_t,
+                    n: self.party_n,
+                };
                 next_state = round
-                    .proceed(self.gmap_queue(M::Round1))
+                    .proceed(self.gmap_queue(move |(comm, ephemeral_pk)| {
+                        M::Round1((comm, ephemeral_pk, params))
+                    }))
                     .map(R::Round1)
                     .map_err(Error::ProceedRound)?;
                 true
@@ -107,7 +313,7 @@ impl Keygen {
                 next_state = s;
                 false
             }
-            R::Round1(round) if !store1_wants_more && (!round.is_expensive() || may_block) => {
+            R::Round1(round) if !store1_wants_more && (!round.is_expensive() || may_block()) => {
                 let store = self.msgs1.take().ok_or(InternalError::StoreGone)?;
                 let msgs = store
                     .finish()
@@ -122,7 +328,7 @@ impl Keygen {
                 next_state = s;
                 false
             }
-            R::Round2(round) if !store2_wants_more && (!round.is_expensive() || may_block) => {
+            R::Round2(round) if !store2_wants_more && (!round.is_expensive() || may_block()) => {
                 let store = self.msgs2.take().ok_or(InternalError::StoreGone)?;
                 let msgs = store
                     .finish()
@@ -137,7 +343,7 @@ impl Keygen {
                 next_state = s;
                 false
             }
-            R::Round3(round) if !store3_wants_more && (!round.is_expensive() || may_block) => {
+            R::Round3(round) if !store3_wants_more && (!round.is_expensive() || may_block()) => {
                 let store = self.msgs3.take().ok_or(InternalError::StoreGone)?;
                 let msgs = store
                     .finish()
@@ -152,7 +358,7 @@ impl Keygen {
                 next_state = s;
                 false
             }
-            R::Round4(round) if !store4_wants_more && (!round.is_expensive() || may_block) => {
+            R::Round4(round) if !store4_wants_more && (!round.is_expensive() || may_block()) => {
                 let store = self.msgs4.take().ok_or(InternalError::StoreGone)?;
                 let msgs = store
                     .finish()
@@ -175,13 +381,124 @@ impl Keygen {
 
         self.round = next_state;
         if try_again {
-            self.proceed_round(may_block)
+            self.proceed_round_while(may_block)
         } else {
             Ok(())
         }
     }
 }
 
+/// Configures and constructs [Keygen]
+///
+/// [Keygen::new] is a shortcut for `KeygenBuilder::new(i, t, n).build()`
+#[derive(Debug, Clone)]
+pub struct KeygenBuilder {
+    i: u16,
+    t: u16,
+    n: u16,
+
+    security_bits: usize,
+    skip_dlog_proofs: bool,
+    encrypt_shares: bool,
+}
+
+impl KeygenBuilder {
+    /// Takes the same arguments as [Keygen::new]
+    pub fn new(i: u16, t: u16, n: u16) -> Self {
+        Self {
+            i,
+            t,
+            n,
+            security_bits: party_i::SECURITY,
+            skip_dlog_proofs: false,
+            encrypt_shares: false,
+        }
+    }
+
+    /// Sets bit length of blinding factor used in round 1 commitments
+    ///
+    /// Defaults to [SECURITY](party_i::SECURITY). Must be at least
+    /// [MIN_SECURITY](party_i::MIN_SECURITY), otherwise [build](Self::build) returns
+    /// [Error::WeakSecurityParameter].
+    pub fn security_bits(mut self, bits: usize) -> Self {
+        self.security_bits = bits;
+        self
+    }
+
+    /// Accepts round 4 without verifying parties' DLog proofs
+    ///
+    /// __Insecure, use only for testing.__ Without DLog proofs parties don't prove knowledge of
+    /// their shares, so a malicious party can corrupt resulting verification keys. This is only
+    /// meant to speed up simulations and local development where a single operator runs every
+    /// party. Defaults to `false`. Available only in tests and with `dev` feature.
+    #[cfg(any(test, feature = "dev"))]
+    pub fn skip_dlog_proofs(mut self, skip: bool) -> Self {
+        self.skip_dlog_proofs = skip;
+        self
+    }
+
+    /// Encrypts VSS shares sent in round 3 to recipients' ephemeral keys
+    ///
+    /// Parties exchange ephemeral public keys in round 1, so shares are never exposed to the
+    /// transport, even if it doesn't encrypt P2P messages. Every party must enable this option,
+    /// otherwise keygen fails with [ProceedError::Round1EncryptionMismatch]. Defaults to `false`.
+    pub fn encrypt_shares(mut self, encrypt: bool) -> Self {
+        self.encrypt_shares = encrypt;
+        self
+    }
+
+    /// Constructs a party of keygen protocol
+    ///
+    /// Returns the same errors as [Keygen::new], and additionally [Error::WeakSecurityParameter]
+    pub fn build(self) -> Result<Keygen> {
+        Keygen::with_builder(self)
+    }
+}
+
+/// Splits existing secret key into `t`-out-of-`n` threshold key by trusted dealer
+///
+/// Alternative to running [Keygen] for those who already have a key (e.g.
+/// [KeyPairG2](crate::basic_bls::KeyPairG2)) and trust the dealer: the dealer learns the secret
+/// and every share, so it must erase them once local keys are handed over to parties. Returns
+/// one local key per party, `i`-th key (counting from 1) belongs to party `i`. Public key of
+/// every local key equals `secret·G`.
+///
+/// `secret` is zeroized once it's shared. Validates `t` and `n` the same way as
+/// [Keygen::new], leaving `secret` intact if they're invalid.
+pub fn deal(secret: &mut FE2, t: u16, n: u16) -> Result<Vec<LocalKey>> {
+    if n < 2 {
+        return Err(Error::TooFewParties);
+    }
+    if t == 0 || t >= n {
+        return Err(Error::InvalidThreshold);
+    }
+    let params = ShamirSecretSharing {
+        threshold: usize::from(t),
+        share_count: usize::from(n),
+    };
+    let (_, shares) = VerifiableSS::<GE2>::share(params.threshold, params.share_count, secret);
+    let vk = GE2::generator() * &*secret;
+    secret.zeroize();
+    let vk_vec: Vec<GE2> = shares.iter().map(|s| GE2::generator() * s).collect();
+
+    Ok((1..=n)
+        .zip(shares)
+        .map(|(i, sk_i)| LocalKey {
+            shared_keys: party_i::SharedKeys {
+                index: usize::from(i) - 1,
+                params: params.clone(),
+                vk,
+                sk_i,
+            },
+            vk_vec: vk_vec.clone(),
+            points: vec![],
+            i,
+            t,
+            n,
+        })
+        .collect())
+}
+
 impl StateMachine for Keygen {
     type MessageBody = ProtocolMessage;
     type Err = Error;
@@ -189,9 +506,29 @@ impl StateMachine for Keygen {
 
     fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<()> {
         let current_round = self.current_round();
+        if let Some(events) = self.events.as_mut() {
+            events.push(Event::Incoming(msg.clone()));
+        }
+
+        if msg.body.0 != PROTOCOL_VERSION {
+            return Err(Error::UnsupportedProtocolVersion {
+                version: msg.body.0,
+            });
+        }
 
         match msg.body {
-            ProtocolMessage(M::Round1(m)) => {
+            ProtocolMessage(_, M::Round1((comm, ephemeral_pk, params))) => {
+                check_msg_kind(1, msg.receiver, true)?;
+                if params.t != self.party_t || params.n != self.party_n {
+                    return Err(Error::ParameterMismatch {
+                        party: msg.sender,
+                        their_t: params.t,
+                        their_n: params.n,
+                    });
+                }
+                if !comm.is_well_sized() {
+                    return Err(Error::OversizedCommitment { party: msg.sender });
+                }
                 let store = self
                     .msgs1
                     .as_mut()
@@ -203,12 +540,13 @@ impl StateMachine for Keygen {
                     .push_msg(Msg {
                         sender: msg.sender,
                         receiver: msg.receiver,
-                        body: m,
+                        body: (comm, ephemeral_pk),
                     })
                     .map_err(Error::HandleMessage)?;
                 self.proceed_round(false)
             }
-            ProtocolMessage(M::Round2(m)) => {
+            ProtocolMessage(_, M::Round2(m)) => {
+                check_msg_kind(2, msg.receiver, true)?;
                 let store = self
                     .msgs2
                     .as_mut()
@@ -225,7 +563,8 @@ impl StateMachine for Keygen {
                     .map_err(Error::HandleMessage)?;
                 self.proceed_round(false)
             }
-            ProtocolMessage(M::Round3(m)) => {
+            ProtocolMessage(_, M::Round3(m)) => {
+                check_msg_kind(3, msg.receiver, false)?;
                 let store = self
                     .msgs3
                     .as_mut()
@@ -242,7 +581,11 @@ impl StateMachine for Keygen {
                     .map_err(Error::HandleMessage)?;
                 self.proceed_round(false)
             }
-            ProtocolMessage(M::Round4(m)) => {
+            ProtocolMessage(version, M::Unsupported) => {
+                Err(Error::UnsupportedProtocolVersion { version })
+            }
+            ProtocolMessage(_, M::Round4(m)) => {
+                check_msg_kind(4, msg.receiver, true)?;
                 let store = self
                     .msgs4
                     .as_mut()
@@ -283,7 +626,10 @@ impl StateMachine for Keygen {
     }
 
     fn proceed(&mut self) -> Result<()> {
-        self.proceed_round(true)
+        let from_round = self.current_round();
+        let result = self.proceed_round(true);
+        self.record_proceed(from_round);
+        result
     }
 
     fn round_timeout(&self) -> Option<Duration> {
@@ -298,6 +644,13 @@ impl StateMachine for Keygen {
         matches!(self.round, R::Final(_))
     }
 
+    /// Takes protocol output
+    ///
+    /// One-shot: returns output once protocol is completed (see
+    /// [output_ready](Keygen::output_ready)), and error on any subsequent call. Returns `None`
+    /// without affecting protocol state if protocol is not completed yet.
+    ///
+    /// Output is moved out of the state machine, so it doesn't retain any secrets afterwards.
     fn pick_output(&mut self) -> Option<Result<Self::Output>> {
         match self.round {
             R::Final(_) => (),
@@ -392,15 +745,44 @@ enum R {
 /// Protocol message which parties send on wire
 ///
 /// Hides actual messages structure so it could be changed without breaking semver policy.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtocolMessage(M);
+/// Serialized message is tagged with protocol version, so parties running incompatible versions
+/// of the protocol reject each other's messages. Body of a message of another version isn't
+/// decoded, so such message is reported as [Error::UnsupportedProtocolVersion] rather than
+/// failing to deserialize.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolMessage(u8, M);
+
+impl<'de> Deserialize<'de> for ProtocolMessage {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (version, body) =
+            deserialize_versioned(deserializer, "ProtocolMessage", PROTOCOL_VERSION)?;
+        Ok(ProtocolMessage(version, body.unwrap_or(M::Unsupported)))
+    }
+}
+
+/// Version of protocol messages, must be bumped on every change of message structure
+const PROTOCOL_VERSION: u8 = 3;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 enum M {
-    Round1(party_i::KeyGenComm),
+    Round1((party_i::KeyGenComm, Option<GE2>, Params)),
     Round2(party_i::KeyGenDecom),
     Round3((VerifiableSS<GE2>, FE2)),
     Round4(DLogProof<GE2>),
+    /// Body of a message of unsupported protocol version, which is not decoded
+    #[serde(skip)]
+    Unsupported,
+}
+
+/// Keygen parameters party was constructed with, broadcasted in round 1 so parties can check
+/// they agree on them
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Params {
+    t: u16,
+    n: u16,
 }
 
 // Error
@@ -424,15 +806,60 @@ pub enum Error {
     /// Party index `i` is not in range `[1; n]`
     #[error("party index is not in range [1; n]")]
     InvalidPartyIndex,
+    /// Commitments security parameter is less than [MIN_SECURITY](party_i::MIN_SECURITY)
+    #[error("security parameter is too small")]
+    WeakSecurityParameter,
+    /// Mapping given to [LocalKey::relabel] is not a bijection over `[1; n]`
+    #[error("relabeling is not a bijection over [1; n]")]
+    InvalidRelabeling,
+    /// Keygen index given to [LocalKey::combine_partials] is not in range `[1; n]`, repeated, or
+    /// doesn't match index of the partial signature
+    #[error("keygen index {index} is not in range [1; n], repeated, or mislabeled")]
+    InvalidKeygenIndex { index: KeygenIndex },
+    /// [LocalKey::combine_partials] couldn't combine partial signatures
+    #[error("combine partial signatures: {0:?}")]
+    CombinePartials(party_i::CombineError),
+    /// Partial signatures given to [LocalKey::dry_run_combine] combined into a signature which
+    /// doesn't verify under group public key
+    ///
+    /// Partial signatures labelled with wrong index are blamed on their signers as
+    /// [InvalidKeygenIndex](Self::InvalidKeygenIndex) before combining, so this error means that
+    /// verification keys in the local key don't match its public key.
+    #[error("combined signature doesn't verify under group public key")]
+    CombinedSignatureInvalid,
 
     /// Received message didn't pass pre-validation
     #[error("received message didn't pass pre-validation: {0}")]
     HandleMessage(#[source] StoreErr),
-    /// Received message which we didn't expect to receive now (e.g. message from previous round)
+    /// Received message of a round that's already completed
+    ///
+    /// Messages of future rounds are not an error: they're kept until their round begins.
     #[error(
         "didn't expect to receive message from round {msg_round} (being at round {current_round})"
     )]
     ReceivedOutOfOrderMessage { current_round: u16, msg_round: u16 },
+    /// Party `party` was constructed with threshold or number of parties different from ours
+    #[error("party {party} runs keygen with t={their_t} n={their_n}, which differs from ours")]
+    ParameterMismatch {
+        party: u16,
+        their_t: u16,
+        their_n: u16,
+    },
+    /// Party `party` sent round 1 commitment exceeding
+    /// [COMMITMENT_BYTES_LEN](party_i::COMMITMENT_BYTES_LEN) bytes
+    #[error("party {party} sent oversized commitment")]
+    OversizedCommitment { party: u16 },
+    /// Received message of protocol version which is not supported by this party
+    #[error("received message of unsupported protocol version {version}")]
+    UnsupportedProtocolVersion { version: u8 },
+    /// Received P2P message in a round that expects broadcast messages, or vice versa
+    #[error(
+        "received message of unexpected kind at round {round} (expected_broadcast={expected_broadcast})"
+    )]
+    UnexpectedMessageKind {
+        round: u16,
+        expected_broadcast: bool,
+    },
     /// [Keygen::pick_output] called twice
     #[error("pick_output called twice")]
     DoublePickOutput,
@@ -443,6 +870,15 @@ pub enum Error {
     InternalError(InternalError),
 }
 
+impl From<UnexpectedMessageKind> for Error {
+    fn from(err: UnexpectedMessageKind) -> Self {
+        Self::UnexpectedMessageKind {
+            round: err.round,
+            expected_broadcast: err.expected_broadcast,
+        }
+    }
+}
+
 impl IsCritical for Error {
     fn is_critical(&self) -> bool {
         true
@@ -483,6 +919,10 @@ mod test {
         }
 
         let keys = simulation.run().unwrap();
+        for (i, key) in (1..).zip(&keys) {
+            assert_eq!(key.index(), KeygenIndex(i));
+            assert_eq!((key.t(), key.n()), (t, n));
+        }
 
         println!("Benchmark results:");
         println!("{:#?}", simulation.benchmark_results().unwrap());
@@ -504,4 +944,741 @@ mod test {
     fn simulate_keygen_t2_n3() {
         simulate_keygen(2, 3);
     }
+
+    #[test]
+    fn simulate_keygen_with_min_security() {
+        let mut simulation = Simulation::new();
+        for i in 1..=3 {
+            simulation.add_party(
+                KeygenBuilder::new(i, 1, 3)
+                    .security_bits(party_i::MIN_SECURITY)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        simulation.run().unwrap();
+    }
+
+    #[test]
+    fn simulate_keygen_skipping_dlog_proofs() {
+        let mut simulation = Simulation::new();
+        for i in 1..=3 {
+            simulation.add_party(
+                KeygenBuilder::new(i, 1, 3)
+                    .skip_dlog_proofs(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        let keys = simulation.run().unwrap();
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+    }
+
+    #[test]
+    fn local_key_debug_is_redacted() {
+        let keys = simulate_keygen(1, 3);
+        let fingerprint = keys[0].fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+        assert!(keys.iter().all(|k| k.fingerprint() == fingerprint));
+        assert_eq!(
+            format!("{:?}", keys[1]),
+            format!("LocalKey {{ i: 2, t: 1, n: 3, vk: {} }}", fingerprint)
+        );
+    }
+
+    #[test]
+    fn group_public_params_roundtrip() {
+        let keys = simulate_keygen(1, 3);
+        let params = keys[0].public_params();
+        assert_eq!(params, keys[2].public_params());
+        assert_eq!(params.vk, keys[0].public_key());
+
+        let bytes = params.to_bytes();
+        assert_eq!(GroupPublicParams::from_bytes(&bytes), Ok(params.clone()));
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(
+            serde_json::from_str::<GroupPublicParams>(&json).unwrap(),
+            params
+        );
+
+        // Verification keys don't match group public key
+        let mut tampered = params.clone();
+        tampered.vk_vec.swap(0, 1);
+        assert_eq!(
+            GroupPublicParams::from_bytes(&tampered.to_bytes()),
+            Err(crate::ParseError::InvalidEncoding)
+        );
+
+        // Verification key beyond the first t+1 doesn't match the others
+        let mut tampered = params.clone();
+        tampered.vk_vec[2] = tampered.vk_vec[0];
+        assert_eq!(
+            GroupPublicParams::from_bytes(&tampered.to_bytes()),
+            Err(crate::ParseError::InvalidEncoding)
+        );
+        let json = serde_json::to_string(&tampered).unwrap();
+        assert!(serde_json::from_str::<GroupPublicParams>(&json).is_err());
+
+        assert_eq!(
+            GroupPublicParams::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(crate::ParseError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn simulate_keygen_with_encrypted_shares() {
+        let mut simulation = Simulation::new();
+        for i in 1..=3 {
+            simulation.add_party(
+                KeygenBuilder::new(i, 1, 3)
+                    .encrypt_shares(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+        let keys = simulation.run().unwrap();
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+    }
+
+    /// Runs honest parties to completion, returns their outputs and every message sent on wire
+    fn run_observed(mut parties: Vec<Keygen>) -> (Vec<LocalKey>, Vec<Msg<ProtocolMessage>>) {
+        let mut transcript = vec![];
+        while !parties.iter().all(|p| p.is_finished()) {
+            for i in 0..parties.len() {
+                if parties[i].wants_to_proceed() {
+                    parties[i].proceed().unwrap();
+                }
+                let msgs: Vec<_> = parties[i].message_queue().drain(..).collect();
+                for msg in msgs {
+                    for (j, party) in parties.iter_mut().enumerate() {
+                        let receiver = j as u16 + 1;
+                        if receiver != msg.sender && msg.receiver.map_or(true, |r| r == receiver) {
+                            party.handle_incoming(msg.clone()).unwrap();
+                        }
+                    }
+                    transcript.push(msg);
+                }
+            }
+        }
+        let keys = parties
+            .iter_mut()
+            .map(|p| p.pick_output().unwrap().unwrap())
+            .collect();
+        (keys, transcript)
+    }
+
+    /// Counts round 3 shares which an observer can check against sender's VSS commitments
+    fn shares_exposed_to_observer(transcript: &[Msg<ProtocolMessage>]) -> usize {
+        transcript
+            .iter()
+            .filter_map(|msg| match (&msg.body, msg.receiver) {
+                (ProtocolMessage(_, M::Round3((vss, share))), Some(receiver)) => {
+                    Some(vss.validate_share(share, usize::from(receiver)).is_ok())
+                }
+                _ => None,
+            })
+            .filter(|&exposed| exposed)
+            .count()
+    }
+
+    #[test]
+    fn passive_observer_cannot_recover_encrypted_shares() {
+        let parties = (1..=3)
+            .map(|i| Keygen::with_encryption(i, 1, 3).unwrap())
+            .collect();
+        let (keys, transcript) = run_observed(parties);
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+        assert_eq!(shares_exposed_to_observer(&transcript), 0);
+
+        // Without encryption, every share is exposed
+        let parties = (1..=3).map(|i| Keygen::new(i, 1, 3).unwrap()).collect();
+        let (_, transcript) = run_observed(parties);
+        assert_eq!(shares_exposed_to_observer(&transcript), 6);
+    }
+
+    #[test]
+    fn keygen_fails_if_party_doesnt_encrypt_shares() {
+        let mut simulation = Simulation::new();
+        simulation.add_party(
+            KeygenBuilder::new(1, 1, 2)
+                .encrypt_shares(true)
+                .build()
+                .unwrap(),
+        );
+        simulation.add_party(Keygen::new(2, 1, 2).unwrap());
+        assert!(matches!(
+            simulation.run(),
+            Err(Error::ProceedRound(
+                ProceedError::Round1EncryptionMismatch { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn drain_messages_attaches_delivery_metadata() {
+        let mut party = Keygen::new(1, 1, 2).unwrap();
+        party.proceed().unwrap();
+
+        let msgs = party.drain_messages();
+        assert_eq!(msgs.len(), 1);
+        assert!(msgs[0].is_broadcast);
+        assert_eq!(msgs[0].receiver, None);
+        assert!(party.message_queue().is_empty());
+    }
+
+    #[test]
+    fn keygen_rejects_message_of_unexpected_kind() {
+        let mut party = Keygen::new(1, 1, 2).unwrap();
+        let result = party.handle_incoming(Msg {
+            sender: 2,
+            receiver: Some(1),
+            body: ProtocolMessage(
+                PROTOCOL_VERSION,
+                M::Round1((
+                    party_i::KeyGenComm {
+                        com: curv::BigInt::from(1u32),
+                    },
+                    None,
+                    Params { t: 1, n: 2 },
+                )),
+            ),
+        });
+        assert!(matches!(
+            result,
+            Err(Error::UnexpectedMessageKind {
+                round: 1,
+                expected_broadcast: true
+            })
+        ));
+    }
+
+    #[test]
+    fn keygen_detects_parameters_mismatch() {
+        let mut simulation = Simulation::new();
+        simulation.add_party(Keygen::new(1, 1, 3).unwrap());
+        simulation.add_party(Keygen::new(2, 2, 3).unwrap());
+        simulation.add_party(Keygen::new(3, 2, 3).unwrap());
+        // Depending on delivery order, either party 1 or party 2 notices mismatch first
+        assert!(matches!(
+            simulation.run(),
+            Err(Error::ParameterMismatch { party, their_t, their_n })
+                if (party, their_t, their_n) == (1, 1, 3) || (party, their_t, their_n) == (2, 2, 3)
+        ));
+
+        let mut party1 = Keygen::new(1, 1, 3).unwrap();
+        let mut party2 = Keygen::new(2, 1, 2).unwrap();
+        party2.proceed().unwrap();
+        let msg = party2.message_queue().pop().unwrap();
+        assert!(matches!(
+            party1.handle_incoming(msg),
+            Err(Error::ParameterMismatch {
+                party: 2,
+                their_t: 1,
+                their_n: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn keygen_rejects_message_of_unsupported_version() {
+        let mut party1 = Keygen::new(1, 1, 2).unwrap();
+        let mut party2 = Keygen::new(2, 1, 2).unwrap();
+        party1.proceed().unwrap();
+
+        let mut msg = party1.message_queue().pop().unwrap();
+        msg.body.0 = PROTOCOL_VERSION + 1;
+        assert!(matches!(
+            party2.handle_incoming(msg),
+            Err(Error::UnsupportedProtocolVersion { version }) if version == PROTOCOL_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn message_of_unsupported_version_is_deserialized_without_body() {
+        let json = format!(
+            r#"{{"sender":1,"receiver":null,"body":[{},{{"Round9":[1,2,3]}}]}}"#,
+            PROTOCOL_VERSION + 1
+        );
+        let msg: Msg<ProtocolMessage> = serde_json::from_str(&json).unwrap();
+        assert!(matches!(msg.body, ProtocolMessage(_, M::Unsupported)));
+
+        let mut party = Keygen::new(2, 1, 2).unwrap();
+        assert!(matches!(
+            party.handle_incoming(msg),
+            Err(Error::UnsupportedProtocolVersion { version }) if version == PROTOCOL_VERSION + 1
+        ));
+
+        let json = format!(
+            r#"{{"sender":1,"receiver":null,"body":[{},{{"Round9":[1,2,3]}}]}}"#,
+            PROTOCOL_VERSION
+        );
+        assert!(serde_json::from_str::<Msg<ProtocolMessage>>(&json).is_err());
+    }
+
+    #[test]
+    fn keygen_rejects_weak_security() {
+        let result = KeygenBuilder::new(1, 1, 2)
+            .security_bits(party_i::MIN_SECURITY - 1)
+            .build();
+        assert!(matches!(result, Err(Error::WeakSecurityParameter)));
+    }
+
+    #[test]
+    fn shares_reach_intended_recipients() {
+        for n in 2..=5 {
+            let mut parties: Vec<_> = (1..=n).map(|i| Keygen::new(i, 1, n).unwrap()).collect();
+
+            // Rounds 1 and 2 are broadcast
+            for _round in 1..=2 {
+                let mut msgs = vec![];
+                for party in &mut parties {
+                    party.proceed().unwrap();
+                    msgs.append(party.message_queue());
+                }
+                for msg in msgs {
+                    for party in parties.iter_mut().filter(|p| p.party_ind() != msg.sender) {
+                        party.handle_incoming(msg.clone()).unwrap();
+                    }
+                }
+            }
+
+            // Round 3 sends shares P2P
+            for party in &mut parties {
+                party.proceed().unwrap();
+                let mut receivers = vec![];
+                for msg in party.message_queue().drain(..) {
+                    let receiver = msg.receiver.expect("shares are sent P2P");
+                    let (vss, share) = match msg.body {
+                        ProtocolMessage(_, M::Round3(m)) => m,
+                        body => panic!("expected round 3 message, got {:?}", body),
+                    };
+                    assert!(vss.validate_share(&share, usize::from(receiver)).is_ok());
+                    receivers.push(receiver);
+                }
+                let expected: Vec<_> = (1..=n).filter(|&j| j != party.party_ind()).collect();
+                assert_eq!(receivers, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn combine_partials_without_signing_protocol() {
+        let keys = simulate_keygen(1, 3);
+        let message = b"~~ MESSAGE ~~";
+
+        let partials: Vec<_> = [&keys[2], &keys[0]]
+            .iter()
+            .map(|key| (key.index(), key.partial_sign(message)))
+            .collect();
+        let sig = keys[1].combine_partials(message, &partials).unwrap();
+        assert!(sig.verify(message, &keys[0].public_key()));
+
+        assert!(matches!(
+            keys[1].combine_partials(message, &partials[..1]),
+            Err(Error::CombinePartials(
+                party_i::CombineError::MisMatchedVectors
+            ))
+        ));
+        let repeated = vec![partials[0].clone(), partials[0].clone()];
+        assert!(matches!(
+            keys[1].combine_partials(message, &repeated),
+            Err(Error::InvalidKeygenIndex {
+                index: KeygenIndex(3)
+            })
+        ));
+        let mislabeled = vec![partials[0].clone(), (KeygenIndex(2), partials[1].1.clone())];
+        assert!(matches!(
+            keys[1].combine_partials(message, &mislabeled),
+            Err(Error::InvalidKeygenIndex {
+                index: KeygenIndex(2)
+            })
+        ));
+        assert!(matches!(
+            keys[1].combine_partials(b"another message", &partials),
+            Err(Error::CombinePartials(
+                party_i::CombineError::InvalidPartials(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn dry_run_combine_blames_invalid_partials() {
+        let keys = simulate_keygen(1, 3);
+        let message = b"~~ MESSAGE ~~";
+
+        let mut partials: Vec<_> = [&keys[2], &keys[0]]
+            .iter()
+            .map(|key| (key.index(), key.partial_sign(message)))
+            .collect();
+        assert!(keys[1].dry_run_combine(message, &partials).is_ok());
+
+        // Party 1 contributes partial signature of another message
+        partials[1].1 = keys[0].partial_sign(b"another message");
+        match keys[1].dry_run_combine(message, &partials) {
+            Err(Error::CombinePartials(party_i::CombineError::InvalidPartials(blamed))) => {
+                assert_eq!(blamed, vec![partials[1].0])
+            }
+            result => panic!("expected invalid partials, got {:?}", result),
+        }
+
+        // Party 3 labels its partial signature with index of party 2
+        partials[1].1 = keys[0].partial_sign(message);
+        partials[0].0 = KeygenIndex(2);
+        match keys[1].dry_run_combine(message, &partials) {
+            Err(Error::InvalidKeygenIndex { index }) => assert_eq!(index, KeygenIndex(2)),
+            result => panic!("expected invalid keygen index, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn verification_keys_for_signers() {
+        let keys = simulate_keygen(1, 3);
+
+        let vks = keys[0]
+            .verification_keys_for(&[KeygenIndex(3), KeygenIndex(1)])
+            .unwrap();
+        assert_eq!(vks, vec![keys[0].vk_vec[2], keys[0].vk_vec[0]]);
+
+        for signers in &[
+            vec![KeygenIndex(0)],
+            vec![KeygenIndex(2), KeygenIndex(4)],
+            vec![KeygenIndex(2), KeygenIndex(1), KeygenIndex(2)],
+        ] {
+            assert!(matches!(
+                keys[0].verification_keys_for(signers),
+                Err(Error::InvalidKeygenIndex { index }) if Some(&index) == signers.last()
+            ));
+        }
+    }
+
+    #[test]
+    fn dealt_keys_sign_under_secret_public_key() {
+        use curv::elliptic::curves::traits::ECScalar;
+
+        use crate::threshold_bls::state_machine::sign::Sign;
+
+        let secret: FE2 = ECScalar::new_random();
+        let mut dealt = secret;
+        let keys = deal(&mut dealt, 1, 3).unwrap();
+        assert!(dealt == FE2::zero());
+        assert!(keys
+            .iter()
+            .all(|k| k.public_key() == GE2::generator() * &secret));
+
+        let message = b"~~ MESSAGE ~~";
+        let mut simulation = Simulation::new();
+        simulation.add_party(Sign::new(message.to_vec(), 1, 2, keys[2].clone()).unwrap());
+        simulation.add_party(Sign::new(message.to_vec(), 2, 2, keys[0].clone()).unwrap());
+        let sig = *simulation.run().unwrap()[0].signature();
+        assert!(sig.verify(message, &(GE2::generator() * &secret)));
+
+        let mut dealt = secret;
+        assert!(matches!(
+            deal(&mut dealt, 0, 3),
+            Err(Error::InvalidThreshold)
+        ));
+        assert!(matches!(deal(&mut dealt, 1, 1), Err(Error::TooFewParties)));
+        assert!(dealt == secret);
+    }
+
+    #[test]
+    fn pick_output_before_completion_doesnt_consume_state() {
+        let n = 3;
+        let mut parties: Vec<_> = (1..=n).map(|i| Keygen::new(i, 1, n).unwrap()).collect();
+        for _round in 1..=4 {
+            let mut msgs = vec![];
+            for party in &mut parties {
+                assert!(!party.output_ready());
+                assert!(party.pick_output().is_none());
+                party.proceed().unwrap();
+                msgs.append(party.message_queue());
+            }
+            for msg in msgs {
+                for party in parties.iter_mut().filter(|p| p.party_ind() != msg.sender) {
+                    if msg.receiver.is_none() || msg.receiver == Some(party.party_ind()) {
+                        party.handle_incoming(msg.clone()).unwrap();
+                    }
+                }
+            }
+        }
+        for party in &mut parties {
+            party.proceed().unwrap();
+            assert!(party.output_ready());
+            assert!(matches!(party.pick_output(), Some(Ok(_))));
+            assert!(!party.output_ready());
+            assert!(matches!(
+                party.pick_output(),
+                Some(Err(Error::DoublePickOutput))
+            ));
+        }
+    }
+
+    #[test]
+    fn expected_messages_matches_round_stores() {
+        let keygen = Keygen::new(2, 2, 4).unwrap();
+        assert_eq!(keygen.expected_messages(0), None);
+        assert_eq!(
+            keygen.expected_messages(1),
+            Some(keygen.msgs1.as_ref().unwrap().messages_total())
+        );
+        assert_eq!(
+            keygen.expected_messages(2),
+            Some(keygen.msgs2.as_ref().unwrap().messages_total())
+        );
+        assert_eq!(
+            keygen.expected_messages(3),
+            Some(keygen.msgs3.as_ref().unwrap().messages_total())
+        );
+        assert_eq!(
+            keygen.expected_messages(4),
+            Some(keygen.msgs4.as_ref().unwrap().messages_total())
+        );
+        assert_eq!(keygen.expected_messages(5), None);
+    }
+
+    #[test]
+    fn proceed_within_zero_budget_doesnt_start_expensive_rounds() {
+        let mut party = Keygen::new(1, 1, 2).unwrap();
+        party.proceed_within(Duration::from_secs(0)).unwrap();
+        assert_eq!(party.current_round(), 0);
+        assert!(party.message_queue().is_empty());
+
+        party.proceed_within(Duration::from_secs(60)).unwrap();
+        assert_eq!(party.current_round(), 1);
+        assert_eq!(party.message_queue().len(), 1);
+    }
+
+    /// Runs keygen delivering messages by hand, returns finished parties and every message
+    /// they sent
+    fn run_keygen_by_hand(t: u16, n: u16) -> (Vec<Keygen>, Vec<Msg<ProtocolMessage>>) {
+        let mut parties: Vec<_> = (1..=n).map(|i| Keygen::new(i, t, n).unwrap()).collect();
+        let mut recorded = vec![];
+        while !parties.iter().all(|party| party.is_finished()) {
+            for party in &mut parties {
+                if party.wants_to_proceed() {
+                    party.proceed().unwrap();
+                }
+            }
+            let sent: Vec<_> = parties
+                .iter_mut()
+                .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+                .collect();
+            for msg in sent {
+                for party in &mut parties {
+                    let i = party.party_ind();
+                    if i != msg.sender && msg.receiver.map(|j| j == i).unwrap_or(true) {
+                        party.handle_incoming(msg.clone()).unwrap();
+                    }
+                }
+                recorded.push(msg);
+            }
+        }
+        (parties, recorded)
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected_without_panic() {
+        use rand::Rng;
+
+        const MUTATIONS_PER_MESSAGE: usize = 50;
+        const REPLACEMENTS: &[u8] = b"0-9af\"[]{}, ";
+
+        let mut rng = rand::thread_rng();
+        let (_, recorded) = run_keygen_by_hand(1, 3);
+        for msg in recorded {
+            let valid = serde_json::to_vec(&msg.body).unwrap();
+            for _ in 0..MUTATIONS_PER_MESSAGE {
+                let mut mutated = valid.clone();
+                let pos = rng.gen_range(0, mutated.len());
+                mutated[pos] = REPLACEMENTS[rng.gen_range(0, REPLACEMENTS.len())];
+                if rng.gen() {
+                    mutated.truncate(rng.gen_range(pos, valid.len()));
+                }
+
+                // Deserialization must either fail or produce a message that can be safely handled
+                if let Ok(body) = serde_json::from_slice::<ProtocolMessage>(&mutated) {
+                    let mut party = Keygen::new(1, 1, 3).unwrap();
+                    let _ = party.handle_incoming(Msg {
+                        sender: if msg.sender == 1 { 2 } else { msg.sender },
+                        receiver: msg.receiver.map(|_| 1),
+                        body,
+                    });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_stuck_keygen() {
+        // Party 3 goes silent after round 1, so parties 1 and 2 get stuck at round 2
+        let mut parties: Vec<_> = (1..=3).map(|i| Keygen::new(i, 1, 3).unwrap()).collect();
+        parties[0].record();
+        loop {
+            for party in &mut parties {
+                if party.wants_to_proceed() {
+                    party.proceed().unwrap();
+                }
+            }
+            let sent: Vec<_> = parties
+                .iter_mut()
+                .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+                .filter(|msg| msg.sender != 3 || matches!(msg.body.1, M::Round1(_)))
+                .collect();
+            if sent.is_empty() {
+                break;
+            }
+            for msg in sent {
+                for party in &mut parties {
+                    let i = party.party_ind();
+                    if i != msg.sender && msg.receiver.map_or(true, |j| j == i) {
+                        party.handle_incoming(msg.clone()).unwrap();
+                    }
+                }
+            }
+        }
+        assert_eq!(parties[0].current_round(), 2);
+
+        let events = parties[0].events().to_vec();
+        let replayed = Keygen::new(1, 1, 3).unwrap().replay(&events).unwrap();
+        assert_eq!(replayed.current_round(), 2);
+        assert_eq!(format!("{:?}", replayed), format!("{:?}", parties[0]));
+        assert_eq!(replayed.events().len(), events.len());
+    }
+
+    #[test]
+    fn messages_of_future_rounds_are_buffered() {
+        let round_of = |msg: &Msg<ProtocolMessage>| match msg.body.1 {
+            M::Round1(_) => 1,
+            M::Round2(_) => 2,
+            M::Round3(_) => 3,
+            M::Round4(_) => 4,
+            M::Unsupported => unreachable!("messages are produced by parties of this version"),
+        };
+
+        // Messages from party 3 to party 1 are delayed until everyone else is stuck, so party 1
+        // receives messages of party 2 ahead of its current round
+        let mut parties: Vec<_> = (1..=3).map(|i| Keygen::new(i, 1, 3).unwrap()).collect();
+        let mut delayed = vec![];
+        let mut received_ahead = 0;
+        while !parties.iter().all(|party| party.is_finished()) {
+            for party in &mut parties {
+                if party.wants_to_proceed() {
+                    party.proceed().unwrap();
+                }
+            }
+            let sent: Vec<_> = parties
+                .iter_mut()
+                .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+                .collect();
+            if sent.is_empty() {
+                assert!(!delayed.is_empty(), "protocol got stuck");
+                for msg in delayed.drain(..) {
+                    parties[0].handle_incoming(msg).unwrap();
+                }
+                continue;
+            }
+            for msg in sent {
+                for party in &mut parties {
+                    let i = party.party_ind();
+                    if i == msg.sender || msg.receiver.map_or(false, |j| j != i) {
+                        continue;
+                    }
+                    if i == 1 && msg.sender == 3 {
+                        delayed.push(msg.clone());
+                        continue;
+                    }
+                    if party.current_round() < round_of(&msg) {
+                        received_ahead += 1;
+                    }
+                    party.handle_incoming(msg.clone()).unwrap();
+                }
+            }
+        }
+        assert!(received_ahead > 0);
+
+        let keys: Vec<_> = parties
+            .iter_mut()
+            .map(|party| party.pick_output().unwrap().unwrap())
+            .collect();
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+    }
+
+    #[test]
+    fn oversized_commitment_is_rejected() {
+        use curv::BigInt;
+
+        let mut sender = Keygen::new(2, 1, 3).unwrap();
+        sender.proceed().unwrap();
+        let mut msg = sender.message_queue().pop().unwrap();
+        if let ProtocolMessage(_, M::Round1((comm, _, _))) = &mut msg.body {
+            comm.com = BigInt::from(&[0xffu8; party_i::COMMITMENT_BYTES_LEN + 1][..]);
+        } else {
+            unreachable!("first message is sent at round 1")
+        }
+
+        let mut party = Keygen::new(1, 1, 3).unwrap();
+        assert!(matches!(
+            party.handle_incoming(msg),
+            Err(Error::OversizedCommitment { party: 2 })
+        ));
+    }
+
+    #[test]
+    fn abort_wipes_secrets() {
+        use curv::elliptic::curves::traits::ECScalar;
+
+        let (mut parties, _) = run_keygen_by_hand(1, 2);
+        let mut party = parties.remove(0);
+        party.wipe_secrets();
+        let local_key = party.pick_output().unwrap().unwrap();
+        assert!(local_key.shared_keys.sk_i == FE2::zero());
+        assert!(matches!(party.round, R::Gone));
+
+        let mut party = Keygen::new(1, 1, 2).unwrap();
+        party.proceed().unwrap();
+        assert_eq!(party.current_round(), 1);
+        party.abort();
+    }
+
+    #[test]
+    fn abort_wipes_recorded_shares() {
+        use curv::elliptic::curves::traits::ECScalar;
+
+        let mut parties: Vec<_> = (1..=2).map(|i| Keygen::new(i, 1, 2).unwrap()).collect();
+        parties[0].record();
+        while !parties.iter().all(|party| party.is_finished()) {
+            for party in &mut parties {
+                if party.wants_to_proceed() {
+                    party.proceed().unwrap();
+                }
+            }
+            let sent: Vec<_> = parties
+                .iter_mut()
+                .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+                .collect();
+            for msg in sent {
+                let receiver = if msg.sender == 1 { 1 } else { 0 };
+                parties[receiver].handle_incoming(msg).unwrap();
+            }
+        }
+
+        let recorded_shares = |party: &Keygen| -> Vec<FE2> {
+            party
+                .events()
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Incoming(Msg {
+                        body: ProtocolMessage(_, M::Round3((_, share))),
+                        ..
+                    }) => Some(*share),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(recorded_shares(&parties[0]).len(), 1);
+        parties[0].wipe_secrets();
+        assert!(recorded_shares(&parties[0])
+            .iter()
+            .all(|share| *share == FE2::zero()));
+    }
 }
@@ -4,39 +4,68 @@ use curv::cryptographic_primitives::secret_sharing::feldman_vss::{
 };
 use curv::elliptic::curves::bls12_381::g2::FE as FE2;
 use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use pairing_plus::bls12_381::G2Affine;
+use pairing_plus::serdes::SerDes;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+
 use round_based::containers::push::Push;
 use round_based::containers::{self, BroadcastMsgs, P2PMsgs, Store};
 use round_based::Msg;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use thiserror::Error;
+use zeroize::Zeroize;
 
+use crate::basic_bls::{g2_from_bytes, hash_to_g1, BLSSignature};
+use crate::threshold_bls::group_public_key;
 use crate::threshold_bls::party_i;
+use crate::threshold_bls::state_machine::KeygenIndex;
+use crate::ParseError;
+
+use super::Error;
 
 pub struct Round0 {
     pub party_i: u16,
     pub t: u16,
     pub n: u16,
+
+    pub security_bits: usize,
+    pub skip_dlog_proofs: bool,
+    pub encrypt_shares: bool,
 }
 
 impl Round0 {
     pub fn proceed<O>(self, mut output: O) -> Result<Round1>
     where
-        O: Push<Msg<party_i::KeyGenComm>>,
+        O: Push<Msg<(party_i::KeyGenComm, Option<GE2>)>>,
     {
         let keys = party_i::Keys::phase1_create(usize::from(self.party_i) - 1);
-        let (comm, decom) = keys.phase1_broadcast();
+        let (comm, decom) = keys
+            .phase1_broadcast_with_security(self.security_bits)
+            .map_err(ProceedError::Round0Commit)?;
+        let ephemeral_key = if self.encrypt_shares {
+            let sk: FE2 = ECScalar::new_random();
+            Some((sk, GE2::generator() * &sk))
+        } else {
+            None
+        };
         output.push(Msg {
             sender: self.party_i,
             receiver: None,
-            body: comm.clone(),
+            body: (comm.clone(), ephemeral_key.map(|(_, pk)| pk)),
         });
         Ok(Round1 {
             keys,
             comm,
             decom,
+            ephemeral_key,
             party_i: self.party_i,
             t: self.t,
             n: self.n,
+
+            skip_dlog_proofs: self.skip_dlog_proofs,
         })
     }
     pub fn is_expensive(&self) -> bool {
@@ -48,21 +77,41 @@ pub struct Round1 {
     keys: party_i::Keys,
     comm: party_i::KeyGenComm,
     decom: party_i::KeyGenDecom,
+    ephemeral_key: Option<(FE2, GE2)>,
 
     party_i: u16,
     t: u16,
     n: u16,
+
+    skip_dlog_proofs: bool,
 }
 
 impl Round1 {
     pub fn proceed<O>(
         self,
-        input: BroadcastMsgs<party_i::KeyGenComm>,
+        input: BroadcastMsgs<(party_i::KeyGenComm, Option<GE2>)>,
         mut output: O,
     ) -> Result<Round2>
     where
         O: Push<Msg<party_i::KeyGenDecom>>,
     {
+        let own_ephemeral_pk = self.ephemeral_key.map(|(_, pk)| pk);
+        let (received_comm, ephemeral_pks): (Vec<_>, Vec<_>) = input
+            .into_vec_including_me((self.comm, own_ephemeral_pk))
+            .into_iter()
+            .unzip();
+        if let Some(j) = ephemeral_pks
+            .iter()
+            .position(|pk| pk.is_some() != self.ephemeral_key.is_some())
+        {
+            return Err(ProceedError::Round1EncryptionMismatch {
+                party: j as u16 + 1,
+            });
+        }
+        let share_encryption = self
+            .ephemeral_key
+            .map(|(sk, _)| (sk, ephemeral_pks.into_iter().flatten().collect()));
+
         output.push(Msg {
             sender: self.party_i,
             receiver: None,
@@ -70,18 +119,24 @@ impl Round1 {
         });
         Ok(Round2 {
             keys: self.keys,
-            received_comm: input.into_vec_including_me(self.comm),
+            received_comm,
             decom: self.decom.clone(),
+            share_encryption,
 
             party_i: self.party_i,
             t: self.t,
             n: self.n,
+
+            skip_dlog_proofs: self.skip_dlog_proofs,
         })
     }
     pub fn is_expensive(&self) -> bool {
         false
     }
-    pub fn expects_messages(i: u16, n: u16) -> Store<BroadcastMsgs<party_i::KeyGenComm>> {
+    pub fn expects_messages(
+        i: u16,
+        n: u16,
+    ) -> Store<BroadcastMsgs<(party_i::KeyGenComm, Option<GE2>)>> {
         containers::BroadcastMsgsStore::new(i, n)
     }
 }
@@ -90,10 +145,22 @@ pub struct Round2 {
     keys: party_i::Keys,
     received_comm: Vec<party_i::KeyGenComm>,
     decom: party_i::KeyGenDecom,
+    share_encryption: Option<(FE2, Vec<GE2>)>,
 
     party_i: u16,
     t: u16,
     n: u16,
+
+    skip_dlog_proofs: bool,
+}
+
+impl Zeroize for Round1 {
+    fn zeroize(&mut self) {
+        self.keys.zeroize();
+        if let Some((sk, _)) = &mut self.ephemeral_key {
+            sk.zeroize();
+        }
+    }
 }
 
 impl Round2 {
@@ -115,19 +182,28 @@ impl Round2 {
             .phase1_verify_com_phase2_distribute(&params, &received_decom, &self.received_comm)
             .map_err(ProceedError::Round2VerifyCommitments)?;
         for (i, share) in secret_shares.iter().enumerate() {
-            if i + 1 == usize::from(self.party_i) {
+            // Share `i` is the polynomial evaluated at point `i+1`, which is party `i+1`'s
+            let receiver = i as u16 + 1;
+            if receiver == self.party_i {
                 continue;
             }
 
+            let share = match &self.share_encryption {
+                Some((sk, pks)) => {
+                    party_i::encrypt_share(share, sk, &pks[i], usize::from(self.party_i) - 1, i)
+                }
+                None => share.clone(),
+            };
             output.push(Msg {
                 sender: self.party_i,
-                receiver: Some(i as u16 + 1),
-                body: (vss_scheme.clone(), share.clone()),
+                receiver: Some(receiver),
+                body: (vss_scheme.clone(), share),
             })
         }
 
         Ok(Round3 {
             keys: self.keys,
+            share_encryption: self.share_encryption,
 
             y_vec: received_decom.into_iter().map(|d| d.y_i).collect(),
 
@@ -138,6 +214,8 @@ impl Round2 {
             party_i: self.party_i,
             t: self.t,
             n: self.n,
+
+            skip_dlog_proofs: self.skip_dlog_proofs,
         })
     }
     pub fn is_expensive(&self) -> bool {
@@ -156,10 +234,22 @@ pub struct Round3 {
     index: usize,
     own_vss: VerifiableSS<GE2>,
     own_share: FE2,
+    share_encryption: Option<(FE2, Vec<GE2>)>,
 
     party_i: u16,
     t: u16,
     n: u16,
+
+    skip_dlog_proofs: bool,
+}
+
+impl Zeroize for Round2 {
+    fn zeroize(&mut self) {
+        self.keys.zeroize();
+        if let Some((sk, _)) = &mut self.share_encryption {
+            sk.zeroize();
+        }
+    }
 }
 
 impl Round3 {
@@ -175,10 +265,18 @@ impl Round3 {
             threshold: self.t.into(),
             share_count: self.n.into(),
         };
-        let (vss_schemes, party_shares): (Vec<_>, Vec<_>) = input
+        let (vss_schemes, mut party_shares): (Vec<_>, Vec<_>) = input
             .into_vec_including_me((self.own_vss, self.own_share))
             .into_iter()
             .unzip();
+        if let Some((sk, pks)) = &self.share_encryption {
+            let me = usize::from(self.party_i) - 1;
+            for (j, share) in party_shares.iter_mut().enumerate() {
+                if j != me {
+                    *share = party_i::decrypt_share(share, sk, &pks[j], j, me);
+                }
+            }
+        }
 
         let (shared_keys, dlog_proof) = self
             .keys
@@ -204,6 +302,8 @@ impl Round3 {
             party_i: self.party_i,
             t: self.t,
             n: self.n,
+
+            skip_dlog_proofs: self.skip_dlog_proofs,
         })
     }
     pub fn is_expensive(&self) -> bool {
@@ -221,6 +321,18 @@ pub struct Round4 {
     party_i: u16,
     t: u16,
     n: u16,
+
+    skip_dlog_proofs: bool,
+}
+
+impl Zeroize for Round3 {
+    fn zeroize(&mut self) {
+        self.keys.zeroize();
+        self.own_share.zeroize();
+        if let Some((sk, _)) = &mut self.share_encryption {
+            sk.zeroize();
+        }
+    }
 }
 
 impl Round4 {
@@ -230,12 +342,24 @@ impl Round4 {
             share_count: self.n.into(),
         };
         let dlog_proofs = input.into_vec_including_me(self.own_dlog_proof);
-        party_i::Keys::verify_dlog_proofs(&params, &dlog_proofs)
-            .map_err(ProceedError::Round4VerifyDLogProof)?;
+        if !self.skip_dlog_proofs {
+            if let Err(err) = party_i::Keys::verify_dlog_proofs_batched(&params, &dlog_proofs) {
+                let parties: Vec<_> = party_i::find_invalid_dlog_proofs(&dlog_proofs)
+                    .into_iter()
+                    .map(|i| i as u16 + 1)
+                    .collect();
+                return Err(if parties.is_empty() {
+                    ProceedError::Round4VerifyDLogProof(err)
+                } else {
+                    ProceedError::Round4InvalidDLogProofs { parties }
+                });
+            }
+        }
         let vk_vec = dlog_proofs.into_iter().map(|p| p.pk).collect();
         Ok(LocalKey {
             shared_keys: self.shared_keys,
             vk_vec,
+            points: vec![],
 
             i: self.party_i,
             t: self.t,
@@ -250,27 +374,324 @@ impl Round4 {
     }
 }
 
+impl Zeroize for Round4 {
+    fn zeroize(&mut self) {
+        self.shared_keys.zeroize();
+    }
+}
+
 /// Local secret obtained by party after [keygen](super::Keygen) protocol is completed
 #[derive(Clone, Serialize, Deserialize)]
 pub struct LocalKey {
     pub(in crate::threshold_bls::state_machine) shared_keys: party_i::SharedKeys,
+    /// Verification keys of shares, `vk_vec[j-1]` is the key of share with keygen index `j`
     pub(in crate::threshold_bls::state_machine) vk_vec: Vec<GE2>,
+    /// Keygen indexes of shares held by parties, `points[i-1]` for party with index `i`
+    ///
+    /// Empty unless the key was [relabeled](Self::relabel), in which case every party holds
+    /// share with keygen index equal to its own index.
+    #[serde(default)]
+    pub(in crate::threshold_bls::state_machine) points: Vec<KeygenIndex>,
 
     pub(in crate::threshold_bls::state_machine) i: u16,
     pub(in crate::threshold_bls::state_machine) t: u16,
     pub(in crate::threshold_bls::state_machine) n: u16,
 }
 
+impl Zeroize for LocalKey {
+    fn zeroize(&mut self) {
+        self.shared_keys.zeroize();
+    }
+}
+
+/// Prints only public parameters and a fingerprint of the public key, never the secret share,
+/// so local key can be logged safely
+impl fmt::Debug for LocalKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalKey")
+            .field("i", &self.i)
+            .field("t", &self.t)
+            .field("n", &self.n)
+            .field("vk", &format_args!("{}", self.fingerprint()))
+            .finish()
+    }
+}
+
 impl LocalKey {
     /// Public key of secret shared between parties
     pub fn public_key(&self) -> GE2 {
         self.shared_keys.vk
     }
+
+    /// Index of the party at keygen, or index assigned to it by [relabel](Self::relabel)
+    pub fn index(&self) -> KeygenIndex {
+        KeygenIndex(self.i)
+    }
+
+    /// Keygen index of the share held by party with given `index`
+    ///
+    /// Shares are evaluations of the sharing polynomial at keygen indexes, so partial signature
+    /// of the party is interpolated at this point. It equals `index` unless the key was
+    /// [relabeled](Self::relabel). Returns `None` if `index` is not in range `[1; n]`.
+    pub fn share_index(&self, index: KeygenIndex) -> Option<KeygenIndex> {
+        let position = index.position(self.n)?;
+        Some(self.points.get(position).copied().unwrap_or(index))
+    }
+
+    /// Verification key of the share held by party with given `index`
+    pub(in crate::threshold_bls::state_machine) fn verification_key(
+        &self,
+        index: KeygenIndex,
+    ) -> Option<&GE2> {
+        let point = self.share_index(index)?;
+        self.vk_vec.get(usize::from(point.0) - 1)
+    }
+
+    /// Threshold `t`: any `t+1` parties can sign on behalf of the group
+    pub fn t(&self) -> u16 {
+        self.t
+    }
+
+    /// Number of parties holding a share of the key
+    pub fn n(&self) -> u16 {
+        self.n
+    }
+
+    /// Short fingerprint of the [public key](Self::public_key): hex-encoded first 8 bytes of
+    /// SHA-256 of its compressed encoding
+    ///
+    /// Lets operators check that local keys belong to the same group without printing whole
+    /// keys.
+    pub fn fingerprint(&self) -> String {
+        let mut vk = vec![];
+        G2Affine::serialize(&self.shared_keys.vk.get_element(), &mut vk, true)
+            .expect("serialization into vec never fails");
+        Sha256::digest(&vk)[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Produces partial signature of `message` without running signing protocol
+    ///
+    /// BLS partial signatures are non-interactive, so a party can sign offline and hand the
+    /// partial signature (along with its [index](Self::index)) over to a coordinator who calls
+    /// [combine_partials](Self::combine_partials).
+    pub fn partial_sign(&self, message: &[u8]) -> party_i::PartialSignature {
+        self.shared_keys.partial_sign(message).0
+    }
+
+    /// Verification keys of given signers, aligned with `signers`
+    ///
+    /// `signers` are [indexes](Self::index) of parties. Returned keys can be passed to
+    /// [combine](party_i::SharedKeys::combine) along with partial signatures ordered the same
+    /// way, and [share indexes](Self::share_index) of signers as interpolation points.
+    ///
+    /// Returns [Error::InvalidKeygenIndex] if signer index is out of range or repeated.
+    pub fn verification_keys_for(&self, signers: &[KeygenIndex]) -> Result<Vec<GE2>, Error> {
+        let mut vk_vec = Vec::with_capacity(signers.len());
+        for (k, signer) in signers.iter().enumerate() {
+            match self.verification_key(*signer) {
+                Some(vk) if !signers[..k].contains(signer) => vk_vec.push(*vk),
+                _ => return Err(Error::InvalidKeygenIndex { index: *signer }),
+            }
+        }
+        Ok(vk_vec)
+    }
+
+    /// Public parameters of the group: public key and verification keys of every party
+    ///
+    /// Contains no secrets, so it can be handed over to services which verify partial
+    /// signatures or combine them on behalf of the group.
+    pub fn public_params(&self) -> GroupPublicParams {
+        GroupPublicParams {
+            vk: self.public_key(),
+            vk_vec: self.vk_vec.clone(),
+            t: self.t,
+            n: self.n,
+        }
+    }
+
+    /// Combines partial signatures of `message` produced via [partial_sign](Self::partial_sign)
+    ///
+    /// Every item of `partials` is `(i, partial_sig)` where `i` is the [index](Self::index) of
+    /// the signer. Every partial signature is verified before combining, at least `t+1` of them
+    /// are required.
+    ///
+    /// Returns [Error::InvalidKeygenIndex] if signer index is out of range or repeated, or if
+    /// partial signature isn't made at [share index](Self::share_index) of the signer, and
+    /// [Error::CombinePartials] if partial signatures can't be combined.
+    pub fn combine_partials(
+        &self,
+        message: &[u8],
+        partials: &[(KeygenIndex, party_i::PartialSignature)],
+    ) -> Result<BLSSignature, Error> {
+        let signers: Vec<_> = partials.iter().map(|(signer, _)| *signer).collect();
+        let vk_vec = self.verification_keys_for(&signers)?;
+        let mut s = Vec::with_capacity(partials.len());
+        for (signer, partial) in partials {
+            match self.share_index(*signer) {
+                Some(point) if point == partial.index => s.push(point),
+                _ => return Err(Error::InvalidKeygenIndex { index: *signer }),
+            }
+        }
+        let sigs: Vec<_> = partials.iter().map(|(_, p)| p.clone()).collect();
+        self.shared_keys
+            .combine_verbose(&vk_vec, &sigs, hash_to_g1(message), &s)
+            .map_err(Error::CombinePartials)
+    }
+
+    /// Checks that `partials` combine into a valid signature of `message`, discarding the
+    /// signature
+    ///
+    /// Runs the same checks as [combine_partials](Self::combine_partials) and verifies combined
+    /// signature under the [public key](Self::public_key), so a party can make sure partial
+    /// signatures it collected are good before broadcasting them. Invalid partial signatures
+    /// are blamed via [CombineError::InvalidPartials](party_i::CombineError::InvalidPartials),
+    /// mislabelled ones via [Error::InvalidKeygenIndex].
+    ///
+    /// Returns the same errors as [combine_partials](Self::combine_partials), and
+    /// [Error::CombinedSignatureInvalid] if combined signature doesn't verify.
+    pub fn dry_run_combine(
+        &self,
+        message: &[u8],
+        partials: &[(KeygenIndex, party_i::PartialSignature)],
+    ) -> Result<(), Error> {
+        let signature = self.combine_partials(message, partials)?;
+        if !signature.verify(message, &self.public_key()) {
+            return Err(Error::CombinedSignatureInvalid);
+        }
+        Ok(())
+    }
+
+    /// Applies index permutation to the key
+    ///
+    /// `mapping` takes every party index used at keygen (in range `[1; n]`) to the new one.
+    /// Every party must apply the same mapping to its key, then keys can be used in
+    /// [signing](crate::threshold_bls::state_machine::sign::Sign) under new indexes.
+    ///
+    /// Secret share stays bound to the point it was evaluated at during keygen, so only party
+    /// indexes are changed: key remembers [share index](Self::share_index) of every party, and
+    /// partial signatures are still interpolated at share indexes. Secret key and public key
+    /// remain the same.
+    ///
+    /// Returns [Error::InvalidRelabeling] if `mapping` isn't a bijection over `[1; n]`.
+    pub fn relabel(&self, mapping: &HashMap<u16, u16>) -> Result<LocalKey, Error> {
+        if mapping.len() != usize::from(self.n) {
+            return Err(Error::InvalidRelabeling);
+        }
+        let mut points = vec![None; usize::from(self.n)];
+        for (&from, &to) in mapping {
+            if from == 0 || from > self.n || to == 0 || to > self.n {
+                return Err(Error::InvalidRelabeling);
+            }
+            let slot = &mut points[usize::from(to) - 1];
+            if slot.is_some() {
+                return Err(Error::InvalidRelabeling);
+            }
+            *slot = self.share_index(KeygenIndex(from));
+        }
+
+        Ok(LocalKey {
+            shared_keys: self.shared_keys.clone(),
+            vk_vec: self.vk_vec.clone(),
+            points: points.into_iter().flatten().collect(),
+
+            i: mapping[&self.i],
+            t: self.t,
+            n: self.n,
+        })
+    }
+}
+
+/// Public part of [LocalKey] shared by all parties of the group
+///
+/// Obtained via [LocalKey::public_params]. Deserialized params are validated the same way as
+/// [from_bytes](Self::from_bytes) does.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GroupPublicParams {
+    /// Public key of the group
+    pub vk: GE2,
+    /// Verification keys of shares, `vk_vec[j-1]` is the key of share with keygen index `j`
+    /// (see [LocalKey::share_index])
+    pub vk_vec: Vec<GE2>,
+    pub t: u16,
+    pub n: u16,
+}
+
+impl GroupPublicParams {
+    /// Serializes params as `t`, `n` (2 bytes big-endian each), followed by compressed `vk`
+    /// and `n` compressed verification keys (96 bytes each)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + 96 * (self.vk_vec.len() + 1));
+        bytes.extend_from_slice(&self.t.to_be_bytes());
+        bytes.extend_from_slice(&self.n.to_be_bytes());
+        for vk in std::iter::once(&self.vk).chain(&self.vk_vec) {
+            G2Affine::serialize(&vk.get_element(), &mut bytes, true)
+                .expect("serialize to vec should always succeed");
+        }
+        bytes
+    }
+
+    /// Parses params serialized via [to_bytes](Self::to_bytes)
+    ///
+    /// Returns [ParseError::InvalidEncoding] if `t` is not in range `[1; n-1]`, or if `vk_vec`
+    /// doesn't interpolate to `vk` (every verification key is checked, see
+    /// [group_public_key]).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() < 4 {
+            return Err(ParseError::InvalidLength);
+        }
+        let t = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let n = u16::from_be_bytes([bytes[2], bytes[3]]);
+        if bytes.len() != 4 + 96 * (usize::from(n) + 1) {
+            return Err(ParseError::InvalidLength);
+        }
+        let mut points = bytes[4..]
+            .chunks(96)
+            .map(g2_from_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        let vk = points.remove(0);
+        Self::from_parts(vk, points, t, n)
+    }
+
+    /// Validates params, see [from_bytes](Self::from_bytes)
+    fn from_parts(vk: GE2, vk_vec: Vec<GE2>, t: u16, n: u16) -> Result<Self, ParseError> {
+        if t == 0 || t >= n {
+            return Err(ParseError::InvalidEncoding);
+        }
+        let sharing_params = ShamirSecretSharing {
+            threshold: usize::from(t),
+            share_count: usize::from(n),
+        };
+        if group_public_key(&vk_vec, &sharing_params) != Ok(vk) {
+            return Err(ParseError::InvalidEncoding);
+        }
+        Ok(Self { vk, vk_vec, t, n })
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupPublicParams {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "GroupPublicParams")]
+        struct Unchecked {
+            vk: GE2,
+            vk_vec: Vec<GE2>,
+            t: u16,
+            n: u16,
+        }
+        let Unchecked { vk, vk_vec, t, n } = Unchecked::deserialize(deserializer)?;
+        Self::from_parts(vk, vk_vec, t, n).map_err(de::Error::custom)
+    }
 }
 
 // Errors
 
-type Result<T> = std::result::Result<T, ProceedError>;
+type Result<T, E = ProceedError> = std::result::Result<T, E>;
 
 /// Proceeding protocol error
 ///
@@ -278,10 +699,19 @@ type Result<T> = std::result::Result<T, ProceedError>;
 /// every message was received and pre-validated).
 #[derive(Debug, Error)]
 pub enum ProceedError {
+    #[error("round 0: commit: {0:?}")]
+    Round0Commit(crate::Error),
+    /// Party didn't send ephemeral key though shares must be encrypted, or sent it though
+    /// shares are not encrypted
+    #[error("round 1: party {party} disagrees on shares encryption")]
+    Round1EncryptionMismatch { party: u16 },
     #[error("round 2: verify commitments: {0:?}")]
     Round2VerifyCommitments(crate::Error),
     #[error("round 3: verify vss construction: {0:?}")]
     Round3VerifyVssConstruct(crate::Error),
     #[error("round 4: verify dlog proof: {0:?}")]
     Round4VerifyDLogProof(crate::Error),
+    /// Parties sent DLog proofs that didn't pass verification
+    #[error("round 4: parties {parties:?} sent invalid dlog proofs")]
+    Round4InvalidDLogProofs { parties: Vec<u16> },
 }
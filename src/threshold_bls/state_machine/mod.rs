@@ -1,2 +1,158 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use round_based::Msg;
+use serde::de::{self, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub mod driver;
 pub mod keygen;
 pub mod sign;
+pub mod sink;
+pub mod sync;
+
+pub use crate::threshold_bls::KeygenIndex;
+
+/// Index of party in signing protocol, in range `[1; n]` where `n` is number of signers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SignerIndex(pub u16);
+
+impl fmt::Display for SignerIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Outgoing message along with its delivery metadata
+///
+/// Obtained from [Keygen::drain_messages](keygen::Keygen::drain_messages) and
+/// [Sign::drain_messages](sign::Sign::drain_messages). Lets transport distinguish broadcast
+/// messages from P2P ones without inspecting message body.
+#[derive(Debug, Clone)]
+pub struct OutgoingMsg<M> {
+    /// Message to be sent
+    pub msg: Msg<M>,
+    /// Whether message must be delivered to every party
+    pub is_broadcast: bool,
+    /// Recipient of P2P message, `None` if message is broadcast
+    pub receiver: Option<u16>,
+}
+
+impl<M> From<Msg<M>> for OutgoingMsg<M> {
+    fn from(msg: Msg<M>) -> Self {
+        Self {
+            is_broadcast: msg.receiver.is_none(),
+            receiver: msg.receiver,
+            msg,
+        }
+    }
+}
+
+/// Event recorded by state machine in record mode
+///
+/// See [Keygen::record](keygen::Keygen::record) and [Sign::record](sign::Sign::record).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Event<M> {
+    /// Message passed to [handle_incoming](round_based::StateMachine::handle_incoming)
+    Incoming(Msg<M>),
+    /// Call of [proceed](round_based::StateMachine::proceed) which moved state machine from
+    /// `from_round` to `to_round` (the same round if it didn't proceed)
+    Proceed { from_round: u16, to_round: u16 },
+}
+
+/// Deserializes protocol message `(version, body)`, decoding the body only if the version is
+/// `supported`
+///
+/// Body of a message of another version may have a structure unknown to this party, so it's
+/// skipped and `None` is returned in its place. State machine then rejects the message with
+/// `UnsupportedProtocolVersion` instead of failing at deserialization.
+pub(crate) fn deserialize_versioned<'de, D, M>(
+    deserializer: D,
+    name: &'static str,
+    supported: u8,
+) -> Result<(u8, Option<M>), D::Error>
+where
+    D: Deserializer<'de>,
+    M: Deserialize<'de>,
+{
+    struct VersionedVisitor<M> {
+        supported: u8,
+        body: PhantomData<M>,
+    }
+
+    impl<'de, M: Deserialize<'de>> Visitor<'de> for VersionedVisitor<M> {
+        type Value = (u8, Option<M>);
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("protocol version followed by message body")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let version: u8 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            if version != self.supported {
+                seq.next_element::<IgnoredAny>()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                return Ok((version, None));
+            }
+            let body = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            Ok((version, Some(body)))
+        }
+    }
+
+    deserializer.deserialize_tuple_struct(
+        name,
+        2,
+        VersionedVisitor {
+            supported,
+            body: PhantomData,
+        },
+    )
+}
+
+/// Received P2P message in a round that expects broadcast messages, or vice versa
+///
+/// Every protocol converts it into its own `UnexpectedMessageKind` error.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UnexpectedMessageKind {
+    pub round: u16,
+    pub expected_broadcast: bool,
+}
+
+/// Makes sure that message is broadcast or P2P as the round expects
+pub(crate) fn check_msg_kind(
+    round: u16,
+    receiver: Option<u16>,
+    expected_broadcast: bool,
+) -> Result<(), UnexpectedMessageKind> {
+    if receiver.is_none() != expected_broadcast {
+        return Err(UnexpectedMessageKind {
+            round,
+            expected_broadcast,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::keygen::{Keygen, LocalKey};
+    use super::sign::Sign;
+
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+
+    #[test]
+    fn state_machines_are_send_and_sync() {
+        _assert_send::<Keygen>();
+        _assert_sync::<Keygen>();
+        _assert_send::<Sign>();
+        _assert_sync::<Sign>();
+        _assert_send::<LocalKey>();
+        _assert_sync::<LocalKey>();
+    }
+}
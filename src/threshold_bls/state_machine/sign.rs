@@ -5,32 +5,51 @@ use std::mem::replace;
 use std::time::Duration;
 
 use curv::elliptic::curves::bls12_381::g1::GE as GE1;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use pairing_plus::bls12_381::G1Affine;
+use pairing_plus::serdes::SerDes;
+use pairing_plus::CurveAffine;
 use round_based::containers::{
     push::{Push, PushExt},
     *,
 };
 use round_based::{IsCritical, Msg, StateMachine};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 
-use crate::basic_bls::BLSSignature;
+use crate::basic_bls::{g1_in_subgroup, hash_to_g1, KeyPairG2};
 use crate::threshold_bls::party_i;
 use crate::threshold_bls::state_machine::keygen::LocalKey;
+use crate::threshold_bls::state_machine::{
+    check_msg_kind, deserialize_versioned, Event, KeygenIndex, OutgoingMsg, UnexpectedMessageKind,
+};
 
 mod rounds;
-pub use rounds::ProceedError;
-use rounds::{Round0, Round1};
+mod signing_root;
+use rounds::{CommitRound, ReceiveFirstValidPartialSigs, RevealRound, Round0, Round1};
+pub use rounds::{ProceedError, SignatureReceipt, SignedMessage};
+pub use signing_root::SigningRoot;
 
 /// Signing protocol state machine
 ///
-/// Successfully completed keygen protocol produces [GE1] representing a message on g1 curve and
-/// [BLSSignature]
+/// Successfully completed signing protocol produces [SignedMessage]: threshold signature along
+/// with the message hash point on G1
+///
+/// `Sign` is `Send + Sync`, so state machine can be moved into a spawned task or shared between
+/// threads.
 pub struct Sign {
     round: R,
 
-    msgs1: Option<Store<BroadcastMsgs<(u16, party_i::PartialSignature)>>>,
+    msgs1: Option<ReceiveFirstValidPartialSigs>,
+    msgs_commit: Option<Store<BroadcastMsgs<BigInt>>>,
+    msgs_reveal: Option<Store<BroadcastMsgs<(KeygenIndex, party_i::PartialSignature, BigInt)>>>,
+    early_combine: bool,
+    commit_reveal: bool,
+    receipt: Option<SignatureReceipt>,
 
     msgs_queue: Vec<Msg<ProtocolMessage>>,
+    events: Option<Vec<Event<ProtocolMessage>>>,
 
     party_i: u16,
     party_n: u16,
@@ -41,7 +60,8 @@ impl Sign {
     ///
     /// Takes party index `i` (in range `[1; n]`), number of parties involved in
     /// signing `n`, and local key obtained in keygen. Party index identifies this party
-    /// in the protocol, so it must be guaranteed to be unique.
+    /// in the protocol, so it must be guaranteed to be unique. `message` may be empty (see
+    /// [hash_to_g1]).
     ///
     /// Returns error if:
     /// * `n` is less than `threshold+1`, returns [Error::TooFewParties]
@@ -49,6 +69,100 @@ impl Sign {
     ///   returns [Error::TooManyParties]
     /// * `i` is not in range `[1; n]`, returns [Error::InvalidPartyIndex]
     pub fn new(message: Vec<u8>, i: u16, n: u16, local_key: LocalKey) -> Result<Self> {
+        Self::construct(message, None, i, n, local_key, Mode::Regular, None)
+    }
+
+    /// Constructs a party of signing protocol that issues receipt of its participation
+    ///
+    /// Takes the same arguments and returns the same errors as [Sign::new]. Additionally takes
+    /// party's long-term key `receipt_key` which signs [SignatureReceipt] once partial
+    /// signature is produced. Receipt can be obtained via [receipt](Self::receipt) and checked
+    /// by anyone knowing long-term public key of the party.
+    pub fn with_receipt(
+        message: Vec<u8>,
+        i: u16,
+        n: u16,
+        local_key: LocalKey,
+        receipt_key: KeyPairG2,
+    ) -> Result<Self> {
+        Self::construct(
+            message,
+            None,
+            i,
+            n,
+            local_key,
+            Mode::Regular,
+            Some(receipt_key),
+        )
+    }
+
+    /// Constructs a party of signing protocol that combines signature as soon as it received
+    /// `threshold+1` valid partial signatures (including its own one)
+    ///
+    /// Takes the same arguments and returns the same errors as [Sign::new]. Every received
+    /// partial signature is verified on arrival, invalid ones are ignored. Once quorum is
+    /// reached, the signer set is frozen and partial signatures arriving later are ignored, so
+    /// slow or misbehaving signers don't stall the protocol.
+    ///
+    /// Note that the quorum depends on the order messages were delivered in, so different
+    /// parties may combine signature from different signer sets. Resulting signature is the
+    /// same regardless of the signer set, but if you record which parties contributed to the
+    /// signature, don't expect the records to match across parties.
+    pub fn new_early_combine(
+        message: Vec<u8>,
+        i: u16,
+        n: u16,
+        local_key: LocalKey,
+    ) -> Result<Self> {
+        Self::construct(message, None, i, n, local_key, Mode::EarlyCombine, None)
+    }
+
+    /// Constructs a party of signing protocol that commits to its partial signature before
+    /// revealing it
+    ///
+    /// Takes the same arguments and returns the same errors as [Sign::new]. Protocol takes two
+    /// broadcast rounds instead of one: at round 1 parties broadcast commitments to their
+    /// partial signatures, and at round 2 they reveal partial signatures, which are checked
+    /// against commitments. A rushing adversary can't pick its partial signature after seeing
+    /// partial signatures of honest parties. Every party must be constructed this way.
+    pub fn with_commit_reveal(
+        message: Vec<u8>,
+        i: u16,
+        n: u16,
+        local_key: LocalKey,
+    ) -> Result<Self> {
+        Self::construct(message, None, i, n, local_key, Mode::CommitReveal, None)
+    }
+
+    /// Constructs a party of signing protocol which signs a point on G1 as is
+    ///
+    /// For protocols where message is mapped to the curve externally, e.g. distributed VRF
+    /// whose input is already a point: hashing it once more would produce a different output.
+    /// Resulting [SignedMessage] has `point` as its hash point, use
+    /// [SignedMessage::verify] to verify it.
+    ///
+    /// Takes the same arguments and returns the same errors as [Sign::new], and additionally
+    /// [Error::InvalidMessagePoint] if `point` is the point at infinity or is not in the prime
+    /// order subgroup.
+    pub fn new_on_point(point: GE1, i: u16, n: u16, local_key: LocalKey) -> Result<Self> {
+        if point.get_element().is_zero() || !g1_in_subgroup(&point) {
+            return Err(Error::InvalidMessagePoint);
+        }
+        let mut message = vec![];
+        G1Affine::serialize(&point.get_element(), &mut message, true)
+            .expect("serialization into vec never fails");
+        Self::construct(message, Some(point), i, n, local_key, Mode::Regular, None)
+    }
+
+    fn construct(
+        message: Vec<u8>,
+        H_x: Option<GE1>,
+        i: u16,
+        n: u16,
+        local_key: LocalKey,
+        mode: Mode,
+        receipt_key: Option<KeyPairG2>,
+    ) -> Result<Self> {
         if n < local_key.t + 1 {
             return Err(Error::TooFewParties);
         }
@@ -58,17 +172,42 @@ impl Sign {
         if i == 0 || i > n {
             return Err(Error::InvalidPartyIndex);
         }
+        let H_x = H_x.unwrap_or_else(|| hash_to_g1(&message));
+        let msgs1 = match mode {
+            Mode::Regular => Some(Round1::expects_messages(i, n)),
+            Mode::EarlyCombine => Some(ReceiveFirstValidPartialSigs::first_valid(
+                i, n, &local_key, H_x,
+            )),
+            Mode::CommitReveal => None,
+        };
+        let commit_reveal = mode == Mode::CommitReveal;
+        let (msgs_commit, msgs_reveal) = if commit_reveal {
+            (
+                Some(CommitRound::expects_messages(i, n)),
+                Some(RevealRound::expects_messages(i, n)),
+            )
+        } else {
+            (None, None)
+        };
         let mut state = Self {
             round: R::Round0(Round0 {
                 key: local_key,
                 message,
+                H_x,
+                receipt_key,
                 i,
                 n,
             }),
 
-            msgs1: Some(Round1::expects_messages(i, n)),
+            msgs1,
+            msgs_commit,
+            msgs_reveal,
+            early_combine: mode == Mode::EarlyCombine,
+            commit_reveal,
+            receipt: None,
 
             msgs_queue: vec![],
+            events: None,
 
             party_i: i,
             party_n: n,
@@ -78,25 +217,176 @@ impl Sign {
         Ok(state)
     }
 
+    /// Receipt of party's participation in signing
+    ///
+    /// Returns `None` if party was constructed without receipt key (see
+    /// [with_receipt](Self::with_receipt)), or if partial signature isn't produced yet.
+    pub fn receipt(&self) -> Option<&SignatureReceipt> {
+        self.receipt.as_ref()
+    }
+
+    /// Enables recording of events: every incoming message and every proceed call
+    ///
+    /// Recorded [events](Self::events) can be [replayed](Self::replay) to deterministically
+    /// reproduce protocol execution, e.g. to debug a stuck signing. Event log contains only
+    /// messages received from other parties, so it doesn't reveal the local key.
+    ///
+    /// Replayed party reaches the same signature, but samples its DDH proof and commit-reveal
+    /// blinding factor anew, so its own messages differ from the recorded run. Replay of a
+    /// [keygen](crate::threshold_bls::state_machine::keygen::Keygen::record) is not
+    /// deterministic at all: replayed party samples a new secret polynomial, so it doesn't
+    /// reproduce the resulting key.
+    pub fn record(&mut self) {
+        if self.events.is_none() {
+            self.events = Some(vec![])
+        }
+    }
+
+    /// Returns events recorded since [record](Self::record) was called
+    ///
+    /// Empty if recording is not enabled.
+    pub fn events(&self) -> &[Event<ProtocolMessage>] {
+        self.events.as_deref().unwrap_or(&[])
+    }
+
+    /// Re-drives freshly constructed state machine through recorded `events`
+    ///
+    /// State machine must be constructed with the same arguments as the one which recorded
+    /// events. Incoming messages are passed to [handle_incoming](StateMachine::handle_incoming)
+    /// and every recorded proceed results in [proceed](StateMachine::proceed) call. Like protocol
+    /// executor, replay skips non-critical errors and stops at the first critical one, which is
+    /// returned. Replayed state machine records events as well.
+    pub fn replay(mut self, events: &[Event<ProtocolMessage>]) -> Result<Self> {
+        self.record();
+        for event in events {
+            let result = match event {
+                Event::Incoming(msg) => self.handle_incoming(msg.clone()),
+                Event::Proceed { .. } => self.proceed(),
+            };
+            match result {
+                Err(err) if err.is_critical() => return Err(err),
+                _ => (),
+            }
+        }
+        Ok(self)
+    }
+
+    fn record_proceed(&mut self, from_round: u16) {
+        let to_round = self.current_round();
+        if let Some(events) = self.events.as_mut() {
+            events.push(Event::Proceed {
+                from_round,
+                to_round,
+            });
+        }
+    }
+
+    /// Takes all outgoing messages from the queue along with their delivery metadata
+    ///
+    /// Alternative to [message_queue](StateMachine::message_queue) for transports that want to
+    /// handle broadcast and P2P messages differently.
+    pub fn drain_messages(&mut self) -> Vec<OutgoingMsg<ProtocolMessage>> {
+        self.msgs_queue.drain(..).map(OutgoingMsg::from).collect()
+    }
+
+    /// Whether protocol is completed and output can be taken via
+    /// [pick_output](StateMachine::pick_output)
+    ///
+    /// Output can be taken only once: after that, `output_ready` returns `false` again.
+    pub fn output_ready(&self) -> bool {
+        matches!(self.round, R::Final(_))
+    }
+
+    /// Number of messages this party needs to receive in given `round` to proceed
+    ///
+    /// Returns `None` if party doesn't receive any messages in the round (or round doesn't
+    /// exist). Signing has a single broadcast round in which party receives partial signature
+    /// from each other party. Party constructed via
+    /// [with_commit_reveal](Self::with_commit_reveal) receives commitments at round 1 and
+    /// partial signatures at round 2.
+    ///
+    /// Party constructed via [new_early_combine](Self::new_early_combine) may proceed sooner,
+    /// so returned value is an upper bound in that case.
+    pub fn expected_messages(&self, round: u16) -> Option<usize> {
+        match round {
+            1 => Some(usize::from(self.party_n) - 1),
+            2 if self.commit_reveal => Some(usize::from(self.party_n) - 1),
+            _ => None,
+        }
+    }
+
+    /// Number of partial signatures this party still waits for from other parties
+    ///
+    /// Lets coordinator show signing progress. Party constructed via
+    /// [new_early_combine](Self::new_early_combine) waits only for `threshold` valid partial
+    /// signatures, other parties wait for partial signatures of every other signer.
+    ///
+    /// Returns `None` once partial signatures are collected and protocol proceeded past them.
+    pub fn signatures_remaining(&self) -> Option<usize> {
+        if let Some(msgs1) = &self.msgs1 {
+            Some(msgs1.remaining())
+        } else {
+            self.msgs_reveal
+                .as_ref()
+                .map(|s| s.messages_total() - s.messages_received())
+        }
+    }
+
+    /// Number of partial signatures this party still waits for, and indexes of signers who
+    /// haven't sent one yet
+    ///
+    /// Lets coordinator tell which signers hold the protocol up. Party constructed via
+    /// [new_early_combine](Self::new_early_combine) counts only valid partial signatures, so
+    /// the number can exceed the number of listed signers if some of them sent invalid ones.
+    ///
+    /// Returns `None` once partial signatures are collected and protocol proceeded past them.
+    pub fn missing_partials(&self) -> Option<(u16, Vec<u16>)> {
+        if let Some(msgs1) = &self.msgs1 {
+            Some(msgs1.blame())
+        } else {
+            self.msgs_reveal.as_ref().map(|s| s.blame())
+        }
+    }
+
     fn gmap_queue<'a, T, F>(&'a mut self, mut f: F) -> impl Push<Msg<T>> + 'a
     where
         F: FnMut(T) -> M + 'a,
     {
-        (&mut self.msgs_queue).gmap(move |m: Msg<T>| m.map_body(|m| ProtocolMessage(f(m))))
+        (&mut self.msgs_queue)
+            .gmap(move |m: Msg<T>| m.map_body(|m| ProtocolMessage(PROTOCOL_VERSION, f(m))))
     }
 
     /// Proceeds round state if it received enough messages and if it's cheap to compute or
     /// `may_block == true`
     fn proceed_round(&mut self, may_block: bool) -> Result<()> {
         let store1_wants_more = self.msgs1.as_ref().map(|s| s.wants_more()).unwrap_or(false);
+        let commit_store_wants_more = self
+            .msgs_commit
+            .as_ref()
+            .map(|s| s.wants_more())
+            .unwrap_or(false);
+        let reveal_store_wants_more = self
+            .msgs_reveal
+            .as_ref()
+            .map(|s| s.wants_more())
+            .unwrap_or(false);
 
         let next_state: R;
         let try_again: bool = match replace(&mut self.round, R::Gone) {
+            R::Round0(round) if self.commit_reveal && (!round.is_expensive() || may_block) => {
+                let commit_round = round
+                    .proceed_with_commitment(self.gmap_queue(M::Commit))
+                    .map_err(Error::ProceedRound)?;
+                self.receipt = commit_round.receipt().cloned();
+                next_state = R::Commit(commit_round);
+                true
+            }
             R::Round0(round) if !round.is_expensive() || may_block => {
-                next_state = round
+                let round1 = round
                     .proceed(self.gmap_queue(M::Round1))
-                    .map(R::Round1)
                     .map_err(Error::ProceedRound)?;
+                self.receipt = round1.receipt.clone();
+                next_state = R::Round1(round1);
                 true
             }
             s @ R::Round0(_) => {
@@ -120,6 +410,42 @@ impl Sign {
                 false
             }
 
+            R::Commit(round)
+                if !commit_store_wants_more && (!round.is_expensive() || may_block) =>
+            {
+                let store = self.msgs_commit.take().ok_or(InternalError::StoreGone)?;
+                let msgs = store
+                    .finish()
+                    .map_err(InternalError::RetrieveRoundMessages)?;
+                next_state = round
+                    .proceed(msgs, self.gmap_queue(M::Reveal))
+                    .map(R::Reveal)
+                    .map_err(Error::ProceedRound)?;
+                true
+            }
+            s @ R::Commit(_) => {
+                next_state = s;
+                false
+            }
+
+            R::Reveal(round)
+                if !reveal_store_wants_more && (!round.is_expensive() || may_block) =>
+            {
+                let store = self.msgs_reveal.take().ok_or(InternalError::StoreGone)?;
+                let msgs = store
+                    .finish()
+                    .map_err(InternalError::RetrieveRoundMessages)?;
+                next_state = round
+                    .proceed(msgs)
+                    .map(R::Final)
+                    .map_err(Error::ProceedRound)?;
+                true
+            }
+            s @ R::Reveal(_) => {
+                next_state = s;
+                false
+            }
+
             s @ R::Final(_) | s @ R::Gone => {
                 next_state = s;
                 false
@@ -138,13 +464,27 @@ impl Sign {
 impl StateMachine for Sign {
     type MessageBody = ProtocolMessage;
     type Err = Error;
-    type Output = (GE1, BLSSignature);
+    type Output = SignedMessage;
 
     fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<()> {
         let current_round = self.current_round();
+        if let Some(events) = self.events.as_mut() {
+            events.push(Event::Incoming(msg.clone()));
+        }
+
+        if msg.body.0 != PROTOCOL_VERSION {
+            return Err(Error::UnsupportedProtocolVersion {
+                version: msg.body.0,
+            });
+        }
 
         match msg.body {
-            ProtocolMessage(M::Round1(m)) => {
+            ProtocolMessage(_, M::Round1(_)) if self.msgs1.is_none() && self.early_combine => {
+                // Signer set is already frozen, late partial signatures are ignored
+                Ok(())
+            }
+            ProtocolMessage(_, M::Round1(m)) => {
+                check_msg_kind(1, msg.receiver, true)?;
                 let store = self
                     .msgs1
                     .as_mut()
@@ -161,6 +501,48 @@ impl StateMachine for Sign {
                     .map_err(Error::HandleMessage)?;
                 self.proceed_round(false)
             }
+            ProtocolMessage(_, M::Commit(m)) => {
+                check_msg_kind(1, msg.receiver, true)?;
+                if m.bit_length() > 8 * party_i::COMMITMENT_BYTES_LEN {
+                    return Err(Error::OversizedCommitment { party: msg.sender });
+                }
+                let store = self
+                    .msgs_commit
+                    .as_mut()
+                    .ok_or(Error::ReceivedOutOfOrderMessage {
+                        current_round,
+                        msg_round: 1,
+                    })?;
+                store
+                    .push_msg(Msg {
+                        sender: msg.sender,
+                        receiver: msg.receiver,
+                        body: m,
+                    })
+                    .map_err(Error::HandleMessage)?;
+                self.proceed_round(false)
+            }
+            ProtocolMessage(version, M::Unsupported) => {
+                Err(Error::UnsupportedProtocolVersion { version })
+            }
+            ProtocolMessage(_, M::Reveal(m)) => {
+                check_msg_kind(2, msg.receiver, true)?;
+                let store = self
+                    .msgs_reveal
+                    .as_mut()
+                    .ok_or(Error::ReceivedOutOfOrderMessage {
+                        current_round,
+                        msg_round: 2,
+                    })?;
+                store
+                    .push_msg(Msg {
+                        sender: msg.sender,
+                        receiver: msg.receiver,
+                        body: m,
+                    })
+                    .map_err(Error::HandleMessage)?;
+                self.proceed_round(false)
+            }
         }
     }
 
@@ -170,16 +552,31 @@ impl StateMachine for Sign {
 
     fn wants_to_proceed(&self) -> bool {
         let store1_wants_more = self.msgs1.as_ref().map(|s| s.wants_more()).unwrap_or(false);
+        let commit_store_wants_more = self
+            .msgs_commit
+            .as_ref()
+            .map(|s| s.wants_more())
+            .unwrap_or(false);
+        let reveal_store_wants_more = self
+            .msgs_reveal
+            .as_ref()
+            .map(|s| s.wants_more())
+            .unwrap_or(false);
 
         match &self.round {
             R::Round0(_) => true,
             R::Round1(_) => !store1_wants_more,
+            R::Commit(_) => !commit_store_wants_more,
+            R::Reveal(_) => !reveal_store_wants_more,
             R::Final(_) | R::Gone => false,
         }
     }
 
     fn proceed(&mut self) -> Result<()> {
-        self.proceed_round(true)
+        let from_round = self.current_round();
+        let result = self.proceed_round(true);
+        self.record_proceed(from_round);
+        result
     }
 
     fn round_timeout(&self) -> Option<Duration> {
@@ -194,6 +591,11 @@ impl StateMachine for Sign {
         matches!(self.round, R::Final(_))
     }
 
+    /// Takes protocol output
+    ///
+    /// One-shot: returns output once protocol is completed (see
+    /// [output_ready](Sign::output_ready)), and error on any subsequent call. Returns `None`
+    /// without affecting protocol state if protocol is not completed yet.
     fn pick_output(&mut self) -> Option<Result<Self::Output>> {
         match self.round {
             R::Final(_) => (),
@@ -210,7 +612,9 @@ impl StateMachine for Sign {
     fn current_round(&self) -> u16 {
         match &self.round {
             R::Round0(_) => 0,
-            R::Round1(_) => 1,
+            R::Round1(_) | R::Commit(_) => 1,
+            R::Reveal(_) => 2,
+            R::Final(_) | R::Gone if self.commit_reveal => 3,
             R::Final(_) | R::Gone => 2,
         }
     }
@@ -248,15 +652,36 @@ pub enum Error {
     /// Party index is not in range `[1; n]`
     #[error("party index is not in range [1; n]")]
     InvalidPartyIndex,
+    /// Point given to [Sign::new_on_point] is the point at infinity or is not in the prime order
+    /// subgroup
+    #[error("message point is the point at infinity or is not in the prime order subgroup")]
+    InvalidMessagePoint,
 
     /// Received message didn't pass pre-validation
     #[error("received message didn't pass pre-validation: {0}")]
     HandleMessage(#[source] StoreErr),
-    /// Received message which we didn't expect to receive now (e.g. message from previous round)
+    /// Received message of a round that's already completed
+    ///
+    /// Messages of future rounds are not an error: they're kept until their round begins.
     #[error(
         "didn't expect to receive message from round {msg_round} (being at round {current_round})"
     )]
     ReceivedOutOfOrderMessage { current_round: u16, msg_round: u16 },
+    /// Party `party` sent commitment to its partial signature exceeding
+    /// [COMMITMENT_BYTES_LEN](party_i::COMMITMENT_BYTES_LEN) bytes
+    #[error("party {party} sent oversized commitment")]
+    OversizedCommitment { party: u16 },
+    /// Received message of protocol version which is not supported by this party
+    #[error("received message of unsupported protocol version {version}")]
+    UnsupportedProtocolVersion { version: u8 },
+    /// Received P2P message in a round that expects broadcast messages, or vice versa
+    #[error(
+        "received message of unexpected kind at round {round} (expected_broadcast={expected_broadcast})"
+    )]
+    UnexpectedMessageKind {
+        round: u16,
+        expected_broadcast: bool,
+    },
     /// [Sign::pick_output] called twice
     #[error("pick_output called twice")]
     DoublePickResult,
@@ -267,6 +692,15 @@ pub enum Error {
     InternalError(InternalError),
 }
 
+impl From<UnexpectedMessageKind> for Error {
+    fn from(err: UnexpectedMessageKind) -> Self {
+        Self::UnexpectedMessageKind {
+            round: err.round,
+            expected_broadcast: err.expected_broadcast,
+        }
+    }
+}
+
 impl IsCritical for Error {
     fn is_critical(&self) -> bool {
         true
@@ -296,7 +730,8 @@ impl fmt::Debug for Sign {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let current_round = match &self.round {
             R::Round0(_) => "0",
-            R::Round1(_) => "1",
+            R::Round1(_) | R::Commit(_) => "1",
+            R::Reveal(_) => "2",
             R::Final(_) => "[Final]",
             R::Gone => "[Gone]",
         };
@@ -319,29 +754,69 @@ impl fmt::Debug for Sign {
 enum R {
     Round0(Round0),
     Round1(Round1),
-    Final((GE1, BLSSignature)),
+    Commit(CommitRound),
+    Reveal(RevealRound),
+    Final(SignedMessage),
     Gone,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Regular,
+    EarlyCombine,
+    CommitReveal,
+}
+
 // Messages
 
 /// Protocol message which parties send on wire
 ///
 /// Hides actual messages structure so it could be changed without breaking semver policy.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ProtocolMessage(M);
+/// Serialized message is tagged with protocol version, so parties running incompatible versions
+/// of the protocol reject each other's messages. Body of a message of another version isn't
+/// decoded, so such message is reported as [Error::UnsupportedProtocolVersion] rather than
+/// failing to deserialize.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProtocolMessage(u8, M);
+
+impl<'de> Deserialize<'de> for ProtocolMessage {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (version, body) =
+            deserialize_versioned(deserializer, "ProtocolMessage", PROTOCOL_VERSION)?;
+        Ok(ProtocolMessage(version, body.unwrap_or(M::Unsupported)))
+    }
+}
+
+/// Version of protocol messages, must be bumped on every change of message structure
+const PROTOCOL_VERSION: u8 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 enum M {
-    Round1((u16, party_i::PartialSignature)),
+    Round1((KeygenIndex, party_i::PartialSignature)),
+    Commit(BigInt),
+    Reveal((KeygenIndex, party_i::PartialSignature, BigInt)),
+    /// Body of a message of unsupported protocol version, which is not decoded
+    #[serde(skip)]
+    Unsupported,
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
+    use curv::elliptic::curves::bls12_381::g1::GE as GE1;
+    use curv::elliptic::curves::bls12_381::g2::FE as FE2;
+    use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+    use pairing_plus::bls12_381::G1Compressed;
+    use pairing_plus::{EncodedPoint, GroupDecodingError};
     use round_based::dev::Simulation;
 
     use super::*;
-    use crate::threshold_bls::state_machine::keygen::Keygen;
+    use crate::basic_bls::{hash_to_g1, verify_bytes};
+    use crate::threshold_bls::state_machine::keygen::{Error as KeygenError, Keygen};
 
     fn simulate_sign(msg: &[u8], s: &[u16], t: u16, n: u16) {
         // Keygen
@@ -364,24 +839,658 @@ mod test {
             sign_simulation.add_party(Sign::new(msg.into(), i, n, key).unwrap());
         }
 
-        let (_, sigs): (Vec<_>, Vec<_>) = sign_simulation.run().unwrap().into_iter().unzip();
+        let (hashes, sigs): (Vec<_>, Vec<_>) = sign_simulation
+            .run()
+            .unwrap()
+            .into_iter()
+            .map(SignedMessage::into_parts)
+            .unzip();
 
         // test all signatures are equal
         let first = sigs[0];
         assert!(sigs.iter().all(|&item| item == first));
         // test the signatures pass verification
         assert!(parties_keys[0].shared_keys.verify(&sigs[0], msg));
+        // test the signature is a standard BLS signature under group public key
+        assert_eq!(hashes[0], hash_to_g1(msg));
+        assert!(sigs[0].verify(msg, &parties_keys[0].public_key()));
 
         println!("Benchmarks:");
         println!("{:#?}", sign_simulation.benchmark_results().unwrap());
     }
 
+    #[test]
+    fn simulate_sign_with_commit_reveal() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut sign_simulation = Simulation::new();
+        for (i, key) in (1..).zip(&parties_keys) {
+            sign_simulation
+                .add_party(Sign::with_commit_reveal(msg.to_vec(), i, 3, key.clone()).unwrap());
+        }
+        let sigs = sign_simulation.run().unwrap();
+        assert!(sigs.iter().all(|sig| sig == &sigs[0]));
+        assert!(sigs[0]
+            .signature()
+            .verify(msg, &parties_keys[0].public_key()));
+    }
+
+    #[test]
+    fn commit_reveal_detects_reveal_not_matching_commitment() {
+        use crate::threshold_bls::state_machine::SignerIndex;
+
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut party1 =
+            Sign::with_commit_reveal(msg.to_vec(), 1, 2, parties_keys[0].clone()).unwrap();
+        let mut party2 =
+            Sign::with_commit_reveal(msg.to_vec(), 2, 2, parties_keys[1].clone()).unwrap();
+        party1.proceed().unwrap();
+        party2.proceed().unwrap();
+        assert_eq!(party1.current_round(), 1);
+
+        // Exchange commitments
+        let commit1 = party1.message_queue().pop().unwrap();
+        let commit2 = party2.message_queue().pop().unwrap();
+        party1.handle_incoming(commit2).unwrap();
+        party2.handle_incoming(commit1).unwrap();
+        assert_eq!(party1.current_round(), 2);
+        assert_eq!(party2.current_round(), 2);
+
+        // Party 1 reveals partial signature with a different blinding factor
+        let mut reveal1 = party1.message_queue().pop().unwrap();
+        match &mut reveal1.body {
+            ProtocolMessage(_, M::Reveal((_, _, blind_factor))) => {
+                *blind_factor = blind_factor.clone() + BigInt::from(1u32)
+            }
+            _ => panic!("expected reveal"),
+        }
+        party2.handle_incoming(reveal1).unwrap();
+        match party2.proceed() {
+            Err(Error::ProceedRound(ProceedError::PartyRevealedUncommittedPartial {
+                who: SignerIndex(1),
+            })) => (),
+            result => panic!("expected PartyRevealedUncommittedPartial, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sign_rejects_message_of_unexpected_kind() {
+        let msg = b"~~ MESSAGE ~~";
+        let keys = crate::threshold_bls::deal(&mut FE2::new_random(), 1, 2).unwrap();
+        let mut party = Sign::new(msg.to_vec(), 1, 2, keys[0].clone()).unwrap();
+        let result = party.handle_incoming(Msg {
+            sender: 2,
+            receiver: Some(1),
+            body: ProtocolMessage(
+                PROTOCOL_VERSION,
+                M::Round1((keys[1].index(), keys[1].partial_sign(msg))),
+            ),
+        });
+        assert!(matches!(
+            result,
+            Err(Error::UnexpectedMessageKind {
+                round: 1,
+                expected_broadcast: true
+            })
+        ));
+    }
+
+    #[test]
+    fn commit_reveal_rejects_oversized_commitment() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut party1 =
+            Sign::with_commit_reveal(msg.to_vec(), 1, 2, parties_keys[0].clone()).unwrap();
+        let mut party2 =
+            Sign::with_commit_reveal(msg.to_vec(), 2, 2, parties_keys[1].clone()).unwrap();
+        party2.proceed().unwrap();
+        let mut commit2 = party2.message_queue().pop().unwrap();
+        match &mut commit2.body {
+            ProtocolMessage(_, M::Commit(com)) => {
+                *com = BigInt::from(&[0xffu8; party_i::COMMITMENT_BYTES_LEN + 1][..])
+            }
+            _ => panic!("expected commitment"),
+        }
+        assert!(matches!(
+            party1.handle_incoming(commit2),
+            Err(Error::OversizedCommitment { party: 2 })
+        ));
+    }
+
+    #[test]
+    fn sign_reports_quorum_lost() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut party1 = Sign::new(msg.to_vec(), 1, 2, parties_keys[0].clone()).unwrap();
+        let mut party2 = Sign::new(msg.to_vec(), 2, 2, parties_keys[1].clone()).unwrap();
+        party1.proceed().unwrap();
+        party2.proceed().unwrap();
+
+        // Party 1 sends a partial signature that doesn't pass verification
+        let mut msg1 = party1.message_queue().pop().unwrap();
+        match &mut msg1.body {
+            ProtocolMessage(_, M::Round1((_, partial_sig))) => {
+                partial_sig.sigma_i = GE1::generator()
+            }
+            _ => panic!("expected partial signature"),
+        }
+
+        party2.handle_incoming(msg1).unwrap();
+        match party2.proceed() {
+            Err(Error::ProceedRound(ProceedError::QuorumLost { got: 1, needed: 2 })) => (),
+            result => panic!("expected QuorumLost, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_signing() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut party1 = Sign::new(msg.to_vec(), 1, 2, parties_keys[0].clone()).unwrap();
+        let mut party2 = Sign::new(msg.to_vec(), 2, 2, parties_keys[1].clone()).unwrap();
+        party1.record();
+        party1.proceed().unwrap();
+        party2.proceed().unwrap();
+        party1
+            .handle_incoming(party2.message_queue().pop().unwrap())
+            .unwrap();
+        party1.proceed().unwrap();
+        let output = party1.pick_output().unwrap().unwrap();
+
+        let events = party1.events().to_vec();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[1], Event::Incoming(_)));
+
+        let mut replayed = Sign::new(msg.to_vec(), 1, 2, parties_keys[0].clone())
+            .unwrap()
+            .replay(&events)
+            .unwrap();
+        assert_eq!(replayed.pick_output().unwrap().unwrap(), output);
+        assert_eq!(format!("{:?}", replayed.events()), format!("{:?}", events));
+    }
+
+    #[test]
+    fn sign_reports_conflicting_partials() {
+        use crate::threshold_bls::state_machine::SignerIndex;
+
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        // Parties 2 and 3 hold the same key share
+        let keys = [&parties_keys[0], &parties_keys[1], &parties_keys[1]];
+        let mut parties: Vec<_> = (1..)
+            .zip(keys.iter())
+            .map(|(i, key)| Sign::new(msg.to_vec(), i, 3, (*key).clone()).unwrap())
+            .collect();
+        for party in &mut parties {
+            party.proceed().unwrap();
+        }
+        let sent: Vec<_> = parties[1..]
+            .iter_mut()
+            .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+            .collect();
+        for msg in sent {
+            parties[0].handle_incoming(msg).unwrap();
+        }
+        match parties[0].proceed() {
+            Err(Error::ProceedRound(ProceedError::ConflictingPartials { index, partials })) => {
+                assert_eq!(index, KeygenIndex(2));
+                assert_eq!(partials[0].0, SignerIndex(2));
+                assert_eq!(partials[1].0, SignerIndex(3));
+                assert_eq!(partials[0].1.sigma_i, partials[1].1.sigma_i);
+            }
+            result => panic!("expected ConflictingPartials, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sign_blames_party_copying_partial_under_own_index() {
+        use crate::threshold_bls::state_machine::SignerIndex;
+
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut parties: Vec<_> = (1..)
+            .zip(&parties_keys)
+            .map(|(i, key)| Sign::new(msg.to_vec(), i, 3, key.clone()).unwrap())
+            .collect();
+        let mut msgs = vec![];
+        for party in &mut parties {
+            party.proceed().unwrap();
+            msgs.push(party.message_queue().pop().unwrap());
+        }
+
+        // Party 3 rebroadcasts partial signature of party 2 under its own index
+        let mut copied = msgs[1].clone();
+        copied.sender = 3;
+        match &mut copied.body {
+            ProtocolMessage(_, M::Round1((index, _))) => *index = KeygenIndex(3),
+            _ => panic!("expected partial signature"),
+        }
+        parties[0].handle_incoming(msgs[1].clone()).unwrap();
+        parties[0].handle_incoming(copied).unwrap();
+        match parties[0].proceed() {
+            Err(Error::ProceedRound(ProceedError::PartySentMislabeledPartial {
+                who: SignerIndex(3),
+                claimed_index: KeygenIndex(2),
+            })) => (),
+            result => panic!("expected PartySentMislabeledPartial, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sign_with_relabeled_keys() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mapping: HashMap<u16, u16> = vec![(1, 3), (2, 1), (3, 2)].into_iter().collect();
+        let relabeled: Vec<_> = parties_keys
+            .iter()
+            .map(|key| key.relabel(&mapping).unwrap())
+            .collect();
+        assert_eq!(relabeled[0].i, 3);
+        assert_eq!(
+            relabeled[0].share_index(KeygenIndex(3)),
+            Some(KeygenIndex(1))
+        );
+        assert_eq!(
+            relabeled[0].share_index(KeygenIndex(1)),
+            Some(KeygenIndex(2))
+        );
+        assert_eq!(relabeled[0].vk_vec, parties_keys[0].vk_vec);
+        assert_eq!(
+            relabeled[0].public_params().to_bytes(),
+            parties_keys[0].public_params().to_bytes()
+        );
+
+        let mut sign_simulation = Simulation::new();
+        sign_simulation.add_party(Sign::new(msg.to_vec(), 1, 2, relabeled[0].clone()).unwrap());
+        sign_simulation.add_party(Sign::new(msg.to_vec(), 2, 2, relabeled[2].clone()).unwrap());
+        let sigs = sign_simulation.run().unwrap();
+        assert!(sigs[0]
+            .signature()
+            .verify(msg, &parties_keys[0].public_key()));
+
+        let not_bijection: HashMap<u16, u16> = vec![(1, 1), (2, 1), (3, 2)].into_iter().collect();
+        assert!(matches!(
+            parties_keys[0].relabel(&not_bijection),
+            Err(KeygenError::InvalidRelabeling)
+        ));
+    }
+
+    #[test]
+    fn sign_reports_mislabeled_partial() {
+        use crate::threshold_bls::state_machine::SignerIndex;
+
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut party1 = Sign::new(msg.to_vec(), 1, 2, parties_keys[0].clone()).unwrap();
+        let mut party2 = Sign::new(msg.to_vec(), 2, 2, parties_keys[1].clone()).unwrap();
+        party1.proceed().unwrap();
+        party2.proceed().unwrap();
+
+        // Party 1 claims its share was evaluated at another point
+        let mut msg1 = party1.message_queue().pop().unwrap();
+        match &mut msg1.body {
+            ProtocolMessage(_, M::Round1((_, partial_sig))) => partial_sig.index = KeygenIndex(3),
+            _ => panic!("expected partial signature"),
+        }
+
+        party2.handle_incoming(msg1).unwrap();
+        match party2.proceed() {
+            Err(Error::ProceedRound(ProceedError::PartySentMislabeledPartial {
+                who: SignerIndex(1),
+                claimed_index: KeygenIndex(3),
+            })) => (),
+            result => panic!("expected PartySentMislabeledPartial, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sign_output_verifies_as_basic_bls() {
+        use pairing_plus::bls12_381::G2Affine;
+        use pairing_plus::serdes::SerDes;
+
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=4 {
+            keygen_simulation.add_party(Keygen::new(i, 2, 4).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut sign_simulation = Simulation::new();
+        for (i, key) in (1..).zip(&parties_keys[1..]) {
+            sign_simulation.add_party(Sign::new(msg.to_vec(), i, 3, key.clone()).unwrap());
+        }
+        let sig = *sign_simulation.run().unwrap()[0].signature();
+
+        // Standard verifier only knows serialized group public key and signature
+        let mut pubkey_bytes = vec![];
+        G2Affine::serialize(
+            &parties_keys[0].public_key().get_element(),
+            &mut pubkey_bytes,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            verify_bytes(&pubkey_bytes, msg, &sig.to_bytes(true)),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn early_combine_ignores_invalid_and_late_partials() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut parties: Vec<_> = (1..)
+            .zip(&parties_keys)
+            .map(|(i, key)| Sign::new_early_combine(msg.to_vec(), i, 3, key.clone()).unwrap())
+            .collect();
+        let mut msgs = vec![];
+        for party in &mut parties {
+            party.proceed().unwrap();
+            msgs.push(party.message_queue().pop().unwrap());
+        }
+
+        assert_eq!(parties[0].signatures_remaining(), Some(1));
+
+        // Party 2 sends invalid partial signature, it's ignored
+        let mut invalid = msgs[1].clone();
+        match &mut invalid.body {
+            ProtocolMessage(_, M::Round1((_, partial_sig))) => {
+                partial_sig.sigma_i = GE1::generator();
+            }
+            _ => panic!("expected partial signature"),
+        }
+        parties[0].handle_incoming(invalid).unwrap();
+        assert!(!parties[0].wants_to_proceed());
+        assert_eq!(parties[0].signatures_remaining(), Some(1));
+
+        // Party 3 sends valid partial signature, quorum is reached
+        parties[0].handle_incoming(msgs[2].clone()).unwrap();
+        assert!(parties[0].wants_to_proceed());
+        assert_eq!(parties[0].signatures_remaining(), Some(0));
+        parties[0].proceed().unwrap();
+        assert_eq!(parties[0].signatures_remaining(), None);
+        let sig = *parties[0].pick_output().unwrap().unwrap().signature();
+        assert!(sig.verify(msg, &parties_keys[0].public_key()));
+
+        // Partial signatures arriving after the signer set was frozen are ignored
+        parties[2].handle_incoming(msgs[0].clone()).unwrap();
+        parties[2].proceed().unwrap();
+        parties[2].handle_incoming(msgs[1].clone()).unwrap();
+        let sig2 = *parties[2].pick_output().unwrap().unwrap().signature();
+        assert_eq!(sig, sig2);
+    }
+
+    #[test]
+    fn early_combine_ignores_partials_under_taken_or_foreign_index() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=4 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 4).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut parties: Vec<_> = (1..)
+            .zip(&parties_keys)
+            .map(|(i, key)| Sign::new_early_combine(msg.to_vec(), i, 4, key.clone()).unwrap())
+            .collect();
+        let mut msgs = vec![];
+        for party in &mut parties {
+            party.proceed().unwrap();
+            msgs.push(party.message_queue().pop().unwrap());
+        }
+
+        // Party 2 replays our own partial signature, it's ignored
+        let mut replayed = msgs[0].clone();
+        replayed.sender = 2;
+        parties[0].handle_incoming(replayed).unwrap();
+        assert_eq!(parties[0].signatures_remaining(), Some(1));
+
+        // Party 3 claims its share was evaluated at another point, it's ignored
+        let mut mislabeled = msgs[2].clone();
+        match &mut mislabeled.body {
+            ProtocolMessage(_, M::Round1((_, partial_sig))) => partial_sig.index = KeygenIndex(2),
+            _ => panic!("expected partial signature"),
+        }
+        parties[0].handle_incoming(mislabeled).unwrap();
+        assert_eq!(parties[0].signatures_remaining(), Some(1));
+        assert!(!parties[0].wants_to_proceed());
+
+        parties[0].handle_incoming(msgs[3].clone()).unwrap();
+        parties[0].proceed().unwrap();
+        let sig = *parties[0].pick_output().unwrap().unwrap().signature();
+        assert!(sig.verify(msg, &parties_keys[0].public_key()));
+    }
+
+    #[test]
+    fn signatures_remaining_counts_down() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let mut parties: Vec<_> = (1..)
+            .zip(&parties_keys)
+            .map(|(i, key)| Sign::new(msg.to_vec(), i, 3, key.clone()).unwrap())
+            .collect();
+        let mut msgs = vec![];
+        for party in &mut parties {
+            party.proceed().unwrap();
+            msgs.push(party.message_queue().pop().unwrap());
+        }
+
+        // Regular signing waits for every other signer
+        assert_eq!(parties[0].signatures_remaining(), Some(2));
+        assert_eq!(parties[0].missing_partials(), Some((2, vec![2, 3])));
+        parties[0].handle_incoming(msgs[1].clone()).unwrap();
+        assert_eq!(parties[0].signatures_remaining(), Some(1));
+        assert_eq!(parties[0].missing_partials(), Some((1, vec![3])));
+        parties[0].handle_incoming(msgs[2].clone()).unwrap();
+        parties[0].proceed().unwrap();
+        assert_eq!(parties[0].signatures_remaining(), None);
+        assert_eq!(parties[0].missing_partials(), None);
+        assert!(parties[0].is_finished());
+    }
+
+    #[test]
+    fn blame_reports_missing_partials() {
+        let msg = b"~~ MESSAGE ~~";
+        let keys = crate::threshold_bls::deal(&mut FE2::new_random(), 2, 4).unwrap();
+        let partial = |sender: u16| Msg {
+            sender,
+            receiver: None,
+            body: (
+                keys[usize::from(sender) - 1].index(),
+                keys[usize::from(sender) - 1].partial_sign(msg),
+            ),
+        };
+
+        let mut store = ReceiveFirstValidPartialSigs::all(1, 4);
+        assert_eq!(store.blame(), (3, vec![2, 3, 4]));
+        store.push_msg(partial(3)).unwrap();
+        assert_eq!(store.blame(), (2, vec![2, 4]));
+
+        let mut store = ReceiveFirstValidPartialSigs::first_valid(1, 4, &keys[0], hash_to_g1(msg));
+        assert_eq!(store.blame(), (2, vec![2, 3, 4]));
+        let mut invalid = partial(2);
+        invalid.body.1.sigma_i = GE1::generator();
+        store.push_msg(invalid).unwrap();
+        assert_eq!(store.blame(), (2, vec![3, 4]));
+        store.push_msg(partial(4)).unwrap();
+        assert_eq!(store.blame(), (1, vec![3]));
+        store.push_msg(partial(3)).unwrap();
+        assert_eq!(store.blame(), (0, vec![]));
+    }
+
+    #[test]
+    fn sign_issues_verifiable_receipt() {
+        let msg = b"~~ MESSAGE ~~";
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+
+        let receipt_key = KeyPairG2::new();
+        let long_term_pk = receipt_key.public_key();
+        let mut party =
+            Sign::with_receipt(msg.to_vec(), 1, 2, parties_keys[2].clone(), receipt_key).unwrap();
+        assert!(party.receipt().is_none());
+        party.proceed().unwrap();
+
+        let receipt = party.receipt().unwrap().clone();
+        assert_eq!(receipt.group_key, parties_keys[0].public_key());
+        assert_eq!(receipt.party, KeygenIndex(3));
+        assert_eq!(receipt.message, msg.to_vec());
+        assert!(receipt.verify(&long_term_pk));
+        assert!(receipt
+            .partial
+            .verify(&hash_to_g1(msg), &parties_keys[0].vk_vec[2]));
+
+        // Receipt is bound to the message, to the group, and to the long-term key
+        let mut forged = receipt.clone();
+        forged.message = b"another message".to_vec();
+        assert!(!forged.verify(&long_term_pk));
+        let mut forged = receipt.clone();
+        forged.group_key = KeyPairG2::new().public_key();
+        assert!(!forged.verify(&long_term_pk));
+        assert!(!receipt.verify(&KeyPairG2::new().public_key()));
+
+        // Malformed receipt is rejected rather than causing a panic
+        let mut malformed = receipt.clone();
+        malformed.partial.ddh_proof.z = curv::BigInt::from(1u32) << 256;
+        assert!(!malformed.verify(&long_term_pk));
+
+        // Party without receipt key doesn't issue receipts
+        let mut party = Sign::new(msg.to_vec(), 2, 2, parties_keys[0].clone()).unwrap();
+        party.proceed().unwrap();
+        assert!(party.receipt().is_none());
+    }
+
+    #[test]
+    fn expected_messages_matches_round_store() {
+        let keys = crate::threshold_bls::deal(&mut FE2::new_random(), 1, 4).unwrap();
+        let party = Sign::new(b"~~ MESSAGE ~~".to_vec(), 1, 3, keys[0].clone()).unwrap();
+        assert_eq!(party.expected_messages(0), None);
+        assert_eq!(
+            party.expected_messages(1),
+            Some(party.msgs1.as_ref().unwrap().messages_total())
+        );
+        assert_eq!(party.expected_messages(2), None);
+    }
+
     #[test]
     fn simulate_sign_t1_n2() {
         let msg = b"~~ MESSAGE ~~";
         simulate_sign(&msg[..], &[1, 2], 1, 2);
     }
 
+    #[test]
+    fn sign_on_point() {
+        let mut keygen_simulation = Simulation::new();
+        for i in 1..=3 {
+            keygen_simulation.add_party(Keygen::new(i, 1, 3).unwrap());
+        }
+        let parties_keys = keygen_simulation.run().unwrap();
+        let public_key = parties_keys[0].public_key();
+
+        // Input of VRF mapped to the curve by external means
+        let point = hash_to_g1(b"VRF input");
+        let mut sign_simulation = Simulation::new();
+        for (i, key) in (1..).zip(&parties_keys[1..]) {
+            sign_simulation.add_party(Sign::new_on_point(point, i, 2, key.clone()).unwrap());
+        }
+        let outputs = sign_simulation.run().unwrap();
+
+        assert!(outputs.iter().all(|output| output == &outputs[0]));
+        assert_eq!(*outputs[0].hash_point(), point);
+        assert!(outputs[0].verify(&public_key));
+        // Point is not hashed once more
+        let mut point_bytes = vec![];
+        G1Affine::serialize(&point.get_element(), &mut point_bytes, true).unwrap();
+        assert!(!outputs[0].signature().verify(&point_bytes, &public_key));
+
+        let infinity = GE1::from(G1Affine::zero());
+        assert!(matches!(
+            Sign::new_on_point(infinity, 1, 2, parties_keys[0].clone()),
+            Err(Error::InvalidMessagePoint)
+        ));
+
+        // Point on curve which is not in the prime order subgroup
+        let outside_subgroup = (1..=255u8)
+            .find_map(|x| {
+                let mut encoded = G1Compressed::empty();
+                encoded.as_mut()[0] = 0x80;
+                encoded.as_mut()[47] = x;
+                match encoded.into_affine() {
+                    Err(GroupDecodingError::NotInSubgroup) => encoded.into_affine_unchecked().ok(),
+                    _ => None,
+                }
+            })
+            .expect("point out of subgroup must be found");
+        assert!(matches!(
+            Sign::new_on_point(GE1::from(outside_subgroup), 1, 2, parties_keys[0].clone()),
+            Err(Error::InvalidMessagePoint)
+        ));
+    }
+
+    #[test]
+    fn simulate_sign_empty_message() {
+        simulate_sign(&[], &[1, 3], 1, 3);
+    }
+
     #[test]
     fn simulate_sign_t1_n3() {
         let msg = b"~~ MESSAGE ~~";
@@ -393,4 +1502,12 @@ mod test {
         let msg = b"~~ MESSAGE ~~";
         simulate_sign(&msg[..], &[1, 2, 3], 2, 3);
     }
+
+    #[test]
+    fn simulate_sign_with_non_contiguous_signers() {
+        let msg = b"~~ MESSAGE ~~";
+        simulate_sign(&msg[..], &[1, 3, 5], 2, 5);
+        // More signers than needed, first t+1 of them are interpolated
+        simulate_sign(&msg[..], &[2, 4, 5], 1, 5);
+    }
 }
@@ -1,16 +1,32 @@
+use curv::arithmetic::traits::{Converter, Samplable};
+use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
+use curv::cryptographic_primitives::commitments::traits::Commitment;
 use curv::elliptic::curves::bls12_381::g1::GE as GE1;
+use curv::elliptic::curves::bls12_381::g2::GE as GE2;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use pairing_plus::bls12_381::{G1Affine, G2Affine};
+use pairing_plus::serdes::SerDes;
 use round_based::containers::push::Push;
-use round_based::containers::{self, BroadcastMsgs, Store};
+use round_based::containers::{self, BroadcastMsgs, MessageStore, Store, StoreErr};
 use round_based::Msg;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
-use crate::basic_bls::BLSSignature;
-use crate::threshold_bls::party_i;
-use crate::threshold_bls::state_machine::keygen::LocalKey;
+use crate::basic_bls::{BLSSignature, KeyPairG2};
+use crate::threshold_bls::party_i::{self, CombineError};
+use crate::threshold_bls::state_machine::keygen::{Error as KeygenError, LocalKey};
+use crate::threshold_bls::state_machine::{KeygenIndex, SignerIndex};
+use crate::ParseError;
 
 pub struct Round0 {
     pub key: LocalKey,
     pub message: Vec<u8>,
+    /// Point on G1 being signed, `hash_to_g1(message)` unless message is a point itself
+    pub H_x: GE1,
+    pub receipt_key: Option<KeyPairG2>,
 
     pub i: u16,
     pub n: u16,
@@ -19,19 +35,55 @@ pub struct Round0 {
 impl Round0 {
     pub fn proceed<O>(self, mut output: O) -> Result<Round1>
     where
-        O: Push<Msg<(u16, party_i::PartialSignature)>>,
+        O: Push<Msg<(KeygenIndex, party_i::PartialSignature)>>,
     {
-        let (partial_sig, H_x) = self.key.shared_keys.partial_sign(&self.message);
+        let round1 = self.sign();
         output.push(Msg {
-            sender: self.i,
+            sender: round1.i,
+            receiver: None,
+            body: (round1.key.index(), round1.partial_sig.clone()),
+        });
+        Ok(round1)
+    }
+
+    /// Same as [proceed](Self::proceed), but broadcasts commitment to the partial signature
+    /// instead of partial signature itself
+    pub fn proceed_with_commitment<O>(self, mut output: O) -> Result<CommitRound>
+    where
+        O: Push<Msg<BigInt>>,
+    {
+        let round1 = self.sign();
+        let blind_factor = BigInt::sample(party_i::SECURITY);
+        let com = commit_to_partial(round1.key.index(), &round1.partial_sig, &blind_factor);
+        output.push(Msg {
+            sender: round1.i,
             receiver: None,
-            body: (self.key.i, partial_sig.clone()),
+            body: com,
         });
-        Ok(Round1 {
+        Ok(CommitRound {
+            round1,
+            blind_factor,
+        })
+    }
+
+    fn sign(self) -> Round1 {
+        let partial_sig = party_i::PartialSignature::sign_point(&self.key.shared_keys, &self.H_x);
+        let receipt = self.receipt_key.map(|receipt_key| {
+            SignatureReceipt::new(
+                &receipt_key,
+                self.key.public_key(),
+                self.key.index(),
+                self.message.clone(),
+                partial_sig.clone(),
+            )
+        });
+        Round1 {
             key: self.key,
-            message: H_x,
+            message: self.H_x,
             partial_sig,
-        })
+            i: self.i,
+            receipt,
+        }
     }
     pub fn is_expensive(&self) -> bool {
         true
@@ -43,36 +95,154 @@ pub struct Round1 {
     message: GE1,
 
     partial_sig: party_i::PartialSignature,
+    i: u16,
+    pub receipt: Option<SignatureReceipt>,
 }
 
 impl Round1 {
     pub fn proceed(
         self,
-        input: BroadcastMsgs<(u16, party_i::PartialSignature)>,
-    ) -> Result<(GE1, BLSSignature)> {
-        let (indexes, sigs): (Vec<_>, Vec<_>) = input
-            .into_vec_including_me((self.key.i, self.partial_sig))
-            .into_iter()
-            .unzip();
-
-        let mut vk_vec = vec![];
-        for (party_i, &keygen_i) in indexes.iter().enumerate() {
-            if keygen_i == 0 || keygen_i > self.key.n {
-                return Err(ProceedError::PartySentOutOfRangeIndex {
-                    who: party_i as u16 + 1,
-                    claimed_index: keygen_i,
+        input: Vec<Msg<(KeygenIndex, party_i::PartialSignature)>>,
+    ) -> Result<SignedMessage> {
+        let own = Msg {
+            sender: self.i,
+            receiver: None,
+            body: (self.key.index(), self.partial_sig),
+        };
+
+        let msgs: Vec<_> = std::iter::once(own).chain(input).collect();
+        check_repeated_indexes(&self.key, &self.message, &msgs)?;
+        let signers: Vec<_> = msgs.iter().map(|msg| msg.body.0).collect();
+        let vk_vec = self
+            .key
+            .verification_keys_for(&signers)
+            .map_err(|err| match err {
+                KeygenError::InvalidKeygenIndex { index } => {
+                    // Index is either out of range or repeated, blame the last party claiming it
+                    let k = signers.iter().rposition(|&s| s == index).unwrap_or(0);
+                    ProceedError::PartySentOutOfRangeIndex {
+                        who: SignerIndex(msgs[k].sender),
+                        claimed_index: index,
+                    }
+                }
+                _ => unreachable!("verification_keys_for fails only on invalid index"),
+            })?;
+
+        // Partial signatures are interpolated at evaluation points of signers' shares, which
+        // might differ from their indexes if keys were relabeled
+        let mut indexes = Vec::with_capacity(msgs.len());
+        for msg in &msgs {
+            let point = self
+                .key
+                .share_index(msg.body.0)
+                .expect("index is checked by verification_keys_for");
+            if msg.body.1.index != point {
+                return Err(ProceedError::PartySentMislabeledPartial {
+                    who: SignerIndex(msg.sender),
+                    claimed_index: msg.body.1.index,
                 });
             }
-            vk_vec.push(self.key.vk_vec[usize::from(keygen_i) - 1])
+            indexes.push(point);
         }
-
-        let indexes: Vec<_> = indexes.into_iter().map(|i| usize::from(i) - 1).collect();
+        let sigs: Vec<_> = msgs.into_iter().map(|msg| msg.body.1).collect();
         let sig = self
             .key
             .shared_keys
-            .combine(&vk_vec, &sigs, self.message, &indexes)
-            .map_err(ProceedError::PartialSignatureVerification)?;
-        Ok((self.message, sig))
+            .combine_verbose(&vk_vec, &sigs, self.message, &indexes)
+            .map_err(|err| match err {
+                CombineError::InvalidPartials(invalid)
+                    if sigs.len() - invalid.len() < usize::from(self.key.t) + 1 =>
+                {
+                    ProceedError::QuorumLost {
+                        got: (sigs.len() - invalid.len()) as u16,
+                        needed: self.key.t + 1,
+                    }
+                }
+                err => ProceedError::PartialSignatureVerification(err.into()),
+            })?;
+        Ok(SignedMessage {
+            hash_point: self.message,
+            signature: sig,
+        })
+    }
+    pub fn is_expensive(&self) -> bool {
+        true
+    }
+    pub fn expects_messages(i: u16, n: u16) -> ReceiveFirstValidPartialSigs {
+        ReceiveFirstValidPartialSigs::all(i, n)
+    }
+}
+
+/// Commitment phase of commit-reveal signing: party has broadcasted commitment to its partial
+/// signature and waits for commitments of other parties
+///
+/// Partial signatures are revealed only once every party is committed, so a rushing adversary
+/// can't choose its partial signature after seeing others' ones.
+pub struct CommitRound {
+    round1: Round1,
+    blind_factor: BigInt,
+}
+
+impl CommitRound {
+    pub fn receipt(&self) -> Option<&SignatureReceipt> {
+        self.round1.receipt.as_ref()
+    }
+    pub fn proceed<O>(self, input: BroadcastMsgs<BigInt>, mut output: O) -> Result<RevealRound>
+    where
+        O: Push<Msg<(KeygenIndex, party_i::PartialSignature, BigInt)>>,
+    {
+        output.push(Msg {
+            sender: self.round1.i,
+            receiver: None,
+            body: (
+                self.round1.key.index(),
+                self.round1.partial_sig.clone(),
+                self.blind_factor,
+            ),
+        });
+        Ok(RevealRound {
+            round1: self.round1,
+            commitments: input.into_vec(),
+        })
+    }
+    pub fn is_expensive(&self) -> bool {
+        false
+    }
+    pub fn expects_messages(i: u16, n: u16) -> Store<BroadcastMsgs<BigInt>> {
+        containers::BroadcastMsgsStore::new(i, n)
+    }
+}
+
+/// Reveal phase of commit-reveal signing: every revealed partial signature is checked against
+/// commitment the party sent at previous round, then signature is combined as in [Round1]
+pub struct RevealRound {
+    round1: Round1,
+    commitments: Vec<BigInt>,
+}
+
+impl RevealRound {
+    pub fn proceed(
+        self,
+        input: BroadcastMsgs<(KeygenIndex, party_i::PartialSignature, BigInt)>,
+    ) -> Result<SignedMessage> {
+        let i = self.round1.i;
+        let senders = (1..).filter(|&j| j != i);
+        let mut partials = Vec::with_capacity(self.commitments.len());
+        for ((sender, (keygen_i, partial_sig, blind_factor)), com) in
+            senders.zip(input.into_vec()).zip(&self.commitments)
+        {
+            if commit_to_partial(keygen_i, &partial_sig, &blind_factor) != *com {
+                return Err(ProceedError::PartyRevealedUncommittedPartial {
+                    who: SignerIndex(sender),
+                });
+            }
+            partials.push(Msg {
+                sender,
+                receiver: None,
+                body: (keygen_i, partial_sig),
+            });
+        }
+        self.round1.proceed(partials)
     }
     pub fn is_expensive(&self) -> bool {
         true
@@ -80,11 +250,364 @@ impl Round1 {
     pub fn expects_messages(
         i: u16,
         n: u16,
-    ) -> Store<BroadcastMsgs<(u16, party_i::PartialSignature)>> {
+    ) -> Store<BroadcastMsgs<(KeygenIndex, party_i::PartialSignature, BigInt)>> {
         containers::BroadcastMsgsStore::new(i, n)
     }
 }
 
+/// Looks for parties claiming the same keygen index
+///
+/// Outcome doesn't depend on the order in which messages were received: if partial signatures
+/// of both parties are valid, returns [ProceedError::ConflictingPartials], otherwise blames the
+/// party with invalid partial signature (or the one with greater signer index if both are
+/// invalid). Out of range indexes are left to be reported by the caller, as well as parties
+/// claiming distinct indexes but sending partial signatures at the same point: at most one of
+/// them signs at its own share index, so the other is blamed for mislabeled partial.
+fn check_repeated_indexes(
+    key: &LocalKey,
+    message: &GE1,
+    msgs: &[Msg<(KeygenIndex, party_i::PartialSignature)>],
+) -> Result<()> {
+    for (k, a) in msgs.iter().enumerate() {
+        for b in &msgs[k + 1..] {
+            if a.body.0 != b.body.0 {
+                continue;
+            }
+            let vk = match key.verification_key(a.body.0) {
+                Some(vk) => vk,
+                None => continue,
+            };
+            let (a, b) = if a.sender < b.sender { (a, b) } else { (b, a) };
+            let blame = |msg: &Msg<(KeygenIndex, party_i::PartialSignature)>| {
+                ProceedError::PartySentOutOfRangeIndex {
+                    who: SignerIndex(msg.sender),
+                    claimed_index: msg.body.0,
+                }
+            };
+            return Err(
+                match (a.body.1.verify(message, vk), b.body.1.verify(message, vk)) {
+                    (true, true) => ProceedError::ConflictingPartials {
+                        index: a.body.0,
+                        partials: Box::new([
+                            (SignerIndex(a.sender), a.body.1.clone()),
+                            (SignerIndex(b.sender), b.body.1.clone()),
+                        ]),
+                    },
+                    (false, true) => blame(a),
+                    (_, false) => blame(b),
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Commits to partial signature along with keygen index of the signer
+///
+/// Partial signature is received from the network and not verified yet, so it's encoded
+/// without [to_bytes](party_i::PartialSignature::to_bytes) which panics on out-of-range values.
+fn commit_to_partial(
+    keygen_i: KeygenIndex,
+    partial_sig: &party_i::PartialSignature,
+    blind_factor: &BigInt,
+) -> BigInt {
+    let mut bytes = keygen_i.0.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&partial_sig.index.0.to_be_bytes());
+    for point in &[&partial_sig.sigma_i, &partial_sig.ddh_proof.a1] {
+        G1Affine::serialize(&point.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+    }
+    G2Affine::serialize(&partial_sig.ddh_proof.a2.get_element(), &mut bytes, true)
+        .expect("serialize to vec should always succeed");
+    let z = BigInt::to_vec(&partial_sig.ddh_proof.z);
+    bytes.extend_from_slice(&(z.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(&z);
+    HashCommitment::create_commitment_with_user_defined_randomness(
+        &BigInt::from(&bytes[..]),
+        blind_factor,
+    )
+}
+
+/// Output of signing protocol: threshold signature along with the message it signs
+///
+/// Message is represented by its hash point on G1, i.e. [hash_to_g1](crate::basic_bls::hash_to_g1)
+/// of the signed bytes. Protocols that aggregate threshold signatures of many messages (see
+/// [aggregate_signatures](crate::threshold_bls::aggregate_signatures)) may work on the hash
+/// points directly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignedMessage {
+    hash_point: GE1,
+    signature: BLSSignature,
+}
+
+impl SignedMessage {
+    /// Hash of the signed message on G1
+    pub fn hash_point(&self) -> &GE1 {
+        &self.hash_point
+    }
+
+    /// Signature of the message under group public key
+    pub fn signature(&self) -> &BLSSignature {
+        &self.signature
+    }
+
+    /// Decomposes into hash point and signature
+    pub fn into_parts(self) -> (GE1, BLSSignature) {
+        (self.hash_point, self.signature)
+    }
+
+    /// Verifies signature of the hash point under group public key
+    ///
+    /// Works for messages signed via [Sign::new_on_point](super::Sign::new_on_point) as well,
+    /// whose hash point is the signed point itself.
+    pub fn verify(&self, public_key: &GE2) -> bool {
+        self.signature.verify_point(&self.hash_point, public_key)
+    }
+}
+
+/// Receipt attesting that party contributed partial signature of a message
+///
+/// Signed by party's long-term key (basic BLS key, independent from the threshold key), so the
+/// party can't deny its participation later. Obtained via [Sign::receipt](super::Sign::receipt).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignatureReceipt {
+    /// Public key of the group the party signed on behalf of
+    pub group_key: GE2,
+    /// Index of the party at keygen
+    pub party: KeygenIndex,
+    /// Message being signed
+    pub message: Vec<u8>,
+    /// Partial signature contributed by the party
+    pub partial: party_i::PartialSignature,
+    /// Unix time (in seconds) the receipt was issued at
+    pub timestamp: u64,
+    /// Signature of the receipt under party's long-term key
+    pub signature: GE1,
+}
+
+impl SignatureReceipt {
+    fn new(
+        key: &KeyPairG2,
+        group_key: GE2,
+        party: KeygenIndex,
+        message: Vec<u8>,
+        partial: party_i::PartialSignature,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_secs())
+            .unwrap_or(0);
+        let mut receipt = SignatureReceipt {
+            group_key,
+            party,
+            message,
+            partial,
+            timestamp,
+            signature: GE1::generator(),
+        };
+        let signed_bytes = receipt
+            .signed_bytes()
+            .expect("own partial signature is always serializable");
+        receipt.signature = BLSSignature::sign(&signed_bytes, key).sigma;
+        receipt
+    }
+
+    /// Checks receipt signature under party's long-term public key
+    ///
+    /// Returns `false` if receipt is malformed. Note that it doesn't check partial signature
+    /// itself, use
+    /// [PartialSignature::verify](party_i::PartialSignature::verify) for that.
+    pub fn verify(&self, long_term_pk: &GE2) -> bool {
+        let signed_bytes = match self.signed_bytes() {
+            Ok(bytes) => bytes,
+            Err(_) => return false,
+        };
+        BLSSignature {
+            sigma: self.signature,
+        }
+        .verify(&signed_bytes, long_term_pk)
+    }
+
+    /// Serializes every receipt field except for the signature
+    ///
+    /// Fails if partial signature can't be serialized, i.e. receipt is malformed.
+    fn signed_bytes(&self) -> Result<Vec<u8>, ParseError> {
+        let mut bytes = b"MULTI_PARTY_BLS_RECEIPT_".to_vec();
+        G2Affine::serialize(&self.group_key.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        bytes.extend_from_slice(&self.party.0.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&(self.message.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.message);
+        bytes.extend_from_slice(&self.partial.index.0.to_be_bytes());
+        G1Affine::serialize(&self.partial.sigma_i.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        bytes.extend_from_slice(&self.partial.ddh_proof.to_bytes()?);
+        Ok(bytes)
+    }
+}
+
+/// Collects partial signatures sent at round 1
+///
+/// By default, waits for a partial signature from every party. In early combine mode, every
+/// partial signature is verified once it's received, and the store stops accepting messages
+/// as soon as it collected `threshold` valid partials (plus our own one gives `threshold+1`).
+/// From that moment the signer set is frozen: invalid partials and partials that arrive later
+/// are ignored.
+pub struct ReceiveFirstValidPartialSigs {
+    i: u16,
+    n: u16,
+    store: Store<BroadcastMsgs<(KeygenIndex, party_i::PartialSignature)>>,
+    quorum: Option<Quorum>,
+}
+
+struct Quorum {
+    message: GE1,
+    /// Index of local party, its partial signature is not received from the network
+    own: KeygenIndex,
+    /// Share index and verification key of every party, ordered by party index
+    shares: Vec<(KeygenIndex, GE2)>,
+    needed: usize,
+    valid: Vec<Msg<(KeygenIndex, party_i::PartialSignature)>>,
+}
+
+impl ReceiveFirstValidPartialSigs {
+    /// Waits for partial signatures from all the parties
+    pub fn all(i: u16, n: u16) -> Self {
+        Self {
+            i,
+            n,
+            store: containers::BroadcastMsgsStore::new(i, n),
+            quorum: None,
+        }
+    }
+
+    /// Waits for the first `threshold` valid partial signatures of message `H_x`
+    pub fn first_valid(i: u16, n: u16, key: &LocalKey, H_x: GE1) -> Self {
+        let shares = (1..=key.n)
+            .map(KeygenIndex)
+            .filter_map(|j| Some((key.share_index(j)?, *key.verification_key(j)?)))
+            .collect();
+        Self {
+            quorum: Some(Quorum {
+                message: H_x,
+                own: key.index(),
+                shares,
+                needed: usize::from(key.t),
+                valid: vec![],
+            }),
+            ..Self::all(i, n)
+        }
+    }
+
+    pub fn push_msg(
+        &mut self,
+        msg: Msg<(KeygenIndex, party_i::PartialSignature)>,
+    ) -> Result<(), StoreErr> {
+        let quorum = match &mut self.quorum {
+            Some(quorum) => quorum,
+            None => return self.store.push_msg(msg),
+        };
+        if quorum.valid.len() >= quorum.needed {
+            // Signer set is frozen
+            return Ok(());
+        }
+        self.store.push_msg(msg.clone())?;
+
+        // Partial is accepted only if the party claims index that is neither ours nor claimed
+        // by already accepted party, and signs with the share held under that index
+        let (keygen_i, sig) = &msg.body;
+        let is_valid = match keygen_i.position(quorum.shares.len() as u16) {
+            Some(position) => {
+                let (point, vk) = &quorum.shares[position];
+                *keygen_i != quorum.own
+                    && quorum
+                        .valid
+                        .iter()
+                        .all(|accepted| accepted.body.0 != *keygen_i)
+                    && sig.index == *point
+                    && sig.verify(&quorum.message, vk)
+            }
+            None => false,
+        };
+        if is_valid {
+            quorum.valid.push(msg)
+        }
+        Ok(())
+    }
+
+    pub fn wants_more(&self) -> bool {
+        match &self.quorum {
+            Some(quorum) if quorum.valid.len() >= quorum.needed => false,
+            _ => self.store.wants_more(),
+        }
+    }
+
+    /// Number of partial signatures still to be received before [wants_more](Self::wants_more)
+    /// turns `false`
+    ///
+    /// Invalid partial signatures received in `first_valid` mode don't count.
+    pub fn remaining(&self) -> usize {
+        match &self.quorum {
+            Some(quorum) => quorum.needed.saturating_sub(quorum.valid.len()),
+            None => self.store.messages_total() - self.store.messages_received(),
+        }
+    }
+
+    /// Returns received partial signatures (excluding our own one)
+    pub fn finish(self) -> Result<Vec<Msg<(KeygenIndex, party_i::PartialSignature)>>, StoreErr> {
+        match self.quorum {
+            Some(quorum) if quorum.valid.len() >= quorum.needed => Ok(quorum.valid),
+            Some(quorum) => {
+                // Every party sent a partial signature, but too few of them are valid. Round1
+                // will report that quorum is lost.
+                self.store.finish()?;
+                Ok(quorum.valid)
+            }
+            None => {
+                let i = self.i;
+                let senders = (1..=self.n).filter(|&j| j != i);
+                Ok(senders
+                    .zip(self.store.finish()?.into_vec())
+                    .map(|(sender, body)| Msg {
+                        sender,
+                        receiver: None,
+                        body,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Returns how many more messages are needed to proceed, and indexes of parties who haven't
+    /// sent a message yet
+    ///
+    /// In early combine mode, only valid partial signatures are counted, so the number of
+    /// needed messages can exceed the number of non-responders if some parties sent invalid
+    /// partial signatures.
+    pub fn blame(&self) -> (u16, Vec<u16>) {
+        let (left, non_responders) = self.store.blame();
+        match &self.quorum {
+            Some(quorum) => {
+                let left = quorum.needed.saturating_sub(quorum.valid.len());
+                (u16::try_from(left).unwrap_or(u16::MAX), non_responders)
+            }
+            None => (left, non_responders),
+        }
+    }
+
+    pub fn messages_received(&self) -> usize {
+        self.store.messages_received()
+    }
+
+    pub fn messages_total(&self) -> usize {
+        match &self.quorum {
+            Some(quorum) => quorum.needed,
+            None => self.store.messages_total(),
+        }
+    }
+}
+
 // Errors
 
 /// Proceeding protocol error
@@ -94,13 +617,49 @@ impl Round1 {
 #[derive(Debug, Error)]
 pub enum ProceedError {
     /// Every party needs to say which index it was using at keygen. This error is raised if
-    /// `index == 0 || index > n` where n is a number of parties holding a key.
+    /// `index == 0 || index > n` where n is a number of parties holding a key, or if the index
+    /// was already claimed by another party.
     #[error(
-        "party {who} claimed its index at keygen was {claimed_index} which is not in range [1;n]"
+        "party {who} claimed its index at keygen was {claimed_index} which is not in range [1;n] \
+         or repeated"
     )]
-    PartySentOutOfRangeIndex { who: u16, claimed_index: u16 },
+    PartySentOutOfRangeIndex {
+        who: SignerIndex,
+        claimed_index: KeygenIndex,
+    },
+    /// Party sent partial signature claiming evaluation point other than keygen index of its
+    /// [share](crate::threshold_bls::state_machine::keygen::LocalKey::share_index)
+    #[error("party {who} sent partial signature at index {claimed_index} which isn't its share")]
+    PartySentMislabeledPartial {
+        who: SignerIndex,
+        claimed_index: KeygenIndex,
+    },
     #[error("partial signatures verification: {0:?}")]
     PartialSignatureVerification(crate::Error),
+    /// In commit-reveal mode, party revealed partial signature that doesn't match the
+    /// commitment it sent before
+    #[error("party {who} revealed partial signature that doesn't match its commitment")]
+    PartyRevealedUncommittedPartial { who: SignerIndex },
+    /// Two parties claimed the same keygen index and both sent valid partial signatures
+    ///
+    /// Partial signatures are broadcasted, so one of the parties might have simply copied the
+    /// partial signature of the other. The error doesn't tell which of them holds the share,
+    /// and must not be treated as evidence of equivocation. Carries signer indexes of both
+    /// parties along with their partial signatures (ordered by signer index), so the caller can
+    /// exclude one of them and restart signing.
+    #[error(
+        "parties {} and {} both signed with key share {index}",
+        .partials[0].0,
+        .partials[1].0
+    )]
+    ConflictingPartials {
+        index: KeygenIndex,
+        partials: Box<[(SignerIndex, party_i::PartialSignature); 2]>,
+    },
+    /// Number of valid partial signatures received by the end of the protocol is less than
+    /// `threshold+1`, so signature cannot be produced
+    #[error("only {got} valid partial signatures received, at least {needed} are required")]
+    QuorumLost { got: u16, needed: u16 },
 }
 
 type Result<T> = std::result::Result<T, ProceedError>;
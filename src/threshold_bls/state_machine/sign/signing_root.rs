@@ -0,0 +1,62 @@
+use sha2::{Digest, Sha256};
+
+/// Message committing to an object within a signature domain
+///
+/// Matches `compute_signing_root` of the Ethereum consensus specs: signing root is a hash tree
+/// root of `SigningData { object_root, domain }`, i.e. `SHA256(object_root || domain)`. Pass it
+/// to [Sign::new](super::Sign::new) (it converts into `Vec<u8>`) to threshold-sign an object
+/// for a beacon-chain-style system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SigningRoot([u8; 32]);
+
+impl SigningRoot {
+    /// Computes signing root of `object_root` (e.g. SSZ hash tree root of the object) under
+    /// `domain`
+    pub fn new(domain: [u8; 32], object_root: [u8; 32]) -> Self {
+        let hash = Sha256::new().chain(&object_root).chain(&domain).result();
+        let mut root = [0u8; 32];
+        root.copy_from_slice(&hash);
+        SigningRoot(root)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl From<SigningRoot> for Vec<u8> {
+    fn from(root: SigningRoot) -> Self {
+        root.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn signing_root_of_zeroes() {
+        // Hash tree root of two zero chunks
+        let expected = "f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b";
+        assert_eq!(
+            hex::encode(SigningRoot::new([0; 32], [0; 32]).as_bytes()),
+            expected
+        );
+    }
+
+    #[test]
+    fn object_root_goes_first() {
+        let domain = [1u8; 32];
+        let object_root = [2u8; 32];
+        let mut data = object_root.to_vec();
+        data.extend_from_slice(&domain);
+        let expected = Sha256::digest(&data);
+        let root = SigningRoot::new(domain, object_root);
+        assert_eq!(&root.as_bytes()[..], &expected[..]);
+        assert_ne!(root, SigningRoot::new(object_root, domain));
+    }
+}
@@ -0,0 +1,82 @@
+//! Flushing outgoing messages into asynchronous transport
+
+use std::mem;
+use std::pin::Pin;
+
+use futures::future::poll_fn;
+use futures::{Sink, SinkExt};
+use round_based::{Msg, StateMachine};
+
+/// Sends every queued outgoing message of the state machine into the `sink`
+///
+/// Messages are sent one by one, waiting until the sink is ready to accept the next message,
+/// so a transport applying backpressure (e.g. a bounded channel) is never overfilled: the task
+/// yields while the sink is full instead of buffering unboundedly or blocking. Sink is flushed
+/// once all messages are sent.
+///
+/// If the sink fails while waiting to become ready, the message that was about to be sent and
+/// every message after it are put back into the queue, and the error is returned. A message
+/// the sink rejected after reporting readiness is considered handed over to the sink.
+pub async fn drain_to_sink<SM, S>(sm: &mut SM, sink: &mut S) -> Result<(), S::Error>
+where
+    SM: StateMachine,
+    S: Sink<Msg<SM::MessageBody>> + Unpin,
+{
+    let mut msgs = mem::take(sm.message_queue()).into_iter().peekable();
+    while msgs.peek().is_some() {
+        if let Err(err) = poll_fn(|cx| Pin::new(&mut *sink).poll_ready(cx)).await {
+            sm.message_queue().extend(msgs);
+            return Err(err);
+        }
+        let msg = msgs.next().expect("peeked above");
+        if let Err(err) = Pin::new(&mut *sink).start_send(msg) {
+            sm.message_queue().extend(msgs);
+            return Err(err);
+        }
+    }
+    sink.flush().await
+}
+
+#[cfg(test)]
+mod test {
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use round_based::StateMachine;
+
+    use super::*;
+    use crate::threshold_bls::state_machine::keygen::Keygen;
+
+    #[tokio::test]
+    async fn drain_to_bounded_channel_doesnt_deadlock() {
+        const MESSAGES: usize = 100;
+
+        let mut party = Keygen::new(1, 2, 5).unwrap();
+        party.proceed().unwrap();
+        let msg = party.message_queue().pop().unwrap();
+        for _ in 0..MESSAGES {
+            party.message_queue().push(msg.clone());
+        }
+
+        let (mut tx, rx) = mpsc::channel(1);
+        let receiver = tokio::spawn(rx.take(MESSAGES).collect::<Vec<_>>());
+        drain_to_sink(&mut party, &mut tx).await.unwrap();
+
+        assert!(party.message_queue().is_empty());
+        assert_eq!(receiver.await.unwrap().len(), MESSAGES);
+    }
+
+    #[tokio::test]
+    async fn unsent_messages_are_kept_in_queue() {
+        let mut party = Keygen::new(1, 1, 2).unwrap();
+        party.proceed().unwrap();
+        let msg = party.message_queue().pop().unwrap();
+        for _ in 0..3 {
+            party.message_queue().push(msg.clone());
+        }
+
+        let (mut tx, rx) = mpsc::channel(1);
+        drop(rx);
+        assert!(drain_to_sink(&mut party, &mut tx).await.is_err());
+        assert_eq!(party.message_queue().len(), 3);
+    }
+}
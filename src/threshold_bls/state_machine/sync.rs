@@ -0,0 +1,95 @@
+//! Synchronous protocol execution
+//!
+//! [run_sync] drives a [StateMachine] to completion over a blocking transport, without
+//! requiring an async runtime.
+
+use round_based::{IsCritical, Msg, StateMachine};
+
+/// Executes protocol using blocking `recv` and `send` functions
+///
+/// Every message produced by the state machine is passed to `send`: broadcast messages have
+/// `receiver == None`, and it's up to transport to deliver them to every other party. `recv`
+/// must block until the next message addressed to this party is received. Messages sent by
+/// this party itself and P2P messages addressed to other parties are ignored, so `recv` can be
+/// fed with every message circulating in the room.
+///
+/// Returns protocol output or the first critical error. Non-critical errors of handling
+/// incoming messages are ignored.
+pub fn run_sync<SM, R, S>(mut sm: SM, mut recv: R, mut send: S) -> Result<SM::Output, SM::Err>
+where
+    SM: StateMachine,
+    SM::Err: IsCritical,
+    R: FnMut() -> Msg<SM::MessageBody>,
+    S: FnMut(Msg<SM::MessageBody>),
+{
+    loop {
+        for msg in sm.message_queue().drain(..) {
+            send(msg)
+        }
+
+        if sm.is_finished() {
+            break;
+        }
+        if sm.wants_to_proceed() {
+            sm.proceed()?;
+            continue;
+        }
+
+        let msg = recv();
+        if msg.sender == sm.party_ind() {
+            continue;
+        }
+        if msg.receiver.is_some() && msg.receiver != Some(sm.party_ind()) {
+            continue;
+        }
+        match sm.handle_incoming(msg) {
+            Err(err) if err.is_critical() => return Err(err),
+            Err(_) | Ok(()) => (),
+        }
+    }
+
+    match sm.pick_output() {
+        Some(output) => output,
+        None => unreachable!("state machine is finished, so output must be ready"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::*;
+    use crate::threshold_bls::state_machine::keygen::Keygen;
+
+    #[test]
+    fn run_sync_keygen_over_channels() {
+        let (t, n) = (1, 3);
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::channel()).unzip();
+
+        let parties: Vec<_> = (1..=n)
+            .zip(receivers)
+            .map(|(i, incoming)| {
+                let outgoing = senders.clone();
+                thread::spawn(move || {
+                    let keygen = Keygen::new(i, t, n).unwrap();
+                    run_sync(
+                        keygen,
+                        || incoming.recv().unwrap(),
+                        |msg| {
+                            for party in &outgoing {
+                                party.send(msg.clone()).unwrap()
+                            }
+                        },
+                    )
+                })
+            })
+            .collect();
+
+        let keys: Vec<_> = parties
+            .into_iter()
+            .map(|party| party.join().unwrap().unwrap())
+            .collect();
+        assert!(keys.iter().all(|k| k.public_key() == keys[0].public_key()));
+    }
+}
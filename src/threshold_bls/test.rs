@@ -1,6 +1,8 @@
 use crate::basic_bls::BLSSignature;
 use crate::threshold_bls::party_i::Keys;
 use crate::threshold_bls::party_i::SharedKeys;
+use crate::threshold_bls::party_i::WeightedSharedKeys;
+use crate::threshold_bls::{audit_keygen, AuditError, KeygenIndex, KeygenTranscript};
 use curv::cryptographic_primitives::secret_sharing::feldman_vss::ShamirSecretSharing;
 use curv::elliptic::curves::bls12_381::g2::FE;
 use curv::elliptic::curves::bls12_381::{g1::GE as GE1, g2::GE as GE2};
@@ -49,6 +51,293 @@ fn test_sign_n8_t4_tprime6() {
     sign(&message[..], 4, 8, &signatories[..], None);
 }
 
+#[test]
+fn test_combine_requires_threshold_plus_one_partials() {
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(2, 4);
+    let message = [100, 101, 102, 103];
+
+    let (partial_sigs, H_x): (Vec<_>, Vec<_>) = shared_keys_vec[0..2]
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+    let s = [KeygenIndex(1), KeygenIndex(2)];
+    let result = shared_keys_vec[0].combine(&vk_vec[0..2], &partial_sigs, H_x[0], &s);
+    assert_eq!(result, Err(crate::Error::SigningMisMatchedVectors));
+}
+
+#[test]
+fn test_combine_verbose_reports_invalid_partials() {
+    use crate::threshold_bls::party_i::CombineError;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+
+    let (mut partial_sigs, H_x): (Vec<_>, Vec<_>) = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+    partial_sigs[1].sigma_i = partial_sigs[0].sigma_i;
+
+    let s = [KeygenIndex(1), KeygenIndex(2), KeygenIndex(3)];
+    let result = shared_keys_vec[0].combine_verbose(&vk_vec, &partial_sigs, H_x[0], &s);
+    assert_eq!(
+        result,
+        Err(CombineError::InvalidPartials(vec![KeygenIndex(2)]))
+    );
+}
+
+#[test]
+fn test_combine_map_matches_partials_by_index() {
+    use crate::threshold_bls::party_i::CombineError;
+    use std::collections::HashMap;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+    let (partials, H_x): (Vec<_>, Vec<_>) = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+
+    // Partials come in different order than verification keys
+    let signed: Vec<_> = vec![
+        (KeygenIndex(3), partials[2].clone()),
+        (KeygenIndex(1), partials[0].clone()),
+    ];
+    let vk_map: HashMap<_, _> = vk_vec
+        .iter()
+        .enumerate()
+        .map(|(i, vk)| (KeygenIndex::from_position(i).unwrap(), *vk))
+        .collect();
+    let sig = shared_keys_vec[0]
+        .combine_map(&signed, &vk_map, H_x[0])
+        .unwrap();
+    assert!(shared_keys_vec[0].verify(&sig, &message[..]));
+
+    // Positional combine with mismatched order fails
+    let result = shared_keys_vec[0].combine(
+        &[vk_vec[0], vk_vec[2]],
+        &[partials[2].clone(), partials[0].clone()],
+        H_x[0],
+        &[KeygenIndex(3), KeygenIndex(1)],
+    );
+    assert!(result.is_err());
+
+    // Indexes ordered differently than partial signatures, or repeated
+    for s in &[
+        [KeygenIndex(3), KeygenIndex(1)],
+        [KeygenIndex(1), KeygenIndex(1)],
+    ] {
+        let result = shared_keys_vec[0].combine(
+            &[vk_vec[0], vk_vec[2]],
+            &[partials[0].clone(), partials[2].clone()],
+            H_x[0],
+            s,
+        );
+        assert_eq!(result, Err(crate::Error::SigningMisMatchedVectors));
+    }
+
+    // Valid partial signature of party 3 labeled as partial signature of party 2
+    let mut mislabeled = signed.clone();
+    mislabeled[0].1.index = KeygenIndex(2);
+    let result = shared_keys_vec[0].combine_map(&mislabeled, &vk_map, H_x[0]);
+    assert_eq!(
+        result,
+        Err(CombineError::IndexMismatch {
+            signer: KeygenIndex(3)
+        })
+    );
+
+    // Signer missing from the map
+    let vk_map: HashMap<_, _> = vk_map
+        .into_iter()
+        .filter(|(i, _)| *i != KeygenIndex(3))
+        .collect();
+    let result = shared_keys_vec[0].combine_map(&signed, &vk_map, H_x[0]);
+    assert_eq!(result, Err(CombineError::MisMatchedVectors));
+}
+
+#[test]
+fn test_partial_aggregate_checkpoint() {
+    use crate::threshold_bls::party_i::PartialAggregate;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(2, 4);
+    let message = [100, 101, 102, 103];
+    let (partial_sigs, H_x): (Vec<_>, Vec<_>) = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+
+    let mut aggregate1 = PartialAggregate::new(H_x[0]);
+    aggregate1.add(partial_sigs[3].clone(), &vk_vec[3]).unwrap();
+    aggregate1.add(partial_sigs[0].clone(), &vk_vec[0]).unwrap();
+    let mut aggregate2 = PartialAggregate::new(H_x[0]);
+    aggregate2.add(partial_sigs[0].clone(), &vk_vec[0]).unwrap();
+    aggregate2.add(partial_sigs[3].clone(), &vk_vec[3]).unwrap();
+
+    // Root doesn't depend on the order partials were collected
+    assert_eq!(aggregate1.merkle_root(), aggregate2.merkle_root());
+    assert!(aggregate1.verify_subset_against(&H_x[0], &vk_vec).is_ok());
+
+    // Duplicated and invalid partials are rejected
+    assert_eq!(
+        aggregate1.add(partial_sigs[0].clone(), &vk_vec[0]),
+        Err(crate::Error::PartialSignatureDuplicate)
+    );
+    assert_eq!(
+        aggregate1.add(partial_sigs[1].clone(), &vk_vec[2]),
+        Err(crate::Error::PartialSignatureVerificationError)
+    );
+
+    aggregate1.add(partial_sigs[1].clone(), &vk_vec[1]).unwrap();
+    assert_ne!(aggregate1.merkle_root(), aggregate2.merkle_root());
+}
+
+#[test]
+fn test_partial_aggregate_merkle_proof() {
+    use crate::threshold_bls::party_i::PartialAggregate;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(3, 5);
+    let message = [100, 101, 102, 103];
+    let (partial_sigs, H_x): (Vec<_>, Vec<_>) = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+
+    let mut aggregate = PartialAggregate::new(H_x[0]);
+    assert_eq!(aggregate.merkle_root(), [0u8; 32]);
+    assert!(aggregate.merkle_proof(KeygenIndex(1)).is_none());
+
+    // Every tree size, including ones with unpaired nodes
+    for (partial, vk) in partial_sigs.iter().zip(&vk_vec) {
+        aggregate.add(partial.clone(), vk).unwrap();
+        let root = aggregate.merkle_root();
+        for included in aggregate.partials() {
+            let proof = aggregate.merkle_proof(included.index).unwrap();
+            assert!(proof.verify(&root, included));
+        }
+    }
+
+    let root = aggregate.merkle_root();
+    let proof = aggregate.merkle_proof(KeygenIndex(2)).unwrap();
+    assert!(!proof.verify(&root, &partial_sigs[2]));
+    assert!(!proof.verify(&[0u8; 32], &partial_sigs[1]));
+    let mut truncated = proof.clone();
+    truncated.siblings.pop();
+    assert!(!truncated.verify(&root, &partial_sigs[1]));
+}
+
+#[test]
+fn test_export_import_share() {
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(2, 4);
+
+    let (index, sk_i, params, vk) = shared_keys_vec[1].export_share();
+    let imported = SharedKeys::import_share(index, sk_i, params.clone(), vk, &vk_vec).unwrap();
+    assert_eq!(imported.index, shared_keys_vec[1].index);
+    assert_eq!(imported.sk_i, shared_keys_vec[1].sk_i);
+    assert_eq!(imported.vk, shared_keys_vec[1].vk);
+
+    // Share of another party doesn't match declared index
+    let (_, other_sk, _, _) = shared_keys_vec[2].export_share();
+    assert_eq!(
+        SharedKeys::import_share(index, other_sk, params.clone(), vk, &vk_vec).err(),
+        Some(crate::Error::ImportedShareMismatch)
+    );
+
+    // vk_vec doesn't match group public key
+    let (_, other_vk_vec) = keygen_t_n_parties(2, 4);
+    assert_eq!(
+        SharedKeys::import_share(index, sk_i, params.clone(), vk, &other_vk_vec).err(),
+        Some(crate::Error::ImportedShareMismatch)
+    );
+
+    // Key of another party beyond the first t+1 is forged
+    let mut forged_vk_vec = vk_vec.clone();
+    forged_vk_vec[3] = vk_vec[0];
+    assert_eq!(
+        SharedKeys::import_share(index, sk_i, params, vk, &forged_vk_vec).err(),
+        Some(crate::Error::ImportedShareMismatch)
+    );
+}
+
+#[test]
+fn test_shared_keys_compact_bytes() {
+    use crate::Error;
+
+    let (shared_keys_vec, _) = keygen_t_n_parties(1, 3);
+    let keys = &shared_keys_vec[2];
+    let bytes = keys.to_bytes_compact();
+    assert_eq!(bytes.len(), SharedKeys::COMPACT_BYTES_LEN);
+
+    let parsed = SharedKeys::from_bytes_compact(&bytes, &keys.params).unwrap();
+    assert_eq!(parsed.index, keys.index);
+    assert_eq!(parsed.vk, keys.vk);
+    assert_eq!(parsed.sk_i, keys.sk_i);
+    assert_eq!(parsed.params.threshold, keys.params.threshold);
+    assert_eq!(parsed.params.share_count, keys.params.share_count);
+
+    // Index is out of range of params
+    let params = ShamirSecretSharing {
+        threshold: 1,
+        share_count: 2,
+    };
+    assert_eq!(
+        SharedKeys::from_bytes_compact(&bytes, &params).err(),
+        Some(Error::ImportedShareMismatch)
+    );
+    // Truncated encoding
+    assert_eq!(
+        SharedKeys::from_bytes_compact(&bytes[1..], &keys.params).err(),
+        Some(Error::Parse(crate::ParseError::InvalidLength))
+    );
+}
+
+#[test]
+fn signature_converts_to_bls_signature() {
+    use crate::threshold_bls::party_i::Signature;
+
+    let message = [1, 2, 3, 4];
+    let sig: BLSSignature = sign(&message[..], 1, 3, &[0, 2], None);
+    let wrapped = Signature::from(sig);
+    assert_eq!(wrapped.to_bytes(true), sig.to_bytes(true));
+    assert_eq!(wrapped.to_bytes(false), sig.to_bytes(false));
+
+    let json = serde_json::to_string(&wrapped).unwrap();
+    let parsed: Signature = serde_json::from_str(&json).unwrap();
+    assert_eq!(BLSSignature::from(parsed), sig);
+}
+
+#[test]
+fn test_verify_partial_sigs_batch() {
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let messages: [&[u8]; 3] = [b"first", b"second", b"third"];
+
+    let mut items: Vec<_> = shared_keys_vec
+        .iter()
+        .zip(&messages)
+        .zip(&vk_vec)
+        .map(|((k, msg), vk_i)| {
+            let (partial_sig, H_x) = k.partial_sign(msg);
+            (H_x, partial_sig, *vk_i)
+        })
+        .collect();
+    assert_eq!(SharedKeys::verify_partial_sigs_batch(&items), Ok(()));
+
+    // Errors cancelling out in the plain sum of signatures are caught by random coefficients
+    let mut tampered = items.clone();
+    let delta = GE1::generator();
+    tampered[0].1.sigma_i = tampered[0].1.sigma_i + delta;
+    tampered[1].1.sigma_i = tampered[1].1.sigma_i.sub_point(&delta.get_element());
+    assert_eq!(
+        SharedKeys::verify_partial_sigs_batch(&tampered),
+        Err(vec![0, 1])
+    );
+
+    // Partial signature is checked against message of another item
+    items[2].0 = items[0].0;
+    assert_eq!(SharedKeys::verify_partial_sigs_batch(&items), Err(vec![2]));
+    assert_eq!(SharedKeys::verify_partial_sigs_batch(&[]), Ok(()));
+}
+
 pub fn keygen_t_n_parties(t: usize, n: usize) -> (Vec<SharedKeys>, Vec<GE2>) {
     let parames = ShamirSecretSharing {
         threshold: t,
@@ -126,6 +415,86 @@ pub fn keygen_t_n_parties(t: usize, n: usize) -> (Vec<SharedKeys>, Vec<GE2>) {
     (shared_keys_vec, vk_vec)
 }
 
+pub fn keygen_weighted(t: usize, weights: &[u16]) -> (Vec<WeightedSharedKeys>, Vec<GE2>) {
+    let n = weights.len();
+    let params = ShamirSecretSharing {
+        threshold: t,
+        share_count: weights.iter().map(|&w| usize::from(w)).sum(),
+    };
+    let party_keys_vec = (0..n).map(Keys::phase1_create).collect::<Vec<Keys>>();
+
+    let (bc1_vec, decom_vec): (Vec<_>, Vec<_>) =
+        party_keys_vec.iter().map(|k| k.phase1_broadcast()).unzip();
+    let y_vec = decom_vec.iter().map(|d| d.y_i).collect::<Vec<GE2>>();
+
+    let (vss_scheme_vec, dealt_shares_vec): (Vec<_>, Vec<_>) = party_keys_vec
+        .iter()
+        .map(|k| {
+            let (vss_scheme, dealt_shares, _) = k
+                .phase1_verify_com_phase2_distribute_weighted(
+                    &params, weights, &decom_vec, &bc1_vec,
+                )
+                .expect("");
+            (vss_scheme, dealt_shares)
+        })
+        .unzip();
+
+    let mut weighted_keys_vec = Vec::new();
+    let mut dlog_proof_vec = Vec::new();
+    for (i, key) in party_keys_vec.iter().enumerate() {
+        let party_shares = dealt_shares_vec
+            .iter()
+            .map(|dealt| dealt[i].clone())
+            .collect::<Vec<_>>();
+        let (weighted_keys, dlog_proofs) = key
+            .phase2_verify_vss_construct_keypairs_prove_dlog_weighted(
+                &params,
+                weights,
+                &y_vec,
+                &party_shares,
+                &vss_scheme_vec,
+            )
+            .expect("");
+        weighted_keys_vec.push(weighted_keys);
+        dlog_proof_vec.extend(dlog_proofs);
+    }
+
+    Keys::verify_dlog_proofs(&params, &dlog_proof_vec).expect("");
+    let vk_vec = dlog_proof_vec.iter().map(|p| p.pk).collect::<Vec<GE2>>();
+
+    (weighted_keys_vec, vk_vec)
+}
+
+#[test]
+fn test_sign_weighted() {
+    // Total weight is 6, any parties of total weight 4 can sign
+    let weights = [3, 1, 2];
+    let (weighted_keys_vec, vk_vec) = keygen_weighted(3, &weights);
+    assert_eq!(weighted_keys_vec[0].weight(), 3);
+    assert_eq!(vk_vec.len(), 6);
+
+    let message = [100, 101, 102, 103];
+    let signers = &weighted_keys_vec[0..2];
+    let (partial_sigs, H_x): (Vec<_>, Vec<_>) =
+        signers.iter().map(|k| k.partial_sign(&message[..])).unzip();
+    let partial_sigs: Vec<_> = partial_sigs.into_iter().flatten().collect();
+    let s: Vec<_> = partial_sigs.iter().map(|p| p.index).collect();
+    let vk_participating: Vec<_> = s.iter().map(|k| vk_vec[usize::from(k.0) - 1]).collect();
+
+    let sig = signers[0].shares[0]
+        .combine(&vk_participating, &partial_sigs, H_x[0], &s)
+        .unwrap();
+    assert!(signers[0].shares[0].verify(&sig, &message[..]));
+
+    // Party of weight 3 can't sign alone
+    let (partial_sigs, H_x) = weighted_keys_vec[0].partial_sign(&message[..]);
+    let s: Vec<_> = partial_sigs.iter().map(|p| p.index).collect();
+    let vk_participating: Vec<_> = s.iter().map(|k| vk_vec[usize::from(k.0) - 1]).collect();
+    assert!(weighted_keys_vec[0].shares[0]
+        .combine(&vk_participating, &partial_sigs, H_x, &s)
+        .is_err());
+}
+
 pub fn sign(
     message: &[u8],
     t: usize,
@@ -152,6 +521,7 @@ pub fn sign(
         .unzip();
 
     // each party runs Combine and out output the signature
+    let indexes: Vec<_> = partial_sign_vec.iter().map(|p| p.index).collect();
     let bls_sig_vec = shared_keys_participating_parties
         .iter()
         .map(|k| {
@@ -159,7 +529,7 @@ pub fn sign(
                 &vk_participating_parties[..],
                 &partial_sign_vec[..],
                 H_x[0],
-                s,
+                &indexes,
             )
             .expect("")
         })
@@ -208,6 +578,35 @@ fn another_bls_impl_validates_signature() {
     assert!(valid);
 }
 
+#[cfg(test)]
+#[test]
+fn blst_validates_signature() {
+    use blst::min_sig::{PublicKey, Signature};
+    use blst::BLST_ERROR;
+    use pairing_plus::bls12_381::G2Affine;
+    use pairing_plus::serdes::SerDes;
+
+    // Run keygen
+    let keygen = keygen_t_n_parties(2, 4);
+    let public_key = keygen.0[0].vk.clone();
+    let mut public_key_bytes = vec![];
+    G2Affine::serialize(&public_key.get_element(), &mut public_key_bytes, true)
+        .expect("serialize to vec should always succeed");
+
+    // Sign message
+    let message = b"KZen";
+    let signature = sign(&message[..], 2, 4, &[0, 2, 3], Some(keygen)).to_bytes(true);
+
+    // Parse public key & signature
+    let public_key = PublicKey::from_bytes(&public_key_bytes).expect("deserialize public key");
+    let signature = Signature::from_bytes(&signature).expect("deserialize signature");
+
+    // Verify signature
+    let dst = &[1u8];
+    let result = signature.verify(true, message, dst, &[], &public_key, true);
+    assert_eq!(result, BLST_ERROR::BLST_SUCCESS);
+}
+
 #[cfg(test)]
 #[test]
 fn we_recognize_signatures_generated_by_ref_impl() {
@@ -236,3 +635,516 @@ fn we_recognize_signatures_generated_by_ref_impl() {
     let valid = signature.verify(message, &public_key);
     assert!(valid);
 }
+
+#[test]
+fn aggregate_signatures_of_independent_groups() {
+    use crate::threshold_bls::{aggregate_signatures, verify_aggregate};
+
+    let group1 = keygen_t_n_parties(1, 3);
+    let group2 = keygen_t_n_parties(2, 4);
+    let group_keys = vec![group1.0[0].vk, group2.0[0].vk];
+
+    let msg1 = b"group 1 message";
+    let msg2 = b"group 2 message";
+    let sig1 = sign(&msg1[..], 1, 3, &[0, 2], Some(group1));
+    let sig2 = sign(&msg2[..], 2, 4, &[1, 2, 3], Some(group2));
+
+    let agg = aggregate_signatures(&[sig1, sig2]);
+    assert!(verify_aggregate(&group_keys, &[&msg1[..], &msg2[..]], &agg));
+    assert!(!verify_aggregate(
+        &group_keys,
+        &[&msg2[..], &msg1[..]],
+        &agg
+    ));
+    assert!(!verify_aggregate(&group_keys[..1], &[&msg1[..]], &agg));
+}
+
+#[test]
+fn detect_equivocation_of_party() {
+    use crate::threshold_bls::detect_equivocation;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let (partial_a, _) = shared_keys_vec[1].partial_sign(b"block 1");
+    let (partial_b, _) = shared_keys_vec[1].partial_sign(b"block 1'");
+
+    let proof = detect_equivocation(&partial_a, b"block 1", &partial_b, b"block 1'", &vk_vec)
+        .expect("party equivocated");
+    assert!(proof.verify(&vk_vec));
+
+    // Same message is not an equivocation
+    assert!(detect_equivocation(&partial_a, b"block 1", &partial_a, b"block 1", &vk_vec).is_none());
+    // Partial signatures must be valid under verification key of their index
+    assert!(
+        detect_equivocation(&partial_a, b"block 1'", &partial_b, b"block 1", &vk_vec).is_none()
+    );
+
+    // Proof doesn't verify under keys of another group, even if it contains key of the party
+    let (_, another_vk_vec) = keygen_t_n_parties(1, 3);
+    let mut forged_vk_vec = another_vk_vec.clone();
+    forged_vk_vec[0] = vk_vec[1];
+    assert!(!proof.verify(&another_vk_vec));
+    assert!(!proof.verify(&forged_vk_vec));
+    assert!(!proof.verify(&vk_vec[..1]));
+}
+
+#[test]
+fn partial_signature_from_external_signer() {
+    use crate::basic_bls::hash_to_g1;
+    use crate::threshold_bls::party_i::PartialSignature;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+
+    // Party 0 signs with exported share, party 2 is an external signer
+    let (_, sk_i, _, _) = shared_keys_vec[0].export_share();
+    let index = shared_keys_vec[0].keygen_index();
+    let (partial0, H_x) = PartialSignature::new(index, &sk_i, &message[..]);
+    let (external, _) = shared_keys_vec[2].partial_sign(&message[..]);
+    let partial2 =
+        PartialSignature::from_parts(external.index, external.sigma_i, external.ddh_proof);
+    assert_eq!(H_x, hash_to_g1(&message[..]));
+    assert!(partial2.verify(&H_x, &vk_vec[2]));
+    assert!(!partial2.verify(&H_x, &vk_vec[0]));
+
+    let sig = shared_keys_vec[1]
+        .combine(
+            &[vk_vec[0], vk_vec[2]],
+            &[partial0, partial2],
+            H_x,
+            &[KeygenIndex(1), KeygenIndex(3)],
+        )
+        .unwrap();
+    assert!(shared_keys_vec[1].verify(&sig, &message[..]));
+}
+
+#[test]
+fn partial_signature_from_signing_oracle() {
+    use std::cell::Cell;
+
+    use crate::threshold_bls::party_i::{PartialSignature, PartialSigner};
+    use crate::threshold_bls::utilities::ECDDHProof;
+
+    // Mimics HSM: share is never exposed, only signing requests are served
+    struct Oracle {
+        share: SharedKeys,
+        requests: Cell<usize>,
+    }
+    impl PartialSigner for Oracle {
+        fn index(&self) -> KeygenIndex {
+            self.share.keygen_index()
+        }
+        fn sign_point(&self, H_x: &GE1) -> (GE1, ECDDHProof) {
+            self.requests.set(self.requests.get() + 1);
+            self.share.sign_point(H_x)
+        }
+    }
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+
+    let oracle = Oracle {
+        share: shared_keys_vec[2].clone(),
+        requests: Cell::new(0),
+    };
+    let (partial2, H_x) = PartialSignature::sign(&oracle, &message[..]);
+    assert_eq!(oracle.requests.get(), 1);
+    assert_eq!(partial2.index, KeygenIndex(3));
+    assert!(partial2.verify(&H_x, &vk_vec[2]));
+
+    let (partial0, _) = shared_keys_vec[0].partial_sign(&message[..]);
+    let sig = shared_keys_vec[1]
+        .combine(
+            &[vk_vec[0], vk_vec[2]],
+            &[partial0, partial2],
+            H_x,
+            &[KeygenIndex(1), KeygenIndex(3)],
+        )
+        .unwrap();
+    assert!(shared_keys_vec[1].verify(&sig, &message[..]));
+}
+
+#[test]
+fn partial_signature_bytes_roundtrip() {
+    use crate::threshold_bls::party_i::PartialSignature;
+    use crate::ParseError;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let (partial, H_x) = shared_keys_vec[2].partial_sign(&[1, 2, 3][..]);
+
+    let bytes = partial.to_bytes().unwrap();
+    assert_eq!(bytes.len(), PartialSignature::BYTES_LEN);
+    let parsed = PartialSignature::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed, partial);
+    assert!(parsed.verify(&H_x, &vk_vec[2]));
+
+    assert_eq!(
+        PartialSignature::from_bytes(&bytes[1..]),
+        Err(ParseError::InvalidLength)
+    );
+}
+
+#[test]
+fn threshold_property_doesnt_reveal_signers() {
+    use crate::basic_bls::hash_to_g1;
+    use crate::threshold_bls::party_i::lagrange_coefficient_at;
+    use crate::threshold_bls::verify_threshold_property;
+    use curv::elliptic::curves::bls12_381::g1::FE as FE1;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(2, 4);
+    let params = shared_keys_vec[0].params.clone();
+    let message = [1, 2, 3, 4];
+    let H_x = hash_to_g1(&message[..]);
+    let partials: Vec<_> = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]).0)
+        .collect();
+
+    // Any quorum of t+1 parties produces exactly the same signature
+    let combine = |s: &[usize]| {
+        let vks: Vec<_> = s.iter().map(|&i| vk_vec[i]).collect();
+        let sigs: Vec<_> = s.iter().map(|&i| partials[i].clone()).collect();
+        let indexes: Vec<_> = sigs.iter().map(|p| p.index).collect();
+        shared_keys_vec[0]
+            .combine(&vks, &sigs, H_x, &indexes)
+            .unwrap()
+    };
+    let sig_a = combine(&[0, 1, 2]);
+    let sig_b = combine(&[1, 2, 3]);
+    assert_eq!(sig_a, sig_b);
+    assert!(verify_threshold_property(
+        &vk_vec,
+        &params,
+        &message[..],
+        &sig_a
+    ));
+    assert!(!verify_threshold_property(
+        &vk_vec[..3],
+        &params,
+        &message[..],
+        &sig_a
+    ));
+    let mut tampered = vk_vec.clone();
+    tampered[3] = tampered[0];
+    assert!(!verify_threshold_property(
+        &tampered,
+        &params,
+        &message[..],
+        &sig_a
+    ));
+
+    // t parties can't produce valid signature
+    let s = [KeygenIndex(1), KeygenIndex(2)];
+    let zero: FE1 = ECScalar::zero();
+    let term = |i: usize| partials[i].sigma_i * &lagrange_coefficient_at(&zero, s[i], &s);
+    let sigma = term(0) + term(1);
+    let forged = BLSSignature { sigma };
+    assert!(!verify_threshold_property(
+        &vk_vec,
+        &params,
+        &message[..],
+        &forged
+    ));
+}
+
+#[test]
+fn group_public_key_from_verification_keys() {
+    use crate::threshold_bls::group_public_key;
+    use crate::Error;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(2, 5);
+    let params = shared_keys_vec[0].params.clone();
+    assert_eq!(
+        group_public_key(&vk_vec, &params),
+        Ok(shared_keys_vec[0].vk)
+    );
+
+    let mut tampered = vk_vec.clone();
+    tampered[1] = tampered[0];
+    assert_ne!(
+        group_public_key(&tampered, &params),
+        Ok(shared_keys_vec[0].vk)
+    );
+
+    // Keys beyond the first t+1 aren't used for interpolation, but they're checked as well
+    let mut tampered = vk_vec.clone();
+    tampered[4] = tampered[0];
+    assert_eq!(
+        group_public_key(&tampered, &params),
+        Err(Error::InconsistentVerificationKeys)
+    );
+    assert_eq!(
+        group_public_key(&vk_vec[..4], &params),
+        Err(Error::InconsistentVerificationKeys)
+    );
+}
+
+fn keygen_transcript(t: usize, n: usize) -> (KeygenTranscript, Vec<SharedKeys>) {
+    let params = ShamirSecretSharing {
+        threshold: t,
+        share_count: n,
+    };
+    let party_keys_vec = (0..n).map(Keys::phase1_create).collect::<Vec<Keys>>();
+    let (commitments, decommitments): (Vec<_>, Vec<_>) =
+        party_keys_vec.iter().map(|k| k.phase1_broadcast()).unzip();
+    let y_vec = decommitments.iter().map(|d| d.y_i).collect::<Vec<GE2>>();
+
+    let (vss_schemes, secret_shares_vec): (Vec<_>, Vec<_>) = party_keys_vec
+        .iter()
+        .map(|k| {
+            let (vss, shares, _) = k
+                .phase1_verify_com_phase2_distribute(&params, &decommitments, &commitments)
+                .unwrap();
+            (vss, shares)
+        })
+        .unzip();
+
+    let (shared_keys_vec, dlog_proofs): (Vec<_>, Vec<_>) = party_keys_vec
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            let party_shares = secret_shares_vec.iter().map(|s| s[i]).collect::<Vec<FE>>();
+            k.phase2_verify_vss_construct_keypair_prove_dlog(
+                &params,
+                &y_vec,
+                &party_shares,
+                &vss_schemes,
+                &(i + 1),
+            )
+            .unwrap()
+        })
+        .unzip();
+
+    let transcript = KeygenTranscript {
+        params,
+        commitments,
+        decommitments,
+        vss_schemes,
+        dlog_proofs,
+    };
+    (transcript, shared_keys_vec)
+}
+
+#[test]
+fn invalid_vss_share_is_rejected_and_found() {
+    use crate::threshold_bls::party_i::find_invalid_shares;
+    use crate::Error;
+
+    let params = ShamirSecretSharing {
+        threshold: 2,
+        share_count: 4,
+    };
+    let party_keys_vec = (0..4).map(Keys::phase1_create).collect::<Vec<Keys>>();
+    let (commitments, decommitments): (Vec<_>, Vec<_>) =
+        party_keys_vec.iter().map(|k| k.phase1_broadcast()).unzip();
+    let y_vec = decommitments.iter().map(|d| d.y_i).collect::<Vec<GE2>>();
+    let (vss_schemes, mut shares): (Vec<_>, Vec<_>) = party_keys_vec
+        .iter()
+        .map(|k| {
+            let (vss, shares, _) = k
+                .phase1_verify_com_phase2_distribute(&params, &decommitments, &commitments)
+                .unwrap();
+            (vss, shares[0])
+        })
+        .unzip();
+    assert!(find_invalid_shares(&shares, &vss_schemes, 1).is_empty());
+
+    let noise: FE = ECScalar::new_random();
+    shares[2] = shares[2] + &noise;
+    let result = party_keys_vec[0].phase2_verify_vss_construct_keypair_prove_dlog(
+        &params,
+        &y_vec,
+        &shares,
+        &vss_schemes,
+        &1,
+    );
+    assert_eq!(result.err(), Some(Error::KeyGenInvalidShare));
+    assert_eq!(find_invalid_shares(&shares, &vss_schemes, 1), vec![2]);
+}
+
+#[test]
+fn dlog_proofs_batch_verification() {
+    use crate::threshold_bls::party_i::find_invalid_dlog_proofs;
+    use crate::Error;
+    use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
+
+    let params = ShamirSecretSharing {
+        threshold: 2,
+        share_count: 5,
+    };
+    let mut proofs: Vec<_> = (0..5)
+        .map(|_| DLogProof::prove(&FE::new_random()))
+        .collect();
+    assert_eq!(Keys::verify_dlog_proofs_batched(&params, &proofs), Ok(()));
+    assert!(find_invalid_dlog_proofs(&proofs).is_empty());
+
+    proofs[3].pk = proofs[1].pk;
+    assert_eq!(
+        Keys::verify_dlog_proofs_batched(&params, &proofs),
+        Err(Error::KeyGenDlogProofError)
+    );
+    assert_eq!(find_invalid_dlog_proofs(&proofs), vec![3]);
+    assert_eq!(
+        Keys::verify_dlog_proofs_batched(&params, &proofs[1..]),
+        Err(Error::KeyGenMisMatchedVectors)
+    );
+}
+
+#[test]
+fn dlog_proofs_batch_verification_of_empty_slice() {
+    let params = ShamirSecretSharing {
+        threshold: 0,
+        share_count: 0,
+    };
+    assert_eq!(Keys::verify_dlog_proofs_batched(&params, &[]), Ok(()));
+}
+
+#[test]
+fn audit_honest_keygen() {
+    let (transcript, shared_keys_vec) = keygen_transcript(2, 4);
+    assert_eq!(audit_keygen(&transcript), Ok(shared_keys_vec[0].vk));
+}
+
+#[test]
+fn public_share_from_vss_commitments() {
+    use crate::threshold_bls::public_share;
+
+    let (transcript, shared_keys_vec) = keygen_transcript(2, 4);
+    let message = [1, 2, 3];
+    for shared_keys in &shared_keys_vec {
+        let vk_i = public_share(&transcript.vss_schemes, shared_keys.keygen_index());
+        assert_eq!(vk_i, GE2::generator() * &shared_keys.sk_i);
+
+        let (partial, _) = shared_keys.partial_sign(&message[..]);
+        assert!(SharedKeys::verify_partial_sig_from_message(
+            &message[..],
+            &partial,
+            &vk_i
+        ));
+    }
+}
+
+#[test]
+fn audit_detects_dishonest_keygen() {
+    let (transcript, _) = keygen_transcript(1, 3);
+
+    let mut bad_commitment = transcript.clone();
+    bad_commitment.decommitments[1].y_i = bad_commitment.decommitments[0].y_i;
+    assert_eq!(
+        audit_keygen(&bad_commitment),
+        Err(AuditError::BadCommitment { party: 2 })
+    );
+
+    let mut bad_vss = transcript.clone();
+    bad_vss.vss_schemes.swap(0, 2);
+    assert_eq!(
+        audit_keygen(&bad_vss),
+        Err(AuditError::InconsistentVss { party: 1 })
+    );
+
+    let mut bad_proof = transcript.clone();
+    bad_proof.dlog_proofs.swap(0, 1);
+    assert_eq!(
+        audit_keygen(&bad_proof),
+        Err(AuditError::InvalidDLogProof { party: 1 })
+    );
+
+    let mut truncated = transcript;
+    truncated.dlog_proofs.pop();
+    assert_eq!(audit_keygen(&truncated), Err(AuditError::MismatchedLengths));
+}
+
+#[test]
+fn partial_signature_matches_basic_keypair_signature() {
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+
+    let keypair = shared_keys_vec[1].as_basic_keypair();
+    let sig = BLSSignature::sign(&message[..], &keypair);
+    let (partial, _) = shared_keys_vec[1].partial_sign(&message[..]);
+    assert_eq!(sig.sigma, partial.sigma_i);
+    assert!(sig.verify(&message[..], &vk_vec[1]));
+}
+
+#[test]
+fn verify_partial_sig_from_raw_message() {
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 3);
+    let message = [100, 101, 102, 103];
+
+    let (partial, _) = shared_keys_vec[0].partial_sign(&message[..]);
+    assert!(SharedKeys::verify_partial_sig_from_message(
+        &message[..],
+        &partial,
+        &vk_vec[0]
+    ));
+    assert!(!SharedKeys::verify_partial_sig_from_message(
+        b"another message",
+        &partial,
+        &vk_vec[0]
+    ));
+    assert!(!SharedKeys::verify_partial_sig_from_message(
+        &message[..],
+        &partial,
+        &vk_vec[1]
+    ));
+}
+
+#[test]
+fn combine_at_custom_evaluation_point() {
+    use curv::elliptic::curves::bls12_381::g1::FE as FE1;
+
+    let (shared_keys_vec, vk_vec) = keygen_t_n_parties(1, 4);
+    let message = [100, 101, 102, 103];
+    let (partials, H_x): (Vec<_>, Vec<_>) = shared_keys_vec
+        .iter()
+        .map(|k| k.partial_sign(&message[..]))
+        .unzip();
+    let keys = &shared_keys_vec[0];
+
+    // Default evaluation point gives ordinary signature
+    let zero: FE1 = ECScalar::zero();
+    let sig = keys
+        .combine_at(
+            &zero,
+            &vk_vec[..2],
+            &partials[..2],
+            H_x[0],
+            &[KeygenIndex(1), KeygenIndex(2)],
+        )
+        .unwrap();
+    assert!(keys.verify(&sig, &message[..]));
+
+    // Interpolating at evaluation point of party 3 gives its partial signature
+    let point: FE1 = ECScalar::from(&curv::BigInt::from(3u32));
+    let sig = keys
+        .combine_at(
+            &point,
+            &vk_vec[..2],
+            &partials[..2],
+            H_x[0],
+            &[KeygenIndex(1), KeygenIndex(2)],
+        )
+        .unwrap();
+    assert_eq!(sig.sigma, partials[2].sigma_i);
+
+    // Result doesn't depend on signer set
+    let point: FE1 = ECScalar::from(&curv::BigInt::from(42u32));
+    let sig1 = keys
+        .combine_at(
+            &point,
+            &vk_vec[..2],
+            &partials[..2],
+            H_x[0],
+            &[KeygenIndex(1), KeygenIndex(2)],
+        )
+        .unwrap();
+    let sig2 = keys
+        .combine_at(
+            &point,
+            &vk_vec[2..],
+            &partials[2..],
+            H_x[0],
+            &[KeygenIndex(3), KeygenIndex(4)],
+        )
+        .unwrap();
+    assert_eq!(sig1, sig2);
+}
@@ -1,3 +1,4 @@
+use curv::arithmetic::traits::*;
 use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
 use curv::cryptographic_primitives::hashing::traits::Hash;
 use curv::elliptic::curves::bls12_381::g1::FE as FE1;
@@ -7,9 +8,14 @@ use curv::elliptic::curves::bls12_381::g2::GE as GE2;
 use curv::elliptic::curves::traits::ECPoint;
 use curv::elliptic::curves::traits::ECScalar;
 use curv::BigInt;
+use pairing_plus::bls12_381::{G1Affine, G2Affine};
+use pairing_plus::serdes::SerDes;
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
+use crate::basic_bls::{g1_from_bytes, g2_from_bytes};
+use crate::ParseError;
+
 /// NIZK required for our threshold BLS:
 /// This is a special case of the ec ddh proof from Curv:
 /// [https://github.com/ZenGo-X/curv/blob/master/src/cryptographic_primitives/proofs/sigma_ec_ddh.rs]
@@ -54,13 +60,21 @@ impl ECDDHProof {
             &a1.bytes_compressed_to_big_int(),
             &a2.bytes_compressed_to_big_int(),
         ]);
-        let z = s + e * &w.x;
+        let q = FE1::q();
+        let z = BigInt::mod_add(&s, &BigInt::mod_mul(&e, &w.x, &q), &q);
         s1.zeroize();
         s2.zeroize();
         ECDDHProof { a1, a2, z }
     }
 
+    /// Verifies the proof
+    ///
+    /// `z` must be reduced modulo the group order, otherwise the proof is rejected: accepting
+    /// `z + k*q` would make the proof malleable.
     pub fn verify(&self, delta: &ECDDHStatement) -> bool {
+        if self.z < BigInt::zero() || self.z >= FE1::q() {
+            return false;
+        }
         let e = HSha256::create_hash(&[
             &delta.g1.bytes_compressed_to_big_int(),
             &delta.h1.bytes_compressed_to_big_int(),
@@ -76,6 +90,47 @@ impl ECDDHProof {
         let a2_plus_e_h2 = &self.a2 + &(&delta.h2 * &ECScalar::from(&e));
         z_g1 == a1_plus_e_h1 && z_g2 == a2_plus_e_h2
     }
+
+    /// Size of proof serialized via [to_bytes](Self::to_bytes)
+    pub const BYTES_LEN: usize = 48 + 96 + 32;
+
+    /// Serializes proof as compressed `a1` (48 bytes), compressed `a2` (96 bytes), and `z`
+    /// (32 bytes big-endian)
+    ///
+    /// Much more compact than serde encoding. Returns [ParseError::InvalidEncoding] if `z` is
+    /// negative or doesn't fit into 32 bytes, which never happens for proofs produced by
+    /// [prove](Self::prove), but may happen for proofs received from the network.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ParseError> {
+        let z = BigInt::to_vec(&self.z);
+        if self.z < BigInt::zero() || z.len() > 32 {
+            return Err(ParseError::InvalidEncoding);
+        }
+        let mut bytes = Vec::with_capacity(Self::BYTES_LEN);
+        G1Affine::serialize(&self.a1.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        G2Affine::serialize(&self.a2.get_element(), &mut bytes, true)
+            .expect("serialize to vec should always succeed");
+        bytes.resize(Self::BYTES_LEN - z.len(), 0);
+        bytes.extend_from_slice(&z);
+        Ok(bytes)
+    }
+
+    /// Parses proof serialized via [to_bytes](Self::to_bytes)
+    ///
+    /// Points are checked to be on curve and in the prime order subgroup, and `z` is checked
+    /// to be reduced modulo the group order.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != Self::BYTES_LEN {
+            return Err(ParseError::InvalidLength);
+        }
+        let a1 = g1_from_bytes(&bytes[..48])?;
+        let a2 = g2_from_bytes(&bytes[48..144])?;
+        let z = BigInt::from(&bytes[144..]);
+        if z >= FE1::q() {
+            return Err(ParseError::InvalidEncoding);
+        }
+        Ok(ECDDHProof { a1, a2, z })
+    }
 }
 
 #[cfg(test)]
@@ -99,6 +154,116 @@ mod tests {
         assert!(proof.verify(&delta));
     }
 
+    #[test]
+    fn test_ecddh_proof_rejects_out_of_range_z() {
+        let x = FE1::new_random().to_big_int();
+        let g1 = ECPoint::generator();
+        let g2 = ECPoint::base_point2();
+        let h1 = &g1 * &ECScalar::from(&x);
+        let h2 = &g2 * &ECScalar::from(&x);
+
+        let delta = ECDDHStatement { g1, h1, g2, h2 };
+        let w = ECDDHWitness { x };
+        let proof = ECDDHProof::prove(&w, &delta);
+
+        // z + q is congruent to z, but must be rejected anyway
+        let mut malleated = proof.clone();
+        malleated.z = &proof.z + &FE1::q();
+        assert!(!malleated.verify(&delta));
+
+        let mut negative = proof.clone();
+        negative.z = &proof.z - &FE1::q();
+        assert!(!negative.verify(&delta));
+    }
+
+    #[test]
+    fn test_ecddh_proof_bytes_roundtrip() {
+        let x = FE1::new_random().to_big_int();
+        let g1 = ECPoint::generator();
+        let g2 = ECPoint::base_point2();
+        let h1 = &g1 * &ECScalar::from(&x);
+        let h2 = &g2 * &ECScalar::from(&x);
+
+        let delta = ECDDHStatement { g1, h1, g2, h2 };
+        let proof = ECDDHProof::prove(&ECDDHWitness { x }, &delta);
+
+        let bytes = proof.to_bytes().unwrap();
+        assert_eq!(bytes.len(), ECDDHProof::BYTES_LEN);
+        let parsed = ECDDHProof::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, proof);
+        assert!(parsed.verify(&delta));
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<ECDDHProof>(&json).unwrap(), proof);
+    }
+
+    #[test]
+    fn test_ecddh_proof_golden_bytes() {
+        // Generators of G1 and G2 in compressed form, as defined by ZCash serialization format
+        const G1_GENERATOR: &str = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+        const G2_GENERATOR: &str = "93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8";
+
+        let proof = ECDDHProof {
+            a1: ECPoint::generator(),
+            a2: ECPoint::generator(),
+            z: BigInt::from(0x0102_0304u32),
+        };
+        let expected = format!(
+            "{}{}{}01020304",
+            G1_GENERATOR,
+            G2_GENERATOR,
+            "00".repeat(28)
+        );
+        assert_eq!(hex::encode(proof.to_bytes().unwrap()), expected);
+        assert_eq!(
+            ECDDHProof::from_bytes(&hex::decode(&expected).unwrap()),
+            Ok(proof.clone())
+        );
+
+        // Serde encoding is embedded into every partial signature sent over the network, so
+        // it's pinned as well: points are compressed, scalar is hex without leading zeroes
+        let expected_json = format!(
+            r#"{{"a1":{{"bytes_str":"{}"}},"a2":{{"bytes_str":"{}"}},"z":"1020304"}}"#,
+            G1_GENERATOR, G2_GENERATOR
+        );
+        assert_eq!(serde_json::to_string(&proof).unwrap(), expected_json);
+        assert_eq!(
+            serde_json::from_str::<ECDDHProof>(&expected_json).unwrap(),
+            proof
+        );
+    }
+
+    #[test]
+    fn test_ecddh_proof_to_bytes_rejects_oversized_z() {
+        let mut proof = ECDDHProof {
+            a1: ECPoint::generator(),
+            a2: ECPoint::generator(),
+            z: BigInt::one() << 256,
+        };
+        assert_eq!(proof.to_bytes(), Err(ParseError::InvalidEncoding));
+        proof.z = BigInt::zero() - BigInt::one();
+        assert_eq!(proof.to_bytes(), Err(ParseError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_ecddh_proof_from_bytes_rejects_unreduced_z() {
+        let proof = ECDDHProof {
+            a1: ECPoint::generator(),
+            a2: ECPoint::generator(),
+            z: BigInt::zero(),
+        };
+        let mut bytes = proof.to_bytes().unwrap();
+        bytes[144..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            ECDDHProof::from_bytes(&bytes),
+            Err(ParseError::InvalidEncoding)
+        );
+        assert_eq!(
+            ECDDHProof::from_bytes(&bytes[1..]),
+            Err(ParseError::InvalidLength)
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_bad_ecddh_proof() {
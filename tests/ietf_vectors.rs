@@ -0,0 +1,112 @@
+//! Conformance of basic BLS against [draft-irtf-cfrg-bls-signature-04]
+//!
+//! The crate implements the minimal-signature-size variant: signatures are in G1 and public
+//! keys are in G2. This is a differential test: expected values are computed at runtime by
+//! [blst], which implements the draft, rather than pinned. Secret keys are derived by the
+//! draft's `KeyGen` out of fixed IKMs, and messages are those of the draft's hash-to-curve
+//! vectors.
+//!
+//! The crate hashes messages with domain separation tag `[1]` instead of the ciphersuite's
+//! `BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_`, which is the only deviation from the draft:
+//! `CoreSign` matches the draft bit-for-bit with the crate's tag, and the ciphersuite is
+//! reproduced exactly once its tag is used.
+//!
+//! [draft-irtf-cfrg-bls-signature-04]: https://tools.ietf.org/html/draft-irtf-cfrg-bls-signature-04
+//! [blst]: https://github.com/supranational/blst
+
+use blst::min_sig::{PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::bls12_381::g1::FE as FE1;
+use curv::elliptic::curves::bls12_381::g2::FE as FE2;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use pairing_plus::bls12_381::G2Affine;
+use pairing_plus::serdes::SerDes;
+
+use bls::basic_bls::{g1_from_bytes, g2_from_bytes, hash_to_g1_with_dst, BLSSignature, KeyPairG2};
+
+/// Ciphersuite `BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_` (basic scheme, signatures in G1)
+const CIPHERSUITE_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+/// Domain separation tag used by the crate
+const CRATE_DST: &[u8] = &[1];
+
+const IKMS: &[[u8; 32]] = &[[0; 32], [0x42; 32], *b"KZen Networks multi-party BLS ~~"];
+
+fn messages() -> Vec<Vec<u8>> {
+    vec![
+        b"".to_vec(),
+        b"abc".to_vec(),
+        b"abcdef0123456789".to_vec(),
+        [&b"q128_"[..], &[b'q'; 128][..]].concat(),
+        [&b"a512_"[..], &[b'a'; 512][..]].concat(),
+    ]
+}
+
+/// Key pair and blst secret key derived by the draft's `KeyGen` from `ikm`
+fn keygen(ikm: &[u8]) -> (KeyPairG2, SecretKey) {
+    let sk = SecretKey::key_gen(ikm, &[]).expect("ikm is long enough");
+    let x: FE2 = ECScalar::from(&BigInt::from(&sk.to_bytes()[..]));
+    (KeyPairG2::from_secret_key(x), sk)
+}
+
+fn public_key_bytes(keypair: &KeyPairG2) -> Vec<u8> {
+    let mut bytes = vec![];
+    G2Affine::serialize(&keypair.public_key().get_element(), &mut bytes, true)
+        .expect("serialize to vec should always succeed");
+    bytes
+}
+
+#[test]
+fn public_keys_match_draft() {
+    for ikm in IKMS {
+        let (keypair, sk) = keygen(ikm);
+        let expected = sk.sk_to_pk().compress();
+        assert_eq!(public_key_bytes(&keypair), expected.to_vec());
+        assert_eq!(g2_from_bytes(&expected).unwrap(), keypair.public_key());
+    }
+}
+
+#[test]
+fn core_sign_matches_draft_with_crate_dst() {
+    for ikm in IKMS {
+        let (keypair, sk) = keygen(ikm);
+        for message in messages() {
+            let expected = sk.sign(&message, CRATE_DST, &[]).compress();
+            let signature = BLSSignature::sign(&message, &keypair);
+            assert_eq!(signature.to_bytes(true), expected.to_vec());
+            assert!(signature.verify(&message, &keypair.public_key()));
+        }
+    }
+}
+
+#[test]
+fn ciphersuite_is_reproduced_with_its_dst() {
+    for ikm in IKMS {
+        let (keypair, sk) = keygen(ikm);
+        let x: FE1 = ECScalar::from(&BigInt::from(&sk.to_bytes()[..]));
+        let public_key = PublicKey::from_bytes(&public_key_bytes(&keypair)).unwrap();
+        for message in messages() {
+            let expected = sk.sign(&message, CIPHERSUITE_DST, &[]).compress();
+
+            let hashed = hash_to_g1_with_dst(&message, CIPHERSUITE_DST);
+            let signature = BLSSignature { sigma: hashed * &x };
+            assert_eq!(signature.to_bytes(true), expected.to_vec());
+            assert!(signature.verify_point(&hashed, &keypair.public_key()));
+
+            // Signature of the ciphersuite verifies under blst and parses as ours
+            let parsed = Signature::from_bytes(&expected).unwrap();
+            assert_eq!(
+                parsed.verify(true, &message, CIPHERSUITE_DST, &[], &public_key, true),
+                BLST_ERROR::BLST_SUCCESS
+            );
+            assert_eq!(g1_from_bytes(&expected).unwrap(), signature.sigma);
+
+            // The only deviation: crate's default tag produces another signature
+            assert_ne!(
+                BLSSignature::sign(&message, &keypair).to_bytes(true),
+                expected.to_vec()
+            );
+        }
+    }
+}